@@ -112,12 +112,60 @@ where
   return nodes_by_level.nodes_by_level;
 }
 
+/// Same as [create_nodes_by_level] for a graph with many roots, every root starts at level 0.
+/// Roots that are already reached from a previous root keep the level of that traversal.
+pub fn create_nodes_by_level_from_roots<'a, T>(
+  graph: &'a Graph<T, i32, Directed>,
+  node_indexes: &[NodeIndex],
+) -> HashMap<i32, HashSet<&'a T>>
+where
+  T: Hash + Eq,
+{
+  let mut nodes_by_level = NodesByLevel::new();
+
+  for node_index in node_indexes {
+    if nodes_by_level.visited.insert(*node_index) {
+      nodes_by_level.fill_nodes_by_level(graph, *node_index, 0);
+    }
+  }
+
+  return nodes_by_level.nodes_by_level;
+}
+
+/// Level of every node reached by [create_nodes_by_level_from_roots], a node reached
+/// from many levels keeps the lowest one. Levels are shifted so the lowest level is 0.
+pub fn create_level_by_node_from_roots<'a, T>(
+  graph: &'a Graph<T, i32, Directed>,
+  node_indexes: &[NodeIndex],
+) -> HashMap<&'a T, i32>
+where
+  T: Hash + Eq,
+{
+  let mut level_by_node: HashMap<&'a T, i32> = Default::default();
+
+  for (level, nodes) in create_nodes_by_level_from_roots(graph, node_indexes) {
+    for node in nodes {
+      let node_level = level_by_node.entry(node).or_insert(level);
+
+      *node_level = level.min(*node_level);
+    }
+  }
+
+  let min_level: i32 = level_by_node.values().min().cloned().unwrap_or_default();
+
+  for level in level_by_node.values_mut() {
+    *level -= min_level;
+  }
+
+  return level_by_node;
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
   mod nodes_by_level {
     use super::*;
-    use lezeh_common::macros::hashmap_literal;
+    use crate::macros::hashmap_literal;
 
     #[test]
     fn test_empty_graph() {
@@ -246,4 +294,61 @@ mod test {
       assert_eq!(nodes_by_level, expected_levels);
     }
   }
+
+  mod nodes_by_level_from_roots {
+    use super::*;
+    use crate::macros::hashmap_literal;
+
+    #[test]
+    fn test_disconnected_and_reached_roots() {
+      let mut graph: Graph<(i32, &str), i32> = Graph::new();
+
+      // Same convention as above -> ({level}, {label}).
+      let a = graph.add_node((0, "a"));
+      let pa1 = graph.add_node((-1, "pa1"));
+      let b = graph.add_node((0, "b"));
+      let cb1 = graph.add_node((1, "cb1"));
+      let c = graph.add_node((0, "c"));
+
+      graph.extend_with_edges([(a, pa1), (cb1, b), (c, pa1)]);
+
+      let nodes_by_level = create_nodes_by_level_from_roots(&graph, &[a, b, c]);
+
+      let expected_levels: HashMap<i32, HashSet<&(i32, &str)>> = hashmap_literal! {
+        -1 => HashSet::from([&(-1, "pa1")]),
+        0 => HashSet::from([&(0, "a"), &(0, "b"), &(0, "c")]),
+        1 => HashSet::from([&(1, "cb1")]),
+      };
+
+      assert_eq!(nodes_by_level, expected_levels);
+    }
+  }
+
+  mod level_by_node_from_roots {
+    use super::*;
+    use crate::macros::hashmap_literal;
+
+    #[test]
+    fn it_should_keep_the_lowest_level_shifted_to_0() {
+      let mut graph: Graph<&str, i32> = Graph::new();
+
+      let a = graph.add_node("a");
+      let pa1 = graph.add_node("pa1");
+      let paa1 = graph.add_node("paa1");
+      let ca1 = graph.add_node("ca1");
+
+      graph.extend_with_edges([(a, pa1), (pa1, paa1), (ca1, a)]);
+
+      let level_by_node = create_level_by_node_from_roots(&graph, &[a]);
+
+      let expected_levels: HashMap<&&str, i32> = hashmap_literal! {
+        &"paa1" => 0,
+        &"pa1" => 1,
+        &"a" => 2,
+        &"ca1" => 3,
+      };
+
+      assert_eq!(level_by_node, expected_levels);
+    }
+  }
 }
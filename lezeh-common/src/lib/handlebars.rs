@@ -34,6 +34,26 @@ impl HandlebarsRenderer {
       .map_err(Error::new);
   }
 
+  /// Compiles the template once under the given name, a syntax error
+  /// surfaces here instead of when it's rendered.
+  pub fn register_template(&mut self, name: &str, template: &str) -> ResultAnyError<()> {
+    return self
+      .handlebars_client
+      .register_template_string(name, template)
+      .map_err(Error::new);
+  }
+
+  pub fn render_registered(
+    &self,
+    name: &str,
+    json_serializible: impl serde::Serialize,
+  ) -> ResultAnyError<String> {
+    return self
+      .handlebars_client
+      .render(name, &handlebars::to_json(json_serializible))
+      .map_err(Error::new);
+  }
+
   pub fn render_from_template_path(
     &self,
     // template_path: &str,
@@ -2,7 +2,7 @@
 ///  1 => 2,
 ///  3 => 4
 /// };
-#[allow(unused_macros)]
+#[macro_export]
 macro_rules! hashmap_literal {
   ($($key:expr => $value:expr),* $(,)?) => {{
     use std::collections::HashMap;
@@ -13,5 +13,4 @@ macro_rules! hashmap_literal {
   }}
 }
 
-#[allow(unused_imports)]
-pub(crate) use hashmap_literal;
+pub use crate::hashmap_literal;
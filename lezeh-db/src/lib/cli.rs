@@ -1,9 +1,15 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::convert::TryInto;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use anyhow::anyhow;
+use chrono::Utc;
 use clap::App as Cli;
 use clap::Arg;
 use clap::ArgMatches;
@@ -14,13 +20,41 @@ use petgraph::dot::{Config as GraphDotConfig, Dot as GraphDot};
 use petgraph::graph::NodeIndex;
 use slog::Logger;
 
-use crate::config::{Config, DbConnectionConfig};
+use crate::config::{CherryPickPresetConfig, Config, DbConnectionConfig, DbDriver};
+use crate::mysql::connection::*;
+use crate::mysql::db_metadata::MysqlDbMetadata;
+use crate::mysql::statement_executor::MysqlStatementExecutor;
+use crate::mysql::table_metadata::MysqlTableMetadata;
 use crate::psql;
 use crate::psql::connection::*;
+use crate::psql::connection_pool::PsqlConnectionPool;
 use crate::psql::db_metadata::DbMetadata;
+use crate::psql::db_metadata::DbMetadataImpl;
+use crate::psql::db_metadata_cache::CachedDbMetadata;
 use crate::psql::dto::{FromSqlSink, PsqlTable, PsqlTableIdentity, PsqlTableRow};
-use crate::psql::relation_fetcher::RowGraph;
-use crate::psql::table_metadata::TableMetadataImpl;
+use crate::psql::graph_html::GraphHtml;
+use crate::psql::graph_template::{CompiledGraphTemplate, GraphTemplate};
+use crate::psql::insert_script::{InsertScript, InsertScriptConfig, InsertScriptHeader};
+use crate::psql::integrity_check::{CheckedRelation, IntegrityCheck};
+use crate::psql::load_order::LoadOrder;
+use crate::psql::relation_fetcher::{
+  ChildRowLimitConfig, ChildRowOrder, RelationFetcher, RowGraph,
+};
+use crate::psql::relation_insert::{InsertStatementConfig, RelationInsert};
+use crate::psql::relation_path::RelationPathFinder;
+use crate::psql::root_values::RootValues;
+use crate::psql::schema_diff::SchemaDiff;
+use crate::psql::seed::{SeedConfig, SeedRowGenerator};
+use crate::psql::statement_apply::{ApplyStatement, StatementApply};
+use crate::psql::statement_executor::{PsqlStatementExecutor, StatementExecutor};
+use crate::psql::table_ddl::TableDdl;
+use crate::psql::table_description::TableDescription;
+use crate::psql::table_metadata::{
+  PooledTableMetadata, RowFilter, TableMetadata, TableMetadataImpl,
+};
+use crate::psql::table_metadata_snapshot::{
+  RecordingTableMetadata, ReplayTableMetadata, TableMetadataSnapshot,
+};
 
 pub mod built_info {
   include!(concat!(env!("OUT_DIR"), "/built.rs"));
@@ -28,16 +62,38 @@ pub mod built_info {
 
 pub struct DbCli {}
 
+/// Driver specific accessors that share 1 connection
+type DbAccess = (
+  Box<dyn TableMetadata>,
+  Box<dyn DbMetadata>,
+  Box<dyn StatementExecutor>,
+);
+
+/// Rows can be reached through many paths in a dense graph
+const MAX_EXPLAINED_PATHS: usize = 10;
+
+/// Values from `--values-from` could be thousands of ids
+const MAX_DESCRIBED_ROOT_VALUES: usize = 10;
+
+const CHERRY_PICK_OUTPUT_FORMATS: [&str; 4] = ["insert-statement", "copy", "graphviz", "html"];
+
 enum CherryPickOutputFormatEnum {
   InsertStatement,
+  Copy,
   Graphviz,
+  Html,
+
+  /// Handlebars template file given by `--template`
+  Template(PathBuf),
 }
 
 impl From<&str> for CherryPickOutputFormatEnum {
   fn from(s: &str) -> Self {
     match s.to_uppercase().as_ref() {
       "INSERT-STATEMENT" => CherryPickOutputFormatEnum::InsertStatement,
+      "COPY" => CherryPickOutputFormatEnum::Copy,
       "GRAPHVIZ" => CherryPickOutputFormatEnum::Graphviz,
+      "HTML" => CherryPickOutputFormatEnum::Html,
       _ => CherryPickOutputFormatEnum::InsertStatement,
     }
   }
@@ -47,7 +103,12 @@ impl std::fmt::Display for CherryPickOutputFormatEnum {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     match self {
       CherryPickOutputFormatEnum::InsertStatement => write!(f, "insert-statement"),
+      CherryPickOutputFormatEnum::Copy => write!(f, "copy"),
       CherryPickOutputFormatEnum::Graphviz => write!(f, "graphviz"),
+      CherryPickOutputFormatEnum::Html => write!(f, "html"),
+      CherryPickOutputFormatEnum::Template(template_file_path) => {
+        write!(f, "template {}", template_file_path.display())
+      }
     }
   }
 }
@@ -73,13 +134,19 @@ impl DbCli {
               .long("--schema")
               .required(false)
               .takes_value(true)
-              .default_value("public")
-              .help("Db schema"),
+              .help("Db schema, defaults to public for postgres and the connection database for mysql"),
+          )
+          .arg(
+            Arg::with_name("preset")
+              .long("--preset")
+              .required(false)
+              .takes_value(true)
+              .help("Name of the cherry-pick preset in the config, the other arguments override the preset"),
           )
           .arg(
             Arg::with_name("table")
               .long("--table")
-              .required(true)
+              .required_unless("preset")
               .takes_value(true)
               .help("Db table"),
           )
@@ -88,20 +155,37 @@ impl DbCli {
               .long("--column")
               .required(false)
               .takes_value(true)
-              .default_value("id")
               .help("The column that the values are tied to, default to id"),
           )
           .arg(
             Arg::with_name("values")
               .long("--values")
-              .required(true)
+              .required_unless("values_from")
+              .conflicts_with("values_from")
               .takes_value(true)
               .help("Comma separated values of the column to be fetched"),
           )
+          .arg(
+            Arg::with_name("values_from")
+              .long("--values-from")
+              .required(false)
+              .takes_value(true)
+              .help(
+                "File with the values of the column, 1 value per line or a csv with the column as header, - reads stdin",
+              ),
+          )
+          .arg(
+            Arg::with_name("values_column")
+              .long("--values-column")
+              .required(false)
+              .requires("values_from")
+              .takes_value(true)
+              .help("Header of the --values-from csv column that has the values, defaults to --column"),
+          )
           .arg(
             Arg::with_name("source_db")
               .long("--source-db")
-              .required(true)
+              .required_unless_one(&["replay_snapshot", "preset"])
               .takes_value(true)
               .help("Source db to fetch data from"),
           )
@@ -110,9 +194,84 @@ impl DbCli {
               .long("--output-format")
               .required(false)
               .takes_value(true)
-              .default_value("insert-statement")
-              .possible_values(&["insert-statement", "graphviz"])
-              .help("Print format of the cherry pick cli output"),
+              .possible_values(&CHERRY_PICK_OUTPUT_FORMATS)
+              .help("Print format of the cherry pick cli output, defaults to insert-statement"),
+          )
+          .arg(
+            Arg::with_name("template")
+              .long("--template")
+              .required(false)
+              .takes_value(true)
+              .conflicts_with("output_format")
+              .help("Render the row graph through the given handlebars template file instead of --output-format"),
+          )
+          .arg(
+            Arg::with_name("max_children")
+              .long("--max-children")
+              .required(false)
+              .takes_value(true)
+              .help("Maximum children fetched per parent row of every foreign key relation"),
+          )
+          .arg(
+            Arg::with_name("max_children_by")
+              .long("--max-children-by")
+              .required(false)
+              .takes_value(true)
+              .use_delimiter(true)
+              .help("Maximum children per table or per foreign key column in format '{table_1}={max_1},{table_n}:{fk_column_n}={max_n}' for example 'orders=10, product_stock_ledgers:product_id=5'"),
+          )
+          .arg(
+            Arg::with_name("children_order_by")
+              .long("--children-order-by")
+              .required(false)
+              .takes_value(true)
+              .use_delimiter(true)
+              .help("Order of the limited children, defaults to primary column ascending, in format '{column} {asc|desc},{table_n}:{column_n} {asc|desc}' for example 'created_at desc, orders:id desc'"),
+          )
+          .arg(
+            Arg::with_name("script")
+              .long("--script")
+              .required(false)
+              .takes_value(false)
+              .help("Wrap insert statements in a transaction with a header describing the cherry pick"),
+          )
+          .arg(
+            Arg::with_name("no_script")
+              .long("--no-script")
+              .required(false)
+              .takes_value(false)
+              .conflicts_with("script")
+              .help("Turn off --script of the preset"),
+          )
+          .arg(
+            Arg::with_name("disable_triggers")
+              .long("--disable-triggers")
+              .required(false)
+              .takes_value(false)
+              .help("Set session_replication_role = replica in the script so triggers are not fired, postgres only"),
+          )
+          .arg(
+            Arg::with_name("no_disable_triggers")
+              .long("--no-disable-triggers")
+              .required(false)
+              .takes_value(false)
+              .conflicts_with("disable_triggers")
+              .help("Turn off --disable-triggers of the preset"),
+          )
+          .arg(
+            Arg::with_name("defer_constraints")
+              .long("--defer-constraints")
+              .required(false)
+              .takes_value(false)
+              .help("Defer constraint checks until commit in the script, disables foreign key checks on mysql"),
+          )
+          .arg(
+            Arg::with_name("no_defer_constraints")
+              .long("--no-defer-constraints")
+              .required(false)
+              .takes_value(false)
+              .conflicts_with("defer_constraints")
+              .help("Turn off --defer-constraints of the preset"),
           )
           .arg(
             Arg::with_name("graph_table_columns")
@@ -121,6 +280,322 @@ impl DbCli {
               .takes_value(true)
               .use_delimiter(true)
               .help("Set the table columns that will be displayed on each node in format '{table_1}:{column_1}|{column_2}|{column_n},{table_n}:{column_n}' for example 'users:id|name|email, orders:|code'"),
+          )
+          .arg(
+            Arg::with_name("exclude_columns")
+              .long("--exclude-columns")
+              .required(false)
+              .takes_value(true)
+              .use_delimiter(true)
+              .help("Columns that will not be inserted in format '{table_1}:{column_1}|{column_n},{table_n}:{column_n}' for example 'users:updated_at|search_vector'"),
+          )
+          .arg(
+            Arg::with_name("set_columns")
+              .long("--set-columns")
+              .required(false)
+              .takes_value(true)
+              .multiple(true)
+              .number_of_values(1)
+              .help("Insert the given raw sql value instead of the fetched value in format '{table}:{column}={value}', repeat it to set more columns, for example --set-columns='orders:tenant_id=1' --set-columns=\"users:name=concat(first_name, ' ', last_name)\""),
+          )
+          .arg(
+            Arg::with_name("refresh_metadata")
+              .long("--refresh-metadata")
+              .required(false)
+              .takes_value(false)
+              .help("Ignore the cached db metadata and reload it from the db"),
+          )
+          .arg(
+            Arg::with_name("fetch_parallelism")
+              .long("--fetch-parallelism")
+              .required(false)
+              .takes_value(true)
+              .help(
+                "Max relations that are fetched concurrently, overrides fetch_parallelism of the source db config",
+              ),
+          )
+          .arg(
+            Arg::with_name("explain")
+              .long("--explain")
+              .required(false)
+              .takes_value(true)
+              .use_delimiter(true)
+              .help("Print the foreign key paths from the root row to the given rows instead of the output, in format '{table_1}:{pk_1},{table_n}:{pk_n}' for example 'products:5'"),
+          )
+          .arg(
+            Arg::with_name("with_ddl")
+              .long("--with-ddl")
+              .required(false)
+              .takes_value(false)
+              .help("Emit the create table statements of every fetched table before the insert statements"),
+          )
+          .arg(
+            Arg::with_name("no_with_ddl")
+              .long("--no-with-ddl")
+              .required(false)
+              .takes_value(false)
+              .conflicts_with("with_ddl")
+              .help("Turn off --with-ddl of the preset"),
+          )
+          .arg(
+            Arg::with_name("no_row_filters")
+              .long("--no-row-filters")
+              .required(false)
+              .takes_value(false)
+              .help("Ignore the row filters configured for the source db"),
+          )
+          .arg(
+            Arg::with_name("row_filters")
+              .long("--row-filters")
+              .required(false)
+              .takes_value(false)
+              .conflicts_with("no_row_filters")
+              .help("Turn off --no-row-filters of the preset"),
+          )
+          .arg(
+            Arg::with_name("record_snapshot")
+              .long("--record-snapshot")
+              .required(false)
+              .takes_value(true)
+              .help("Record the table structure and every fetched row into the given snapshot file"),
+          )
+          .arg(
+            Arg::with_name("replay_snapshot")
+              .long("--replay-snapshot")
+              .required(false)
+              .takes_value(true)
+              .conflicts_with_all(&["source_db", "refresh_metadata", "record_snapshot"])
+              .help("Fetch rows from the given snapshot file instead of connecting to the db"),
+          ),
+      )
+      .subcommand(
+        SubCommand::with_name("seed")
+          .about(indoc::indoc! {"
+            Generate fake rows for the given table and every table it references,
+            prints out the insert statements or applies them to the db.
+          "})
+          .arg(
+            Arg::with_name("schema")
+              .long("--schema")
+              .required(false)
+              .takes_value(true)
+              .help("Db schema, defaults to public for postgres and the connection database for mysql"),
+          )
+          .arg(
+            Arg::with_name("table")
+              .long("--table")
+              .required(true)
+              .takes_value(true)
+              .help("Db table to seed"),
+          )
+          .arg(
+            Arg::with_name("source_db")
+              .long("--source-db")
+              .required(true)
+              .takes_value(true)
+              .help("Db connection name that the table structure is read from"),
+          )
+          .arg(
+            Arg::with_name("count")
+              .long("--count")
+              .required(false)
+              .takes_value(true)
+              .default_value("10")
+              .help("Rows generated for the table"),
+          )
+          .arg(
+            Arg::with_name("parent_count")
+              .long("--parent-count")
+              .required(false)
+              .takes_value(true)
+              .default_value("1")
+              .help("Rows generated for each of the referenced tables"),
+          )
+          .arg(
+            Arg::with_name("apply")
+              .long("--apply")
+              .required(false)
+              .takes_value(false)
+              .help("Insert the generated rows into the source db in a single transaction instead of printing them"),
+          )
+          .arg(
+            Arg::with_name("confirm_prod")
+              .long("--confirm-prod")
+              .required(false)
+              .takes_value(false)
+              .help("Confirm writing into a connection whose environment is prod"),
+          ),
+      )
+      .subcommand(
+        SubCommand::with_name("describe")
+          .about("Print the columns, constraints and relations of the given table")
+          .arg(
+            Arg::with_name("schema")
+              .long("--schema")
+              .required(false)
+              .takes_value(true)
+              .help("Db schema, defaults to public for postgres and the connection database for mysql"),
+          )
+          .arg(
+            Arg::with_name("table")
+              .long("--table")
+              .required(true)
+              .takes_value(true)
+              .help("Db table"),
+          )
+          .arg(
+            Arg::with_name("source_db")
+              .long("--source-db")
+              .required(true)
+              .takes_value(true)
+              .help("Db connection name that the table structure is read from"),
+          )
+          .arg(
+            Arg::with_name("refresh_metadata")
+              .long("--refresh-metadata")
+              .required(false)
+              .takes_value(false)
+              .help("Ignore the cached table structure and reload it from the db"),
+          ),
+      )
+      .subcommand(
+        SubCommand::with_name("apply")
+          .about("Insert a cherry-picked sql script or snapshot into the target db row by row and report rows that already existed, conflicted or failed")
+          .arg(
+            Arg::with_name("target_db")
+              .long("--target-db")
+              .required(true)
+              .takes_value(true)
+              .help("Db connection name that the rows will be inserted into"),
+          )
+          .arg(
+            Arg::with_name("file")
+              .required(true)
+              .takes_value(true)
+              .help("Sql script or json snapshot (recorded with --record-snapshot) to apply"),
+          )
+          .arg(
+            Arg::with_name("confirm_prod")
+              .long("--confirm-prod")
+              .required(false)
+              .takes_value(false)
+              .help("Confirm writing into a connection whose environment is prod"),
+          ),
+      )
+      .subcommand(
+        SubCommand::with_name("integrity-check")
+          .about(indoc::indoc! {"
+            Report child rows that reference a missing parent row, checks virtual foreign keys
+            configured in `virtual_foreign_keys` and postgres foreign keys declared as NOT VALID.
+          "})
+          .arg(
+            Arg::with_name("schema")
+              .long("--schema")
+              .required(false)
+              .takes_value(true)
+              .help("Db schema, defaults to public for postgres and the connection database for mysql"),
+          )
+          .arg(
+            Arg::with_name("source_db")
+              .long("--source-db")
+              .required(true)
+              .takes_value(true)
+              .help("Db connection name that will be checked"),
+          )
+          .arg(
+            Arg::with_name("sample_size")
+              .long("--sample-size")
+              .required(false)
+              .takes_value(true)
+              .default_value("10")
+              .help("Orphaned rows printed per relation"),
+          )
+          .arg(
+            Arg::with_name("all_foreign_keys")
+              .long("--all-foreign-keys")
+              .required(false)
+              .takes_value(false)
+              .help("Also check validated foreign keys"),
+          )
+          .arg(
+            Arg::with_name("refresh_metadata")
+              .long("--refresh-metadata")
+              .required(false)
+              .takes_value(false)
+              .help("Ignore the cached table structure and reload it from the db"),
+          ),
+      )
+      .subcommand(
+        SubCommand::with_name("load-order")
+          .about("Print the order to load and truncate every table of a schema and the foreign key cycles")
+          .arg(
+            Arg::with_name("schema")
+              .long("--schema")
+              .required(false)
+              .takes_value(true)
+              .help("Db schema, defaults to public for postgres and the connection database for mysql"),
+          )
+          .arg(
+            Arg::with_name("source_db")
+              .long("--source-db")
+              .required(true)
+              .takes_value(true)
+              .help("Db connection name that the table structure is read from"),
+          )
+          .arg(
+            Arg::with_name("output_format")
+              .long("--output-format")
+              .required(false)
+              .takes_value(true)
+              .default_value("report")
+              .possible_values(&["report", "load", "truncate"])
+              .help("`load` and `truncate` only print the table names, 1 table per line"),
+          )
+          .arg(
+            Arg::with_name("refresh_metadata")
+              .long("--refresh-metadata")
+              .required(false)
+              .takes_value(false)
+              .help("Ignore the cached table structure and reload it from the db"),
+          ),
+      )
+      .subcommand(
+        SubCommand::with_name("schema-diff")
+          .about("Print the tables, columns and constraints that differ between 2 dbs")
+          .arg(
+            Arg::with_name("schema")
+              .long("--schema")
+              .required(false)
+              .takes_value(true)
+              .help("Db schema of both dbs, defaults to public for postgres and the connection database for mysql"),
+          )
+          .arg(
+            Arg::with_name("source_db")
+              .long("--source-db")
+              .required(true)
+              .takes_value(true)
+              .help("Db connection name of the expected schema"),
+          )
+          .arg(
+            Arg::with_name("target_db")
+              .long("--target-db")
+              .required(true)
+              .takes_value(true)
+              .help("Db connection name of the schema that is compared"),
+          )
+          .arg(
+            Arg::with_name("migration_sql")
+              .long("--migration-sql")
+              .required(false)
+              .takes_value(false)
+              .help("Also print the statements that turn the target schema into the source schema, the diff is commented out"),
+          )
+          .arg(
+            Arg::with_name("refresh_metadata")
+              .long("--refresh-metadata")
+              .required(false)
+              .takes_value(false)
+              .help("Ignore the cached table structure and reload it from both dbs"),
           ),
       );
   }
@@ -128,93 +603,775 @@ impl DbCli {
   pub fn run(cli: &ArgMatches<'_>, config: Config, logger: &'static Logger) -> ResultAnyError<()> {
     match cli.subcommand() {
       ("cherry-pick", Some(cherry_pick_cli)) => {
-        let values: Vec<String> = cherry_pick_cli
-          .values_of("values")
-          .or_else(|| Default::default())
-          .unwrap()
-          .into_iter()
-          .map(ToOwned::to_owned)
-          .collect();
+        let preset: CherryPickPresetConfig = match cherry_pick_cli.value_of("preset") {
+          Some(preset_name) => config
+            .cherry_pick_presets
+            .get(preset_name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Cherry-pick preset {} is not configured", preset_name))?,
+          None => Default::default(),
+        };
+
+        // Cli arguments override the preset
+        let single_value_param =
+          |arg_name: &str, preset_value: &Option<String>| -> Option<String> {
+            return cherry_pick_cli
+              .value_of(arg_name)
+              .map(ToOwned::to_owned)
+              .or_else(|| preset_value.clone());
+          };
+
+        let required_value_param =
+          |arg_name: &str, preset_value: &Option<String>| -> ResultAnyError<String> {
+            return single_value_param(arg_name, preset_value).ok_or_else(|| {
+              return anyhow!(
+                "--{} is required, it is not set by the preset",
+                arg_name.replace('_', "-")
+              );
+            });
+          };
+
+        let multiple_values_param = |arg_name: &str, preset_values: &[String]| -> Vec<String> {
+          if !cherry_pick_cli.is_present(arg_name) {
+            return preset_values.to_vec();
+          }
+
+          return cherry_pick_cli
+            .values_of(arg_name)
+            .or_else(|| Some(Default::default()))
+            .unwrap()
+            .into_iter()
+            .map(str::trim)
+            .map(ToOwned::to_owned)
+            .collect();
+        };
+
+        // The negated flag turns off the flag of the preset, e.g. `--no-script`
+        let flag_param = |arg_name: &str, negated_arg_name: &str, preset_value: bool| -> bool {
+          return cherry_pick_cli.is_present(arg_name)
+            || (preset_value && !cherry_pick_cli.is_present(negated_arg_name));
+        };
+
+        let output_format: String = single_value_param("output_format", &preset.output_format)
+          .unwrap_or_else(|| CHERRY_PICK_OUTPUT_FORMATS[0].to_owned());
+
+        if !CHERRY_PICK_OUTPUT_FORMATS.contains(&output_format.as_str()) {
+          return Err(anyhow!(
+            "Invalid output format {}, expected one of {}",
+            output_format,
+            CHERRY_PICK_OUTPUT_FORMATS.join(", ")
+          ));
+        }
+
+        let script_config = InsertScriptConfig {
+          disable_triggers: flag_param(
+            "disable_triggers",
+            "no_disable_triggers",
+            preset.disable_triggers,
+          ),
+          defer_constraints: flag_param(
+            "defer_constraints",
+            "no_defer_constraints",
+            preset.defer_constraints,
+          ),
+        };
+        let is_script = flag_param("script", "no_script", preset.script);
+
+        if !is_script && (script_config.disable_triggers || script_config.defer_constraints) {
+          return Err(anyhow!(
+            "--disable-triggers and --defer-constraints require --script"
+          ));
+        }
+
+        let max_children: Option<String> = single_value_param(
+          "max_children",
+          &preset
+            .max_children
+            .map(|max_children| max_children.to_string()),
+        );
+
+        let child_limit_param = CherryPickChildLimitParam {
+          max_children: max_children.as_deref(),
+          max_children_by: multiple_values_param("max_children_by", &preset.max_children_by),
+          children_order_by: multiple_values_param("children_order_by", &preset.children_order_by),
+        };
+
+        let source_db: String;
+        let source = match cherry_pick_cli.value_of("replay_snapshot") {
+          Some(snapshot_file_path) => CherryPickSourceParam::Snapshot(snapshot_file_path),
+          None => {
+            source_db = required_value_param("source_db", &preset.source_db)?;
+
+            let fetch_parallelism: Option<usize> = cherry_pick_cli
+              .value_of("fetch_parallelism")
+              .map(|fetch_parallelism_str| {
+                return fetch_parallelism_str.parse::<usize>().map_err(|_| {
+                  return anyhow!(
+                    "Invalid fetch_parallelism {}, expected a number",
+                    fetch_parallelism_str
+                  );
+                });
+              })
+              .transpose()?;
+
+            CherryPickSourceParam::Db {
+              source_db: &source_db,
+              refresh_metadata: cherry_pick_cli.is_present("refresh_metadata"),
+              use_row_filters: !flag_param("no_row_filters", "row_filters", preset.no_row_filters),
+              record_snapshot_file_path: cherry_pick_cli.value_of("record_snapshot"),
+              fetch_parallelism,
+            }
+          }
+        };
+
+        let schema: Option<String> = single_value_param("schema", &preset.schema);
+        let table: String = required_value_param("table", &preset.table)?;
+        let column: String =
+          single_value_param("column", &preset.column).unwrap_or_else(|| "id".to_owned());
+
+        let values: Vec<String> = match cherry_pick_cli.value_of("values_from") {
+          Some(values_file_path) => DbCli::read_root_values(
+            values_file_path,
+            &column,
+            cherry_pick_cli.value_of("values_column"),
+          )?,
+          None => cherry_pick_cli
+            .value_of("values")
+            .map(|values| RootValues::parse(values, &column, None))
+            .unwrap_or_else(|| Ok(vec![]))?,
+        };
+
+        if values.is_empty() {
+          return Err(anyhow!("No values to cherry-pick"));
+        }
+
+        // `--output-format` of the cli overrides the template of the preset
+        let template_file_path: Option<String> = match cherry_pick_cli.value_of("output_format") {
+          Some(_) => None,
+          None => single_value_param("template", &preset.template),
+        };
+
+        let output_format: CherryPickOutputFormatEnum = match template_file_path {
+          Some(template_file_path) => {
+            CherryPickOutputFormatEnum::Template(PathBuf::from(template_file_path))
+          }
+          None => output_format.as_str().into(),
+        };
+
+        return DbCli::cherry_pick(CherryPickInput::new(
+          source,
+          schema.as_deref(),
+          &table,
+          &column,
+          values,
+          output_format,
+          Some(script_config).filter(|_| is_script),
+          child_limit_param,
+          multiple_values_param("graph_table_columns", &preset.graph_table_columns),
+          multiple_values_param("exclude_columns", &preset.exclude_columns),
+          multiple_values_param("set_columns", &preset.set_columns),
+          multiple_values_param("explain", &[]),
+          flag_param("with_ddl", "no_with_ddl", preset.with_ddl),
+          config,
+          logger,
+        )?);
+      }
+      ("seed", Some(seed_cli)) => {
+        let source_db = seed_cli.value_of("source_db").unwrap();
+        let db_config: DbConnectionConfig = config
+          .db_connection_by_name
+          .get(source_db)
+          .ok_or_else(|| anyhow!("Source db {} is not registered", source_db))?
+          .clone();
+
+        if seed_cli.is_present("apply") {
+          db_config.ensure_writable(source_db, seed_cli.is_present("confirm_prod"))?;
+        }
+
+        let parse_count = |arg_name: &str| -> ResultAnyError<usize> {
+          let count_str = seed_cli.value_of(arg_name).unwrap();
+
+          return count_str
+            .parse::<usize>()
+            .map_err(|_| anyhow!("Invalid {} {}, expected a number", arg_name, count_str));
+        };
+
+        return DbCli::seed(SeedInput {
+          schema: seed_cli
+            .value_of("schema")
+            .unwrap_or_else(|| db_config.default_schema())
+            .to_owned(),
+          table: seed_cli.value_of("table").unwrap(),
+          seed_config: SeedConfig {
+            row_count: parse_count("count")?,
+            parent_row_count: parse_count("parent_count")?,
+          },
+          apply: seed_cli.is_present("apply"),
+          metadata_cache_file_path: config.metadata_cache_file_path(source_db),
+          db_config,
+        });
+      }
+      ("describe", Some(describe_cli)) => {
+        let source_db = describe_cli.value_of("source_db").unwrap();
+        let db_config: DbConnectionConfig = config
+          .db_connection_by_name
+          .get(source_db)
+          .ok_or_else(|| anyhow!("Source db {} is not registered", source_db))?
+          .clone();
+
+        let schema: String = describe_cli
+          .value_of("schema")
+          .unwrap_or_else(|| db_config.default_schema())
+          .to_owned();
+
+        return DbCli::describe(
+          &db_config,
+          config.metadata_cache_file_path(source_db),
+          describe_cli.is_present("refresh_metadata"),
+          &PsqlTableIdentity::new(schema.as_str(), describe_cli.value_of("table").unwrap()),
+        );
+      }
+      ("apply", Some(apply_cli)) => {
+        let target_db = apply_cli.value_of("target_db").unwrap();
+        let db_config: DbConnectionConfig = config
+          .db_connection_by_name
+          .get(target_db)
+          .ok_or_else(|| anyhow!("Target db {} is not registered", target_db))?
+          .clone();
+
+        db_config.ensure_writable(target_db, apply_cli.is_present("confirm_prod"))?;
+
+        return DbCli::apply(
+          &db_config,
+          config.metadata_cache_file_path(target_db),
+          Path::new(apply_cli.value_of("file").unwrap()),
+        );
+      }
+      ("load-order", Some(load_order_cli)) => {
+        let source_db = load_order_cli.value_of("source_db").unwrap();
+        let db_config: DbConnectionConfig = config
+          .db_connection_by_name
+          .get(source_db)
+          .ok_or_else(|| anyhow!("Source db {} is not registered", source_db))?
+          .clone();
+
+        let schema: String = load_order_cli
+          .value_of("schema")
+          .unwrap_or_else(|| db_config.default_schema())
+          .to_owned();
+
+        return DbCli::load_order(
+          &db_config,
+          config.metadata_cache_file_path(source_db),
+          load_order_cli.is_present("refresh_metadata"),
+          &schema,
+          load_order_cli.value_of("output_format").unwrap(),
+        );
+      }
+      ("schema-diff", Some(schema_diff_cli)) => {
+        let db_config_of = |db_name: &str| -> ResultAnyError<DbConnectionConfig> {
+          return config
+            .db_connection_by_name
+            .get(db_name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Db {} is not registered", db_name));
+        };
+
+        let source_db = schema_diff_cli.value_of("source_db").unwrap();
+        let target_db = schema_diff_cli.value_of("target_db").unwrap();
+
+        return DbCli::schema_diff(SchemaDiffInput {
+          source_db_config: db_config_of(source_db)?,
+          source_metadata_cache_file_path: config.metadata_cache_file_path(source_db),
+          target_db_config: db_config_of(target_db)?,
+          target_metadata_cache_file_path: config.metadata_cache_file_path(target_db),
+          schema: schema_diff_cli.value_of("schema"),
+          migration_sql: schema_diff_cli.is_present("migration_sql"),
+          refresh_metadata: schema_diff_cli.is_present("refresh_metadata"),
+        });
+      }
+      ("integrity-check", Some(integrity_check_cli)) => {
+        let source_db = integrity_check_cli.value_of("source_db").unwrap();
+        let db_config: DbConnectionConfig = config
+          .db_connection_by_name
+          .get(source_db)
+          .ok_or_else(|| anyhow!("Source db {} is not registered", source_db))?
+          .clone();
+
+        let sample_size_str = integrity_check_cli.value_of("sample_size").unwrap();
+        let sample_size = sample_size_str.parse::<usize>().map_err(|_| {
+          return anyhow!("Invalid sample_size {}, expected a number", sample_size_str);
+        })?;
+
+        return DbCli::integrity_check(IntegrityCheckInput {
+          schema: integrity_check_cli
+            .value_of("schema")
+            .unwrap_or_else(|| db_config.default_schema())
+            .to_owned(),
+          sample_size,
+          include_declared: integrity_check_cli.is_present("all_foreign_keys"),
+          refresh_metadata: integrity_check_cli.is_present("refresh_metadata"),
+          metadata_cache_file_path: config.metadata_cache_file_path(source_db),
+          db_config,
+        });
+      }
+      _ => Ok(()),
+    }
+  }
+}
+
+enum CherryPickSourceParam<'a> {
+  Db {
+    source_db: &'a str,
+    refresh_metadata: bool,
+    use_row_filters: bool,
+    record_snapshot_file_path: Option<&'a str>,
+    fetch_parallelism: Option<usize>,
+  },
+  Snapshot(&'a str),
+}
+
+struct CherryPickChildLimitParam<'a> {
+  max_children: Option<&'a str>,
+  max_children_by: Vec<String>,
+  children_order_by: Vec<String>,
+}
+
+struct SeedInput<'a> {
+  db_config: DbConnectionConfig,
+  metadata_cache_file_path: PathBuf,
+  schema: String,
+  table: &'a str,
+  seed_config: SeedConfig,
+  apply: bool,
+}
+
+struct SchemaDiffInput<'a> {
+  source_db_config: DbConnectionConfig,
+  source_metadata_cache_file_path: PathBuf,
+  target_db_config: DbConnectionConfig,
+  target_metadata_cache_file_path: PathBuf,
+  schema: Option<&'a str>,
+  migration_sql: bool,
+  refresh_metadata: bool,
+}
 
-        let graph_table_columns: Vec<String> = cherry_pick_cli
-          .values_of("graph_table_columns")
-          .or_else(|| Some(Default::default()))
-          .unwrap()
-          .into_iter()
-          .map(str::trim)
-          .map(ToOwned::to_owned)
-          .collect();
+struct IntegrityCheckInput {
+  db_config: DbConnectionConfig,
+  metadata_cache_file_path: PathBuf,
+  schema: String,
+  sample_size: usize,
+  include_declared: bool,
+  refresh_metadata: bool,
+}
 
-        return DbCli::cherry_pick(CherryPickInput::new(
-          cherry_pick_cli.value_of("source_db").unwrap(),
-          cherry_pick_cli.value_of("schema").unwrap(),
-          cherry_pick_cli.value_of("table").unwrap(),
-          cherry_pick_cli.value_of("column").unwrap(),
-          values,
-          cherry_pick_cli.value_of("output_format").unwrap().into(),
-          graph_table_columns,
-          config,
-          logger,
-        )?);
-      }
-      _ => Ok(()),
-    }
-  }
+/// Where cherry-pick fetches the table structure and rows from
+enum CherryPickSource {
+  Db {
+    db_config: DbConnectionConfig,
+    metadata_cache_file_path: PathBuf,
+    refresh_metadata: bool,
+    row_filters: Vec<RowFilter>,
+    record_snapshot_file_path: Option<PathBuf>,
+    fetch_parallelism: usize,
+  },
+  Snapshot(TableMetadataSnapshot),
 }
 
 struct CherryPickInput<'a> {
-  source_db: &'a str,
-  schema: &'a str,
+  source: CherryPickSource,
+
+  /// Human readable source, printed on the script header
+  source_name: String,
+  driver: DbDriver,
+  schema: String,
   table: &'a str,
   column: &'a str,
   values: Vec<String>,
   output_format: CherryPickOutputFormatEnum,
+  script_config: Option<InsertScriptConfig>,
+  child_row_limit_config: ChildRowLimitConfig,
   displayed_fields_by_table_id: HashMap<PsqlTableIdentity, Vec<String>>,
-  config: Config,
+  insert_statement_config: InsertStatementConfig,
+
+  /// Rows whose relation path to the root is printed instead of the output
+  explained_row_ids: Vec<(PsqlTableIdentity, String)>,
+
+  /// Prepend the ddl of the fetched tables to the insert statements
+  with_ddl: bool,
   logger: &'static Logger,
 }
 
 impl<'a> CherryPickInput<'a> {
   pub fn new(
-    source_db: &'a str,
-    schema: &'a str,
+    source: CherryPickSourceParam<'a>,
+    schema: Option<&'a str>,
     table: &'a str,
     column: &'a str,
     values: Vec<String>,
     output_format: CherryPickOutputFormatEnum,
+    script_config: Option<InsertScriptConfig>,
+    child_limit_param: CherryPickChildLimitParam<'a>,
     graph_table_columns: Vec<String>,
+    exclude_columns: Vec<String>,
+    set_columns: Vec<String>,
+    explain: Vec<String>,
+    with_ddl: bool,
     config: Config,
     logger: &'static Logger,
   ) -> ResultAnyError<CherryPickInput<'a>> {
+    let source_name: String = match &source {
+      CherryPickSourceParam::Snapshot(snapshot_file_path) => {
+        format!("snapshot {}", snapshot_file_path)
+      }
+      CherryPickSourceParam::Db { source_db, .. } => source_db.to_string(),
+    };
+
+    let (source, driver, default_schema): (CherryPickSource, DbDriver, String) = match source {
+      CherryPickSourceParam::Snapshot(snapshot_file_path) => {
+        let snapshot = TableMetadataSnapshot::load(Path::new(snapshot_file_path))?;
+        let driver = snapshot.driver;
+        let default_schema = snapshot.schema.clone();
+
+        (CherryPickSource::Snapshot(snapshot), driver, default_schema)
+      }
+      CherryPickSourceParam::Db {
+        source_db,
+        refresh_metadata,
+        use_row_filters,
+        record_snapshot_file_path,
+        fetch_parallelism,
+      } => {
+        let db_config: DbConnectionConfig = config
+          .db_connection_by_name
+          .get(source_db)
+          .ok_or_else(|| anyhow!("Source db {} is not registered", source_db))?
+          .clone();
+
+        let driver = db_config.driver;
+        let default_schema = db_config.default_schema().to_owned();
+        let row_filters: Vec<RowFilter> = if use_row_filters {
+          CherryPickInput::create_row_filters(&db_config, schema.unwrap_or(&default_schema))?
+        } else {
+          vec![]
+        };
+
+        (
+          CherryPickSource::Db {
+            metadata_cache_file_path: config.metadata_cache_file_path(source_db),
+            refresh_metadata,
+            row_filters,
+            record_snapshot_file_path: record_snapshot_file_path.map(PathBuf::from),
+            fetch_parallelism: fetch_parallelism.unwrap_or_else(|| db_config.fetch_parallelism()),
+            db_config,
+          },
+          driver,
+          default_schema,
+        )
+      }
+    };
+
+    let schema: String = schema.map(ToOwned::to_owned).unwrap_or(default_schema);
+
+    if let Some(script_config) = script_config {
+      if script_config.disable_triggers && driver != DbDriver::Postgres {
+        return Err(anyhow!("Disabling triggers is only supported on postgres"));
+      }
+    }
+
+    if let CherryPickOutputFormatEnum::Copy = output_format {
+      if driver != DbDriver::Postgres {
+        return Err(anyhow!(
+          "The copy output format is only supported on postgres"
+        ));
+      }
+    }
+
     return Ok(CherryPickInput {
-      source_db,
+      displayed_fields_by_table_id: CherryPickInput::create_columns_by_table_id_from_param(
+        graph_table_columns,
+        &schema,
+      )?,
+      child_row_limit_config: CherryPickInput::create_child_row_limit_config(
+        child_limit_param,
+        &schema,
+      )?,
+      insert_statement_config: CherryPickInput::create_insert_statement_config(
+        &source,
+        driver,
+        exclude_columns,
+        set_columns,
+        &schema,
+      )?,
+      explained_row_ids: CherryPickInput::create_explained_row_ids_from_param(explain, &schema)?,
+      source,
+      source_name,
+      driver,
       schema,
       table,
       values,
       column,
       output_format,
-      displayed_fields_by_table_id:
-        CherryPickInput::create_displayed_fields_by_table_id_from_param(graph_table_columns)?,
-      config,
+      script_config,
+      with_ddl,
       logger,
     });
   }
 
-  fn create_displayed_fields_by_table_id_from_param(
-    graph_table_columns: Vec<String>,
+  /// Merge the configured column exclusions and overrides of the source db with the cli params,
+  /// cli params take precedence.
+  fn create_insert_statement_config(
+    source: &CherryPickSource,
+    driver: DbDriver,
+    exclude_columns: Vec<String>,
+    set_columns: Vec<String>,
+    default_schema: &str,
+  ) -> ResultAnyError<InsertStatementConfig> {
+    let mut insert_statement_config = InsertStatementConfig::new(driver);
+
+    if let CherryPickSource::Db { db_config, .. } = source {
+      for (table_id_str, column_names) in db_config.exclude_columns.iter() {
+        insert_statement_config
+          .excluded_columns_by_table_id
+          .entry(PsqlTableIdentity::from_str_with_default_schema(
+            table_id_str,
+            default_schema,
+          )?)
+          .or_default()
+          .extend(column_names.iter().cloned());
+      }
+
+      for (table_id_str, column_value_by_name) in db_config.set_columns.iter() {
+        insert_statement_config
+          .column_value_by_table_id
+          .entry(PsqlTableIdentity::from_str_with_default_schema(
+            table_id_str,
+            default_schema,
+          )?)
+          .or_default()
+          .extend(column_value_by_name.clone());
+      }
+    }
+
+    for (table_id, column_names) in
+      CherryPickInput::create_columns_by_table_id_from_param(exclude_columns, default_schema)?
+    {
+      insert_statement_config
+        .excluded_columns_by_table_id
+        .entry(table_id)
+        .or_default()
+        .extend(column_names);
+    }
+
+    for (table_id, column_value_by_name) in
+      CherryPickInput::create_column_value_by_table_id_from_param(set_columns, default_schema)?
+    {
+      insert_statement_config
+        .column_value_by_table_id
+        .entry(table_id)
+        .or_default()
+        .extend(column_value_by_name);
+    }
+
+    return Ok(insert_statement_config);
+  }
+
+  fn create_row_filters(
+    db_config: &DbConnectionConfig,
+    default_schema: &str,
+  ) -> ResultAnyError<Vec<RowFilter>> {
+    return db_config
+      .row_filters
+      .iter()
+      .map(|row_filter| {
+        return Ok(RowFilter {
+          table_id: row_filter
+            .table
+            .as_ref()
+            .map(|table_id_str| {
+              return PsqlTableIdentity::from_str_with_default_schema(table_id_str, default_schema);
+            })
+            .transpose()?,
+          column_name: row_filter.column.clone(),
+          predicate: row_filter.predicate.clone(),
+        });
+      })
+      .collect();
+  }
+
+  /// Parse rows in format `{table}:{pk}`
+  fn create_explained_row_ids_from_param(
+    explain: Vec<String>,
+    default_schema: &str,
+  ) -> ResultAnyError<Vec<(PsqlTableIdentity, String)>> {
+    return explain
+      .iter()
+      .filter(|row_id_str| !row_id_str.is_empty())
+      .map(|row_id_str| {
+        let mut splitted = row_id_str.splitn(2, ':');
+        let table_id_str = splitted.next().unwrap().trim();
+        let row_id = splitted
+          .next()
+          .map(str::trim)
+          .filter(|row_id| !row_id.is_empty())
+          .ok_or_else(|| {
+            return anyhow!(
+              "Invalid explain format {}, expected {{table}}:{{pk}}",
+              row_id_str
+            );
+          })?;
+
+        return Ok((
+          PsqlTableIdentity::from_str_with_default_schema(table_id_str, default_schema)?,
+          row_id.to_owned(),
+        ));
+      })
+      .collect();
+  }
+
+  fn create_child_row_limit_config(
+    param: CherryPickChildLimitParam,
+    default_schema: &str,
+  ) -> ResultAnyError<ChildRowLimitConfig> {
+    let parse_max_children = |max_children_str: &str| -> ResultAnyError<usize> {
+      return max_children_str.trim().parse::<usize>().map_err(|err| {
+        return anyhow!(
+          "Max children should be a positive number, got {} instead, error: {}",
+          max_children_str,
+          err
+        );
+      });
+    };
+
+    let mut child_row_limit_config = ChildRowLimitConfig {
+      max_children: param.max_children.map(parse_max_children).transpose()?,
+      ..Default::default()
+    };
+
+    for max_children_by_str in param.max_children_by.iter() {
+      let (relation_str, max_children_str) = max_children_by_str.split_once('=').ok_or_else(|| {
+        return anyhow!(
+          "Max children should be in format {{tableIdentity}}={{max}} or {{tableIdentity}}:{{fkColumn}}={{max}}, got {} instead",
+          max_children_by_str
+        );
+      })?;
+
+      let max_children = parse_max_children(max_children_str)?;
+
+      match relation_str.split_once(':') {
+        None => {
+          child_row_limit_config.max_children_by_table_id.insert(
+            PsqlTableIdentity::from_str_with_default_schema(relation_str.trim(), default_schema)?,
+            max_children,
+          );
+        }
+        Some((table_id_str, fk_column_name)) => {
+          child_row_limit_config.max_children_by_fk.insert(
+            (
+              PsqlTableIdentity::from_str_with_default_schema(table_id_str.trim(), default_schema)?,
+              fk_column_name.trim().to_owned(),
+            ),
+            max_children,
+          );
+        }
+      }
+    }
+
+    for order_by_str in param.children_order_by.iter() {
+      let (table_id_str, order_str) = match order_by_str.split_once(':') {
+        None => (None, order_by_str.as_str()),
+        Some((table_id_str, order_str)) => (Some(table_id_str.trim()), order_str),
+      };
+
+      let order_parts: Vec<&str> = order_str.split_whitespace().collect();
+      let order = match order_parts.as_slice() {
+        [column_name] => ChildRowOrder {
+          column_name: column_name.to_string(),
+          descending: false,
+        },
+        [column_name, direction]
+          if ["asc", "desc"].contains(&direction.to_lowercase().as_str()) =>
+        {
+          ChildRowOrder {
+            column_name: column_name.to_string(),
+            descending: direction.eq_ignore_ascii_case("desc"),
+          }
+        }
+        _ => {
+          return Err(anyhow!(
+            "Children order should be in format {{column}} {{asc|desc}} or {{tableIdentity}}:{{column}} {{asc|desc}}, got {} instead",
+            order_by_str
+          ));
+        }
+      };
+
+      match table_id_str {
+        None => child_row_limit_config.default_order = Some(order),
+        Some(table_id_str) => {
+          child_row_limit_config.order_by_table_id.insert(
+            PsqlTableIdentity::from_str_with_default_schema(table_id_str, default_schema)?,
+            order,
+          );
+        }
+      }
+    }
+
+    return Ok(child_row_limit_config);
+  }
+
+  fn create_column_value_by_table_id_from_param(
+    table_column_values: Vec<String>,
+    default_schema: &str,
+  ) -> ResultAnyError<HashMap<PsqlTableIdentity, HashMap<String, String>>> {
+    let mut column_value_by_table_id: HashMap<PsqlTableIdentity, HashMap<String, String>> =
+      Default::default();
+
+    for table_column_value_str in table_column_values.iter() {
+      let (table_id_str, column_name, value) = table_column_value_str
+        .split_once(':')
+        .and_then(|(table_id_str, column_value_str)| {
+          return column_value_str
+            .split_once('=')
+            .map(|(column_name, value)| (table_id_str, column_name, value));
+        })
+        .ok_or_else(|| {
+          return anyhow!(
+            "Table column value should be in format {{tableIdentity}}:{{column}}={{value}}, got {} instead",
+            table_column_value_str
+          );
+        })?;
+
+      column_value_by_table_id
+        .entry(PsqlTableIdentity::from_str_with_default_schema(
+          table_id_str,
+          default_schema,
+        )?)
+        .or_default()
+        .insert(column_name.trim().to_owned(), value.trim().to_owned());
+    }
+
+    return Ok(column_value_by_table_id);
+  }
+
+  fn create_columns_by_table_id_from_param(
+    table_columns: Vec<String>,
+    default_schema: &str,
   ) -> ResultAnyError<HashMap<PsqlTableIdentity, Vec<String>>> {
-    return graph_table_columns
+    return table_columns
       .into_iter()
       .map(|displayed_table_column_str| {
         let (table_id_str, pipe_separated_column) =
           displayed_table_column_str.split_once(':').ok_or_else(|| {
             return anyhow!(
-              "Display table columsn should be in format {{tableIdentity}}:{{column_1}}|{{column_n}}, got {} instead",
+              "Table columns should be in format {{tableIdentity}}:{{column_1}}|{{column_n}}, got {} instead",
               displayed_table_column_str
             );
           })?;
 
-        return Ok(table_id_str.try_into().map(|table_id| {
+        let table_id =
+          PsqlTableIdentity::from_str_with_default_schema(table_id_str, default_schema);
+
+        return Ok(table_id.map(|table_id| {
           (
             table_id,
             pipe_separated_column
@@ -236,51 +1393,198 @@ impl<'a> CherryPickInput<'a> {
 impl DbCli {
   fn cherry_pick<'a>(input: CherryPickInput) -> ResultAnyError<()> {
     let CherryPickInput {
-      source_db,
+      source,
+      source_name,
+      driver,
       schema,
       table,
       values,
       column,
       output_format,
+      script_config,
+      child_row_limit_config,
       displayed_fields_by_table_id,
-      config,
+      insert_statement_config,
+      explained_row_ids,
+      with_ddl,
       logger,
     } = input;
 
-    let source_db_config: DbConnectionConfig = config
-      .db_connection_by_name
-      .get(source_db)
-      .ok_or_else(|| anyhow!("Source db {} is not registered", source_db))?
-      .clone();
-
-    let db_creds = PsqlCreds {
-      host: source_db_config.host.clone(),
-      database_name: source_db_config.database.clone(),
-      username: source_db_config.username.clone(),
-      password: source_db_config.password.clone(),
+    // A missing or broken template should fail before the graph is fetched
+    let compiled_template: Option<CompiledGraphTemplate> = match &output_format {
+      CherryPickOutputFormatEnum::Template(template_file_path) => {
+        let template = std::fs::read_to_string(template_file_path).map_err(|err| {
+          return anyhow!(
+            "Could not read template {}: {}",
+            template_file_path.display(),
+            err
+          );
+        })?;
+
+        Some(GraphTemplate::compile(&template).map_err(|err| {
+          return anyhow!(
+            "Could not compile template {}: {}",
+            template_file_path.display(),
+            err
+          );
+        })?)
+      }
+      _ => None,
     };
 
-    let psql = Rc::new(RefCell::new(PsqlConnection::new(&db_creds)?));
-    let db_metadata = DbMetadata::new(psql.clone());
-    let psql_table_by_id = db_metadata.load_table_structure(schema)?;
+    let mut recorded_snapshot: Option<(Rc<RefCell<TableMetadataSnapshot>>, PathBuf)> = None;
+
+    let (relation_fetcher, psql_table_by_id) = match source {
+      CherryPickSource::Snapshot(snapshot) => {
+        let psql_table_by_id = snapshot.table_by_id();
+
+        (
+          RelationFetcher::new(
+            Box::new(ReplayTableMetadata::new(snapshot)),
+            child_row_limit_config,
+          ),
+          psql_table_by_id,
+        )
+      }
+      CherryPickSource::Db {
+        db_config,
+        metadata_cache_file_path,
+        refresh_metadata,
+        row_filters,
+        record_snapshot_file_path,
+        fetch_parallelism,
+      } => {
+        let (table_metadata, db_metadata, _) = DbCli::connect(&db_config, row_filters.clone())?;
+        let db_metadata =
+          CachedDbMetadata::new(db_metadata, metadata_cache_file_path, refresh_metadata);
+        let psql_table_by_id = db_metadata.load_table_structure(&schema)?;
+
+        match record_snapshot_file_path {
+          // Postgres relations are fetched concurrently through a connection pool
+          None if driver == DbDriver::Postgres => (
+            RelationFetcher::with_parallelism(
+              Box::new(PooledTableMetadata::new(
+                PsqlConnectionPool::new(DbCli::psql_creds(&db_config), fetch_parallelism),
+                row_filters,
+              )),
+              child_row_limit_config,
+              fetch_parallelism,
+            ),
+            psql_table_by_id,
+          ),
+          None => (
+            RelationFetcher::new(table_metadata, child_row_limit_config),
+            psql_table_by_id,
+          ),
+          Some(snapshot_file_path) => {
+            let snapshot = Rc::new(RefCell::new(TableMetadataSnapshot::new(
+              driver,
+              &schema,
+              &psql_table_by_id,
+            )));
+
+            recorded_snapshot = Some((snapshot.clone(), snapshot_file_path));
+
+            (
+              RelationFetcher::new(
+                Box::new(RecordingTableMetadata::new(table_metadata, snapshot)),
+                child_row_limit_config,
+              ),
+              psql_table_by_id,
+            )
+          }
+        }
+      }
+    };
 
     // --------------------------------
-    let (graph, current_node_index) = DbCli::fetch_relation_graph(
-      psql.clone(),
+    let (graph, root_node_indexes) = DbCli::fetch_relation_graph(
+      relation_fetcher,
       &psql_table_by_id,
       table,
-      values,
+      &values,
       column,
-      schema,
+      &schema,
     )?;
 
+    if let Some((snapshot, snapshot_file_path)) = recorded_snapshot {
+      snapshot.borrow().save(&snapshot_file_path)?;
+    }
+
+    if !explained_row_ids.is_empty() {
+      let relation_path_finder = RelationPathFinder::new(&graph, &root_node_indexes);
+
+      for (table_id, row_id) in explained_row_ids.iter() {
+        let relation_paths =
+          relation_path_finder.find_paths(table_id, row_id, MAX_EXPLAINED_PATHS)?;
+
+        println!("{} {}:", table_id, row_id);
+
+        for relation_path in relation_paths.iter() {
+          println!("{}", relation_path);
+        }
+
+        println!();
+      }
+
+      return Ok(());
+    }
+
     match output_format {
-      CherryPickOutputFormatEnum::InsertStatement => {
-        let nodes_by_level = graph_util::create_nodes_by_level(&graph, current_node_index, 0);
+      CherryPickOutputFormatEnum::InsertStatement | CherryPickOutputFormatEnum::Copy => {
+        let nodes_by_level =
+          graph_util::create_nodes_by_level_from_roots(&graph, &root_node_indexes);
+
+        let mut statements: Vec<String> = vec![];
+
+        if with_ddl {
+          let table_ids: HashSet<&PsqlTableIdentity> =
+            graph.node_weights().map(|row| &row.table.id).collect();
+
+          statements.extend(
+            TableDdl::new(
+              driver,
+              psql_table_by_id
+                .values()
+                .filter(|table| table_ids.contains(&table.id)),
+            )
+            .into_statements(),
+          );
+        }
+
+        statements.extend(match output_format {
+          CherryPickOutputFormatEnum::Copy => {
+            RelationInsert::into_copy_statements(nodes_by_level, &insert_statement_config)?
+          }
+          _ => RelationInsert::into_insert_statements(nodes_by_level, &insert_statement_config)?,
+        });
+
+        match script_config {
+          None => println!("{}", statements.join("\n")),
+          Some(script_config) => {
+            let mut row_count_by_table: BTreeMap<String, usize> = Default::default();
 
-        let statements: Vec<String> =
-          psql::relation_insert::RelationInsert::into_insert_statements(nodes_by_level)?;
-        println!("{}", statements.join("\n"));
+            for row in graph.node_weights() {
+              *row_count_by_table
+                .entry(row.table.id.to_string())
+                .or_default() += 1;
+            }
+
+            let script = InsertScript {
+              driver,
+              config: script_config,
+              header: InsertScriptHeader {
+                source: source_name,
+                root: DbCli::root_description(&schema, table, column, &values),
+                created_at: Utc::now(),
+                row_count_by_table,
+              },
+              statements,
+            };
+
+            print!("{}", script);
+          }
+        }
       }
       CherryPickOutputFormatEnum::Graphviz => {
         let graph = graph.map(
@@ -295,6 +1599,278 @@ impl DbCli {
           GraphDot::with_config(&graph, &[GraphDotConfig::EdgeNoLabel])
         );
       }
+      CherryPickOutputFormatEnum::Html => {
+        let html = GraphHtml::new(&graph, &root_node_indexes).render(
+          &DbCli::root_description(&schema, table, column, &values),
+          |row| PsqlTableRowDynamicVisual::new(row, &displayed_fields_by_table_id).to_string(),
+        )?;
+
+        print!("{}", html);
+      }
+      CherryPickOutputFormatEnum::Template(template_file_path) => {
+        let compiled_template = compiled_template.ok_or_else(|| {
+          return anyhow!("Template {} is not compiled", template_file_path.display());
+        })?;
+
+        let output = GraphTemplate::new(&graph, &root_node_indexes).render(
+          &compiled_template,
+          &DbCli::root_description(&schema, table, column, &values),
+        )?;
+
+        print!("{}", output);
+      }
+    }
+
+    return Ok(());
+  }
+
+  fn describe(
+    db_config: &DbConnectionConfig,
+    metadata_cache_file_path: PathBuf,
+    refresh_metadata: bool,
+    table_id: &PsqlTableIdentity,
+  ) -> ResultAnyError<()> {
+    let (_, db_metadata, _) = DbCli::connect(db_config, vec![])?;
+    let db_metadata =
+      CachedDbMetadata::new(db_metadata, metadata_cache_file_path, refresh_metadata);
+    let psql_table_by_id = db_metadata.load_table_structure(&table_id.schema)?;
+
+    let table = psql_table_by_id
+      .get(table_id)
+      .ok_or_else(|| anyhow!("Table {} not found", table_id))?;
+
+    print!("{}", TableDescription::new(table));
+
+    return Ok(());
+  }
+
+  fn apply(
+    db_config: &DbConnectionConfig,
+    metadata_cache_file_path: PathBuf,
+    file_path: &Path,
+  ) -> ResultAnyError<()> {
+    let (_, db_metadata, statement_executor) = DbCli::connect(db_config, vec![])?;
+    let is_snapshot = file_path
+      .extension()
+      .map(|extension| extension.eq_ignore_ascii_case("json"))
+      .unwrap_or(false);
+
+    let statements: Vec<ApplyStatement> = if is_snapshot {
+      let snapshot = TableMetadataSnapshot::load(file_path)?;
+
+      ApplyStatement::from_rows(
+        snapshot.rows()?,
+        &InsertStatementConfig::new(db_config.driver),
+      )?
+    } else {
+      let script = fs::read_to_string(file_path).map_err(|err| {
+        return anyhow!("Could not read {}, error: {}", file_path.display(), err);
+      })?;
+
+      // Only needed to label the rows with their primary key
+      let psql_table_by_id = CachedDbMetadata::new(db_metadata, metadata_cache_file_path, false)
+        .load_table_structure(db_config.default_schema())?;
+
+      ApplyStatement::from_sql_script(db_config.driver, &script, &psql_table_by_id)?
+    };
+
+    let report = StatementApply::new(statement_executor.as_ref()).apply(statements);
+
+    print!("{}", report);
+
+    if report.failed_count() > 0 {
+      return Err(anyhow!(
+        "Failed to apply {} statements",
+        report.failed_count()
+      ));
+    }
+
+    return Ok(());
+  }
+
+  fn load_order(
+    db_config: &DbConnectionConfig,
+    metadata_cache_file_path: PathBuf,
+    refresh_metadata: bool,
+    schema: &str,
+    output_format: &str,
+  ) -> ResultAnyError<()> {
+    let (_, db_metadata, _) = DbCli::connect(db_config, vec![])?;
+    let db_metadata =
+      CachedDbMetadata::new(db_metadata, metadata_cache_file_path, refresh_metadata);
+    let psql_table_by_id = db_metadata.load_table_structure(schema)?;
+    let load_order = LoadOrder::new(&psql_table_by_id);
+
+    DbCli::warn_tables_without_primary_key(&db_metadata)?;
+
+    let tables: Vec<&PsqlTable> = match output_format {
+      "load" => load_order.tables.clone(),
+      "truncate" => load_order.truncate_order(),
+      _ => {
+        print!("{}", load_order);
+
+        return Ok(());
+      }
+    };
+
+    for table in tables {
+      println!("{}", table.id);
+    }
+
+    return Ok(());
+  }
+
+  fn schema_diff(input: SchemaDiffInput) -> ResultAnyError<()> {
+    let SchemaDiffInput {
+      source_db_config,
+      source_metadata_cache_file_path,
+      target_db_config,
+      target_metadata_cache_file_path,
+      schema,
+      migration_sql,
+      refresh_metadata,
+    } = input;
+
+    if source_db_config.driver != target_db_config.driver {
+      return Err(anyhow!(
+        "Cannot compare a {:?} db with a {:?} db",
+        source_db_config.driver,
+        target_db_config.driver
+      ));
+    }
+
+    let load_table_structure = |db_config: &DbConnectionConfig,
+                                metadata_cache_file_path: PathBuf|
+     -> ResultAnyError<HashMap<PsqlTableIdentity, PsqlTable>> {
+      let (_, db_metadata, _) = DbCli::connect(db_config, vec![])?;
+      let db_metadata =
+        CachedDbMetadata::new(db_metadata, metadata_cache_file_path, refresh_metadata);
+      let table_by_id =
+        db_metadata.load_table_structure(schema.unwrap_or_else(|| db_config.default_schema()))?;
+
+      DbCli::warn_tables_without_primary_key(&db_metadata)?;
+
+      return Ok(table_by_id);
+    };
+
+    let target_schema = schema.unwrap_or_else(|| target_db_config.default_schema());
+    let source_table_by_id = SchemaDiff::rebase_tables(
+      load_table_structure(&source_db_config, source_metadata_cache_file_path)?,
+      schema.unwrap_or_else(|| source_db_config.default_schema()),
+      target_schema,
+    );
+    let target_table_by_id =
+      load_table_structure(&target_db_config, target_metadata_cache_file_path)?;
+
+    let schema_diff = SchemaDiff::new(
+      target_db_config.driver,
+      &source_table_by_id,
+      &target_table_by_id,
+    );
+
+    if !migration_sql {
+      print!("{}", schema_diff);
+
+      return Ok(());
+    }
+
+    // Keep the output runnable as a sql script
+    for line in schema_diff.to_string().lines() {
+      println!("{}", format!("-- {}", line).trim_end());
+    }
+
+    println!();
+
+    for statement in schema_diff.migration_statements() {
+      println!("{}", statement);
+    }
+
+    return Ok(());
+  }
+
+  fn integrity_check(input: IntegrityCheckInput) -> ResultAnyError<()> {
+    let IntegrityCheckInput {
+      db_config,
+      metadata_cache_file_path,
+      schema,
+      sample_size,
+      include_declared,
+      refresh_metadata,
+    } = input;
+
+    // Row filters would hide orphans, every row is checked
+    let (table_metadata, db_metadata, _) = DbCli::connect(&db_config, vec![])?;
+    let psql_table_by_id =
+      CachedDbMetadata::new(db_metadata, metadata_cache_file_path, refresh_metadata)
+        .load_table_structure(&schema)?;
+
+    let checked_relations = CheckedRelation::collect(
+      &psql_table_by_id,
+      &db_config.virtual_foreign_keys,
+      &schema,
+      include_declared,
+    );
+    let report = IntegrityCheck::new(table_metadata.as_ref(), sample_size).check(checked_relations);
+
+    print!("{}", report);
+
+    if report.orphan_row_count() > 0 {
+      return Err(anyhow!("Found {} orphaned rows", report.orphan_row_count()));
+    }
+
+    if report.failed_count() > 0 {
+      return Err(anyhow!(
+        "Failed to check {} relations",
+        report.failed_count()
+      ));
+    }
+
+    return Ok(());
+  }
+
+  fn seed(input: SeedInput) -> ResultAnyError<()> {
+    let SeedInput {
+      db_config,
+      metadata_cache_file_path,
+      schema,
+      table,
+      seed_config,
+      apply,
+    } = input;
+
+    let (table_metadata, db_metadata, statement_executor) = DbCli::connect(&db_config, vec![])?;
+    let db_metadata = CachedDbMetadata::new(db_metadata, metadata_cache_file_path, false);
+    let psql_table_by_id = db_metadata.load_table_structure(&schema)?;
+
+    let rows_by_level =
+      SeedRowGenerator::new(table_metadata.as_ref(), &psql_table_by_id, seed_config)
+        .generate(&PsqlTableIdentity::new(schema.as_str(), table))?;
+
+    let statements = psql::relation_insert::RelationInsert::into_insert_statements(
+      rows_by_level
+        .iter()
+        .map(|(level, rows)| (*level, rows.iter().collect()))
+        .collect(),
+      &InsertStatementConfig::new(db_config.driver),
+    )?;
+
+    if !apply {
+      println!("{}", statements.join("\n"));
+
+      return Ok(());
+    }
+
+    statement_executor.execute_in_transaction(&statements)?;
+
+    for (level, rows) in rows_by_level.iter() {
+      if let Some(row) = rows.first() {
+        eprintln!(
+          "Inserted {} rows into {}{}",
+          rows.len(),
+          row.table.id,
+          if *level == 0 { "" } else { " (parent)" }
+        );
+      }
     }
 
     return Ok(());
@@ -326,7 +1902,7 @@ impl<'a> std::fmt::Debug for PsqlTableRowDynamicVisual<'a> {
 
 impl<'a> std::fmt::Display for PsqlTableRowDynamicVisual<'a> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    let value_by_column: HashMap<&str, FromSqlSink> = self.inner.get_column_value_map();
+    let value_by_column: HashMap<&str, &FromSqlSink> = self.inner.get_column_value_map();
     let mut label: String = format!("`id` {}", self.inner.row_id_representation);
 
     if let Some(fields) = self.displayed_fields_by_table_id.get(&self.inner.table.id) {
@@ -376,23 +1952,172 @@ impl<'a> std::fmt::Display for PsqlTableRowDynamicVisual<'a> {
 
 /// Helper function
 impl DbCli {
+  /// Connect to the given db and create the driver specific
+  /// table metadata, db metadata and statement executor.
+  pub fn connect(
+    db_config: &DbConnectionConfig,
+    row_filters: Vec<RowFilter>,
+  ) -> ResultAnyError<DbAccess> {
+    return match db_config.driver {
+      DbDriver::Postgres => {
+        let psql = Rc::new(RefCell::new(PsqlConnection::new(&DbCli::psql_creds(
+          db_config,
+        ))?));
+        let db_metadata = DbMetadataImpl::new(psql.clone());
+
+        Ok((
+          Box::new(TableMetadataImpl::new(psql.clone(), row_filters)),
+          Box::new(db_metadata),
+          Box::new(PsqlStatementExecutor::new(psql)),
+        ))
+      }
+      DbDriver::Mysql => {
+        let db_creds = MysqlCreds {
+          host: db_config.host.clone(),
+          port: db_config.port,
+          database_name: db_config.database.clone(),
+          username: db_config.username.clone(),
+          password: db_config.password.clone(),
+          read_only: db_config.read_only,
+          statement_timeout_ms: db_config.session_statement_timeout_ms(),
+        };
+
+        let mysql = Rc::new(RefCell::new(MysqlConnection::new(&db_creds)?));
+        let db_metadata = MysqlDbMetadata::new(mysql.clone());
+
+        Ok((
+          Box::new(MysqlTableMetadata::new(mysql.clone(), row_filters)),
+          Box::new(db_metadata),
+          Box::new(MysqlStatementExecutor::new(mysql)),
+        ))
+      }
+    };
+  }
+
+  /// Tables are loaded by their primary key, the ones without it are listed so they are not missed
+  fn warn_tables_without_primary_key(db_metadata: &dyn DbMetadata) -> ResultAnyError<()> {
+    for table_id in db_metadata.tables_without_primary_key()? {
+      eprintln!("Warning: {} has no primary key, it's skipped", table_id);
+    }
+
+    return Ok(());
+  }
+
+  fn psql_creds(db_config: &DbConnectionConfig) -> PsqlCreds {
+    return PsqlCreds {
+      host: db_config.host.clone(),
+      database_name: db_config.database.clone(),
+      username: db_config.username.clone(),
+      password: db_config.password.clone(),
+      read_only: db_config.read_only,
+      statement_timeout_ms: db_config.session_statement_timeout_ms(),
+    };
+  }
+
   pub fn fetch_relation_graph(
-    psql: Rc<RefCell<PsqlConnection>>,
+    mut relation_fetcher: RelationFetcher,
     psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
     table: &str,
-    values: Vec<String>,
+    values: &[String],
     column: &str,
     schema: &str,
-  ) -> ResultAnyError<(RowGraph, NodeIndex)> {
-    let table_metadata = Box::new(TableMetadataImpl::new(psql));
-    let mut relation_fetcher = psql::relation_fetcher::RelationFetcher::new(table_metadata);
-
-    let input = psql::relation_fetcher::FetchRowsAsRoseTreeInput {
-      table_id: &PsqlTableIdentity::new(schema, table),
-      column_name: &column,
-      column_value: values.get(0).unwrap(), // As of now only supports 1 value
+  ) -> ResultAnyError<(RowGraph, Vec<NodeIndex>)> {
+    let table_id = PsqlTableIdentity::new(schema, table);
+
+    // The db cli runs on a blocking thread, the fetch gets its own single threaded runtime
+    // since the fetched rows can't be sent across threads.
+    let runtime = tokio::runtime::Builder::new_current_thread()
+      .enable_all()
+      .build()?;
+
+    let (graph, node_indexes) = runtime.block_on(relation_fetcher.fetch_roots_as_graph(
+      &table_id,
+      column,
+      values,
+      psql_table_by_id,
+      |fetched_count, value| {
+        // Printed to stderr so that the output can still be piped
+        if values.len() > 1 {
+          eprintln!(
+            "[{}/{}] {}.{} = {}",
+            fetched_count,
+            values.len(),
+            table_id,
+            column,
+            value
+          );
+        }
+      },
+    ))?;
+
+    // Printed to stderr so that the output can still be piped
+    for truncated_relation in relation_fetcher.truncated_relations().iter() {
+      eprintln!(
+        "Truncated {} rows of {} referencing through {}",
+        truncated_relation.truncated_row_count,
+        truncated_relation.table_id,
+        truncated_relation.fk_column_name
+      );
+    }
+
+    for missing_parent_relation in relation_fetcher.missing_parent_relations().iter() {
+      eprintln!(
+        "Warning: {} rows of {} reference a {} row through {} that is not found, it's filtered out by the row filters or it does not exist, inserting them will violate the foreign key",
+        missing_parent_relation.row_count,
+        missing_parent_relation.table_id,
+        missing_parent_relation.foreign_table_id,
+        missing_parent_relation.fk_column_name
+      );
+    }
+
+    return Ok((graph, node_indexes));
+  }
+
+  /// `-` reads the values from stdin
+  fn read_root_values(
+    values_file_path: &str,
+    column: &str,
+    values_column: Option<&str>,
+  ) -> ResultAnyError<Vec<String>> {
+    let content = if values_file_path == "-" {
+      let mut content = String::new();
+
+      std::io::stdin()
+        .read_to_string(&mut content)
+        .map_err(|err| anyhow!("Could not read values from stdin, error: {}", err))?;
+
+      content
+    } else {
+      fs::read_to_string(values_file_path).map_err(|err| {
+        return anyhow!("Could not read {}, error: {}", values_file_path, err);
+      })?
     };
 
-    return relation_fetcher.fetch_as_graphs(input, psql_table_by_id);
+    return RootValues::parse(&content, column, values_column);
+  }
+
+  /// Label of the cherry-picked roots, long value lists are shortened
+  fn root_description(schema: &str, table: &str, column: &str, values: &[String]) -> String {
+    let mut displayed_values = values
+      .iter()
+      .take(MAX_DESCRIBED_ROOT_VALUES)
+      .cloned()
+      .collect::<Vec<String>>()
+      .join(", ");
+
+    if values.len() > MAX_DESCRIBED_ROOT_VALUES {
+      displayed_values = format!(
+        "{} and {} more",
+        displayed_values,
+        values.len() - MAX_DESCRIBED_ROOT_VALUES
+      );
+    }
+
+    return format!(
+      "{}.{} = {}",
+      PsqlTableIdentity::new(schema, table),
+      column,
+      displayed_values
+    );
   }
 }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::path::PathBuf;
 
 use serde::Deserialize;
 use serde::Serialize;
@@ -11,16 +12,217 @@ use lezeh_common::types::ResultAnyError;
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
   pub db_connection_by_name: HashMap<String, DbConnectionConfig>,
+
+  /// Directory of the cached db metadata, defaults to ~/.cache/lezeh/db
+  #[serde(default)]
+  pub metadata_cache_dir: Option<String>,
+
+  /// Named cherry-pick arguments, used with `cherry-pick --preset`
+  #[serde(default)]
+  pub cherry_pick_presets: HashMap<String, CherryPickPresetConfig>,
+}
+
+/// Cherry-pick arguments stored under a name, arguments passed to the cli override them.
+/// List values use the same format as their cli argument.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CherryPickPresetConfig {
+  #[serde(default)]
+  pub source_db: Option<String>,
+
+  #[serde(default)]
+  pub schema: Option<String>,
+
+  #[serde(default)]
+  pub table: Option<String>,
+
+  #[serde(default)]
+  pub column: Option<String>,
+
+  #[serde(default)]
+  pub output_format: Option<String>,
+
+  #[serde(default)]
+  pub template: Option<String>,
+
+  #[serde(default)]
+  pub max_children: Option<usize>,
+
+  #[serde(default)]
+  pub max_children_by: Vec<String>,
+
+  #[serde(default)]
+  pub children_order_by: Vec<String>,
+
+  #[serde(default)]
+  pub graph_table_columns: Vec<String>,
+
+  #[serde(default)]
+  pub exclude_columns: Vec<String>,
+
+  #[serde(default)]
+  pub set_columns: Vec<String>,
+
+  #[serde(default)]
+  pub script: bool,
+
+  #[serde(default)]
+  pub disable_triggers: bool,
+
+  #[serde(default)]
+  pub defer_constraints: bool,
+
+  #[serde(default)]
+  pub with_ddl: bool,
+
+  #[serde(default)]
+  pub no_row_filters: bool,
 }
 
 /// DB Related Command Config
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DbConnectionConfig {
+  #[serde(default)]
+  pub driver: DbDriver,
   pub host: String,
   pub port: u32,
   pub database: String,
   pub username: String,
   pub password: Option<String>,
+
+  /// Columns that cherry-pick will not insert, keyed by table
+  #[serde(default)]
+  pub exclude_columns: HashMap<String, Vec<String>>,
+
+  /// Raw sql values that cherry-pick inserts instead of the fetched values,
+  /// keyed by table then column
+  #[serde(default)]
+  pub set_columns: HashMap<String, HashMap<String, String>>,
+
+  /// Predicates that every fetched relation row must satisfy
+  #[serde(default)]
+  pub row_filters: Vec<RowFilterConfig>,
+
+  /// Relations that are not declared as foreign keys, checked by integrity-check
+  #[serde(default)]
+  pub virtual_foreign_keys: Vec<VirtualForeignKeyConfig>,
+
+  /// Label of the connection, e.g. `prod`. Commands that write
+  /// into a prod connection require `--confirm-prod`.
+  #[serde(default)]
+  pub environment: Option<String>,
+
+  /// Every session is read only, commands that write into the db are rejected
+  #[serde(default)]
+  pub read_only: bool,
+
+  /// Statement timeout of every session, read only sessions default to 30 seconds
+  #[serde(default)]
+  pub statement_timeout_ms: Option<u64>,
+
+  /// Max relations that cherry-pick fetches concurrently, each one uses its own connection.
+  /// Only applies to postgres, defaults to 4.
+  #[serde(default)]
+  pub fetch_parallelism: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RowFilterConfig {
+  /// Only applies to this table, applies to every table if not set
+  #[serde(default)]
+  pub table: Option<String>,
+
+  /// Only applies to tables that contain this column
+  #[serde(default)]
+  pub column: Option<String>,
+
+  /// Raw sql predicate, e.g. `deleted_at IS NULL`
+  pub predicate: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VirtualForeignKeyConfig {
+  /// Child table, optionally prefixed with the schema
+  pub table: String,
+  pub column: String,
+
+  /// Parent table, optionally prefixed with the schema
+  pub foreign_table: String,
+
+  /// Defaults to the primary column of the parent table
+  #[serde(default)]
+  pub foreign_column: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DbDriver {
+  Postgres,
+  Mysql,
+}
+
+impl Default for DbDriver {
+  fn default() -> Self {
+    return DbDriver::Postgres;
+  }
+}
+
+const PROD_ENVIRONMENTS: [&str; 2] = ["prod", "production"];
+const DEFAULT_READ_ONLY_STATEMENT_TIMEOUT_MS: u64 = 30_000;
+const DEFAULT_FETCH_PARALLELISM: usize = 4;
+
+impl DbConnectionConfig {
+  pub fn is_prod(&self) -> bool {
+    return self
+      .environment
+      .as_ref()
+      .map(|environment| {
+        return PROD_ENVIRONMENTS
+          .iter()
+          .any(|prod_environment| environment.eq_ignore_ascii_case(prod_environment));
+      })
+      .unwrap_or(false);
+  }
+
+  pub fn session_statement_timeout_ms(&self) -> Option<u64> {
+    if self.read_only {
+      return self
+        .statement_timeout_ms
+        .or(Some(DEFAULT_READ_ONLY_STATEMENT_TIMEOUT_MS));
+    }
+
+    return self.statement_timeout_ms;
+  }
+
+  pub fn fetch_parallelism(&self) -> usize {
+    return self.fetch_parallelism.unwrap_or(DEFAULT_FETCH_PARALLELISM);
+  }
+
+  /// Commands that write into the db check this before connecting
+  pub fn ensure_writable(&self, db_name: &str, confirm_prod: bool) -> Result<(), ConfigError> {
+    if self.read_only {
+      return Err(ConfigError::ReadOnlyConnectionError {
+        db_name: db_name.to_owned(),
+      });
+    }
+
+    if self.is_prod() && !confirm_prod {
+      return Err(ConfigError::UnconfirmedProdWriteError {
+        db_name: db_name.to_owned(),
+        environment: self.environment.clone().unwrap_or_default(),
+      });
+    }
+
+    return Ok(());
+  }
+
+  /// Schema that will be used when the user does not pass one,
+  /// mysql does not have schemas inside a database so we use the database itself.
+  pub fn default_schema(&self) -> &str {
+    return match self.driver {
+      DbDriver::Postgres => "public",
+      DbDriver::Mysql => &self.database,
+    };
+  }
 }
 
 impl Config {
@@ -41,6 +243,18 @@ impl Config {
 
     return Ok(config);
   }
+
+  pub fn metadata_cache_file_path(&self, connection_name: &str) -> PathBuf {
+    let cache_dir: PathBuf = self
+      .metadata_cache_dir
+      .as_ref()
+      .map(PathBuf::from)
+      .unwrap_or_else(|| {
+        return PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".cache/lezeh/db");
+      });
+
+    return cache_dir.join(format!("{}.json", connection_name));
+  }
 }
 
 #[derive(Error, Debug)]
@@ -56,4 +270,54 @@ pub enum ConfigError {
     config_path: String,
     root_err: String,
   },
+
+  #[error("Db {db_name} is configured as read only")]
+  ReadOnlyConnectionError { db_name: String },
+
+  #[error("Db {db_name} is a {environment} connection, pass --confirm-prod to write into it")]
+  UnconfirmedProdWriteError {
+    db_name: String,
+    environment: String,
+  },
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  mod ensure_writable {
+    use super::*;
+
+    fn create_db_config(extra_config: &str) -> DbConnectionConfig {
+      let config_str = format!(
+        "host: localhost\nport: 5432\ndatabase: db\nusername: user\n{}",
+        extra_config
+      );
+
+      return serde_yaml::from_str(&config_str).unwrap();
+    }
+
+    #[test]
+    fn it_should_reject_read_only_and_unconfirmed_prod_connections() {
+      let read_only_config = create_db_config("read_only: true");
+      let prod_config = create_db_config("environment: PROD");
+
+      assert_eq!(
+        read_only_config.session_statement_timeout_ms(),
+        Some(30_000)
+      );
+      assert!(matches!(
+        read_only_config.ensure_writable("replica", true),
+        Err(ConfigError::ReadOnlyConnectionError { .. })
+      ));
+      assert!(matches!(
+        prod_config.ensure_writable("proddb", false),
+        Err(ConfigError::UnconfirmedProdWriteError { .. })
+      ));
+      assert!(prod_config.ensure_writable("proddb", true).is_ok());
+      assert!(create_db_config("")
+        .ensure_writable("localdb", false)
+        .is_ok());
+    }
+  }
 }
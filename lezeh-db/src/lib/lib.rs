@@ -1,3 +1,5 @@
+pub mod asset;
 pub mod cli;
 pub mod config;
+pub mod mysql;
 pub mod psql;
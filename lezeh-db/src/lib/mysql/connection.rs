@@ -0,0 +1,154 @@
+use std::convert::TryFrom;
+
+use ::mysql::prelude::Queryable;
+use ::mysql::Conn as MysqlClient;
+use ::mysql::OptsBuilder as MysqlOptsBuilder;
+
+use lezeh_common::types::ResultAnyError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum MysqlConnectionError {
+  #[error("Error when initialization connection {0}")]
+  InitializeConnectionError(String),
+}
+
+pub struct MysqlConnection {
+  client: MysqlClient,
+}
+
+pub struct MysqlCreds {
+  pub host: String,
+  pub port: u32,
+  pub database_name: String,
+  pub username: String,
+  pub password: Option<String>,
+
+  /// Sets the session transaction as `READ ONLY` so the session can't write
+  pub read_only: bool,
+
+  /// Applied with `MAX_EXECUTION_TIME` that only limits select statements,
+  /// MariaDB applies it to every statement with `max_statement_time`
+  pub statement_timeout_ms: Option<u64>,
+}
+
+impl MysqlConnection {
+  pub fn new(creds: &MysqlCreds) -> ResultAnyError<MysqlConnection> {
+    let port = u16::try_from(creds.port).map_err(|err| {
+      return MysqlConnectionError::InitializeConnectionError(format!(
+        "Invalid port {}: {}",
+        creds.port, err
+      ));
+    })?;
+
+    let opts = MysqlOptsBuilder::new()
+      .ip_or_hostname(Some(creds.host.clone()))
+      .tcp_port(port)
+      .user(Some(creds.username.clone()))
+      .pass(creds.password.clone())
+      .db_name(Some(creds.database_name.clone()));
+
+    let mut client = MysqlClient::new(opts).map_err(|err| {
+      return MysqlConnectionError::InitializeConnectionError(err.to_string());
+    })?;
+
+    // MariaDB has its own statement timeout variable
+    let server_version: String = match creds.statement_timeout_ms {
+      None => Default::default(),
+      Some(_) => client
+        .query_first::<String, _>("SELECT VERSION()")
+        .map_err(|err| {
+          return MysqlConnectionError::InitializeConnectionError(err.to_string());
+        })?
+        .unwrap_or_default(),
+    };
+
+    for session_statement in creds.session_statements(&server_version) {
+      client.query_drop(session_statement).map_err(|err| {
+        return MysqlConnectionError::InitializeConnectionError(err.to_string());
+      })?;
+    }
+
+    return Ok(MysqlConnection { client });
+  }
+}
+
+impl MysqlCreds {
+  /// Statements that are run right after connecting, `server_version`
+  /// is the result of `SELECT VERSION()`.
+  pub fn session_statements(&self, server_version: &str) -> Vec<String> {
+    let mut session_statements: Vec<String> = vec![];
+
+    if self.read_only {
+      session_statements.push("SET SESSION TRANSACTION READ ONLY".to_owned());
+    }
+
+    if let Some(statement_timeout_ms) = self.statement_timeout_ms {
+      if server_version.contains("MariaDB") {
+        session_statements.push(format!(
+          "SET SESSION max_statement_time = {}",
+          statement_timeout_ms as f64 / 1000.0
+        ));
+      } else {
+        session_statements.push(format!(
+          "SET SESSION MAX_EXECUTION_TIME = {}",
+          statement_timeout_ms
+        ));
+      }
+    }
+
+    return session_statements;
+  }
+}
+
+impl MysqlConnection {
+  pub fn get(&mut self) -> &mut MysqlClient {
+    return &mut self.client;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  mod session_statements {
+    use super::*;
+
+    fn create_creds(statement_timeout_ms: Option<u64>) -> MysqlCreds {
+      return MysqlCreds {
+        host: "localhost".to_owned(),
+        port: 3306,
+        database_name: "shop".to_owned(),
+        username: "root".to_owned(),
+        password: None,
+        read_only: true,
+        statement_timeout_ms,
+      };
+    }
+
+    #[test]
+    fn it_should_limit_the_execution_time_on_mysql() {
+      assert_eq!(
+        create_creds(Some(30000)).session_statements("8.0.36"),
+        vec![
+          "SET SESSION TRANSACTION READ ONLY",
+          "SET SESSION MAX_EXECUTION_TIME = 30000",
+        ]
+      );
+    }
+
+    #[test]
+    fn it_should_limit_the_statement_time_in_seconds_on_mariadb() {
+      assert_eq!(
+        create_creds(Some(1500)).session_statements("10.11.6-MariaDB-0+deb12u1"),
+        vec![
+          "SET SESSION TRANSACTION READ ONLY",
+          "SET SESSION max_statement_time = 1.5",
+        ]
+      );
+      assert_eq!(
+        create_creds(None).session_statements(""),
+        vec!["SET SESSION TRANSACTION READ ONLY"]
+      );
+    }
+  }
+}
@@ -0,0 +1,441 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use ::mysql::prelude::Queryable;
+
+use crate::mysql::connection::MysqlConnection;
+use crate::psql::db_metadata::psql_table_map_from_column_info_rows;
+use crate::psql::db_metadata::psql_table_map_from_constraint_info_rows;
+use crate::psql::db_metadata::psql_table_map_from_foreign_key_info_rows;
+use crate::psql::db_metadata::CheckConstraintInformationRow;
+use crate::psql::db_metadata::ColumnInformationRow;
+use crate::psql::db_metadata::DbMetadata;
+use crate::psql::db_metadata::ForeignKeyInformationRow;
+use crate::psql::db_metadata::UniqueConstraintInformationRow;
+use crate::psql::dto::*;
+use lezeh_common::types::ResultAnyError;
+
+/// Selected columns of `COLUMN_QUERY` in order
+type ColumnQueryRow = (
+  String,
+  String,
+  String,
+  String,
+  bool,
+  Option<String>,
+  bool,
+  i64,
+  Option<i64>,
+  String,
+  Option<String>,
+  bool,
+);
+
+const SYSTEM_SCHEMAS: &str = "('mysql', 'information_schema', 'performance_schema', 'sys')";
+
+const TABLE_WITH_FK_QUERY: &str = "
+    SELECT
+      kcu.constraint_name,
+      kcu.table_schema,
+      kcu.table_name,
+      kcu.column_name,
+      c.data_type AS column_data_type,
+      kcu.referenced_table_schema AS foreign_table_schema,
+      kcu.referenced_table_name AS foreign_table_name,
+      kcu.referenced_column_name AS foreign_column_name,
+      foreign_c_meta.data_type AS foreign_column_data_type
+    FROM
+      information_schema.key_column_usage AS kcu
+        JOIN information_schema.columns AS c ON
+          c.table_schema = kcu.table_schema AND
+          c.table_name = kcu.table_name AND
+          c.column_name = kcu.column_name
+        JOIN information_schema.columns AS foreign_c_meta ON
+          foreign_c_meta.table_schema = kcu.referenced_table_schema AND
+          foreign_c_meta.table_name = kcu.referenced_table_name AND
+          foreign_c_meta.column_name = kcu.referenced_column_name
+    WHERE kcu.referenced_table_name IS NOT NULL
+";
+
+const TABLE_WITH_PRIMARY_KEY_QUERY: &str = "
+    SELECT
+      kcu.table_schema,
+      kcu.table_name,
+      kcu.column_name AS primary_column_name,
+      c.data_type AS primary_column_data_type
+    FROM
+      information_schema.key_column_usage AS kcu
+        JOIN information_schema.columns AS c ON
+          c.table_schema = kcu.table_schema AND
+          c.table_name = kcu.table_name AND
+          c.column_name = kcu.column_name
+    WHERE kcu.constraint_name = 'PRIMARY'
+";
+
+/// Mysql has no identity columns, auto increment columns accept explicit values.
+const COLUMN_QUERY: &str = "
+    SELECT
+      table_schema,
+      table_name,
+      column_name,
+      data_type,
+      COALESCE(generation_expression, '') <> '' AS is_generated,
+      column_default,
+      is_nullable = 'YES' AS is_nullable,
+      CAST(ordinal_position AS SIGNED) AS ordinal_position,
+      CAST(character_maximum_length AS SIGNED) AS character_maximum_length,
+      column_type,
+      NULLIF(generation_expression, '') AS generation_expression,
+      extra LIKE '%auto_increment%' AS is_auto_increment
+    FROM information_schema.columns
+    WHERE table_schema NOT IN ('mysql', 'information_schema', 'performance_schema', 'sys')
+";
+
+const UNIQUE_CONSTRAINT_QUERY: &str = "
+    SELECT
+      table_schema,
+      table_name,
+      index_name AS constraint_name,
+      GROUP_CONCAT(column_name ORDER BY seq_in_index SEPARATOR ',') AS column_names
+    FROM information_schema.statistics
+    WHERE non_unique = 0 AND
+      index_name <> 'PRIMARY' AND
+      column_name IS NOT NULL AND
+      table_schema NOT IN ('mysql', 'information_schema', 'performance_schema', 'sys')
+    GROUP BY table_schema, table_name, index_name
+";
+
+/// `information_schema.check_constraints` is only available since mysql 8.0.16
+const CHECK_CONSTRAINT_QUERY: &str = "
+    SELECT
+      tc.table_schema,
+      tc.table_name,
+      cc.constraint_name,
+      cc.check_clause
+    FROM
+      information_schema.table_constraints AS tc
+        JOIN information_schema.check_constraints AS cc ON
+          cc.constraint_schema = tc.constraint_schema AND
+          cc.constraint_name = tc.constraint_name
+    WHERE tc.constraint_type = 'CHECK' AND
+      tc.table_schema NOT IN ('mysql', 'information_schema', 'performance_schema', 'sys')
+";
+
+const CHECK_CONSTRAINT_SUPPORT_QUERY: &str = "
+    SELECT COUNT(*)
+    FROM information_schema.tables
+    WHERE table_schema = 'information_schema' AND table_name = 'CHECK_CONSTRAINTS'
+";
+
+const TABLE_WITHOUT_PRIMARY_KEY_QUERY: &str = "
+    SELECT t.table_schema, t.table_name
+    FROM information_schema.tables AS t
+    WHERE t.table_type = 'BASE TABLE' AND
+      NOT EXISTS (
+        SELECT 1
+        FROM information_schema.table_constraints AS tc
+        WHERE tc.table_schema = t.table_schema AND
+          tc.table_name = t.table_name AND
+          tc.constraint_type = 'PRIMARY KEY'
+      )
+";
+
+/// Columns and keys are hashed separately since mysql GROUP_CONCAT
+/// truncates its result, see group_concat_max_len.
+const SCHEMA_FINGERPRINT_QUERY: &str = "
+    SELECT CONCAT_WS(
+      ':',
+      (
+        SELECT CONCAT(COUNT(*), '-', BIT_XOR(CRC32(CONCAT_WS(
+          ':', table_schema, table_name, column_name, ordinal_position,
+          column_type, is_nullable, column_default, extra
+        ))))
+        FROM information_schema.columns
+        WHERE table_schema NOT IN ('mysql', 'information_schema', 'performance_schema', 'sys')
+      ),
+      (
+        SELECT CONCAT(COUNT(*), '-', BIT_XOR(CRC32(CONCAT_WS(
+          ':', table_schema, table_name, constraint_name, column_name,
+          referenced_table_schema, referenced_table_name, referenced_column_name
+        ))))
+        FROM information_schema.key_column_usage
+        WHERE table_schema NOT IN ('mysql', 'information_schema', 'performance_schema', 'sys')
+      ),
+      (
+        SELECT CONCAT(COUNT(*), '-', BIT_XOR(CRC32(CONCAT_WS(
+          ':', table_schema, table_name, index_name, seq_in_index, column_name, non_unique
+        ))))
+        FROM information_schema.statistics
+        WHERE table_schema NOT IN ('mysql', 'information_schema', 'performance_schema', 'sys')
+      ),
+      (
+        SELECT CONCAT(COUNT(*), '-', BIT_XOR(CRC32(CONCAT_WS(
+          ':', table_schema, table_name, constraint_name, constraint_type
+        ))))
+        FROM information_schema.table_constraints
+        WHERE table_schema NOT IN ('mysql', 'information_schema', 'performance_schema', 'sys')
+      )
+    ) AS fingerprint
+";
+
+pub struct Query {
+  connection: Rc<RefCell<MysqlConnection>>,
+}
+
+impl Query {
+  fn fetch_fk_info(&mut self, _schema: &str) -> ResultAnyError<Vec<ForeignKeyInformationRow>> {
+    let query_str = format!(
+      "{} AND kcu.table_schema NOT IN {}",
+      TABLE_WITH_FK_QUERY, SYSTEM_SCHEMAS
+    );
+
+    let fk_info_rows: Vec<ForeignKeyInformationRow> =
+      self.connection.borrow_mut().get().query_map(
+        query_str,
+        |(
+          constraint_name,
+          table_schema,
+          table_name,
+          column_name,
+          column_data_type,
+          foreign_table_schema,
+          foreign_table_name,
+          foreign_column_name,
+          foreign_column_data_type,
+        )| {
+          return ForeignKeyInformationRow {
+            constraint_name,
+            table_schema,
+            table_name,
+            column_name,
+            column_data_type,
+            foreign_table_schema,
+            foreign_table_name,
+            foreign_column_name,
+            foreign_column_data_type,
+            is_validated: true,
+          };
+        },
+      )?;
+
+    return Ok(fk_info_rows);
+  }
+
+  fn fetch_column_info(&mut self) -> ResultAnyError<Vec<ColumnInformationRow>> {
+    let column_info_rows: Vec<ColumnInformationRow> =
+      self.connection.borrow_mut().get().query_map(
+        COLUMN_QUERY,
+        |(
+          table_schema,
+          table_name,
+          column_name,
+          data_type,
+          is_generated,
+          column_default,
+          is_nullable,
+          ordinal_position,
+          character_maximum_length,
+          column_type,
+          generation_expression,
+          is_auto_increment,
+        ): ColumnQueryRow| {
+          return ColumnInformationRow {
+            table_schema,
+            table_name,
+            column: PsqlTableColumn {
+              name: column_name,
+              data_type,
+              is_identity: false,
+              is_generated,
+              default_value: column_default,
+              sequence_name: None,
+              is_nullable,
+              ordinal_position: ordinal_position as i32,
+              // Text columns can be way longer than i32::MAX, e.g. longtext
+              character_maximum_length: character_maximum_length
+                .map(|length| length.min(i32::MAX as i64) as i32),
+              column_type: Some(column_type),
+              enum_type: None,
+              generation_expression,
+              is_auto_increment,
+            },
+          };
+        },
+      )?;
+
+    return Ok(column_info_rows);
+  }
+
+  fn fetch_unique_constraint_info(
+    &mut self,
+  ) -> ResultAnyError<Vec<UniqueConstraintInformationRow>> {
+    return self
+      .connection
+      .borrow_mut()
+      .get()
+      .query_map(
+        UNIQUE_CONSTRAINT_QUERY,
+        |(table_schema, table_name, constraint_name, column_names): (
+          String,
+          String,
+          String,
+          String,
+        )| {
+          return UniqueConstraintInformationRow {
+            table_schema,
+            table_name,
+            constraint: PsqlUniqueConstraint {
+              name: constraint_name,
+              column_names: column_names.split(',').map(ToOwned::to_owned).collect(),
+            },
+          };
+        },
+      )
+      .map_err(anyhow::Error::from);
+  }
+
+  fn fetch_check_constraint_info(&mut self) -> ResultAnyError<Vec<CheckConstraintInformationRow>> {
+    let mut connection = self.connection.borrow_mut();
+    let is_supported: Option<u64> = connection
+      .get()
+      .query_first(CHECK_CONSTRAINT_SUPPORT_QUERY)?;
+
+    if is_supported.unwrap_or_default() == 0 {
+      return Ok(vec![]);
+    }
+
+    return connection
+      .get()
+      .query_map(
+        CHECK_CONSTRAINT_QUERY,
+        |(table_schema, table_name, constraint_name, check_clause): (
+          String,
+          String,
+          String,
+          String,
+        )| {
+          return CheckConstraintInformationRow {
+            table_schema,
+            table_name,
+            constraint: PsqlCheckConstraint {
+              name: constraint_name,
+              definition: format!("CHECK ({})", check_clause),
+            },
+          };
+        },
+      )
+      .map_err(anyhow::Error::from);
+  }
+
+  fn get_table_by_id(&mut self) -> ResultAnyError<HashMap<PsqlTableIdentity, PsqlTable>> {
+    let query_str = format!(
+      "{} AND kcu.table_schema NOT IN {}",
+      TABLE_WITH_PRIMARY_KEY_QUERY, SYSTEM_SCHEMAS
+    );
+
+    let psql_tables: Vec<PsqlTable> = self.connection.borrow_mut().get().query_map(
+      query_str,
+      |(table_schema, table_name, primary_column_name, primary_column_data_type): (
+        String,
+        String,
+        String,
+        String,
+      )| {
+        return PsqlTable::new(
+          table_schema,
+          table_name,
+          PsqlTableColumn::new(primary_column_name, primary_column_data_type),
+          Default::default(),
+          Default::default(),
+          Default::default(),
+        );
+      },
+    )?;
+
+    return Ok(
+      psql_tables
+        .into_iter()
+        .map(|psql_table| (psql_table.id.clone(), psql_table))
+        .collect(),
+    );
+  }
+
+  fn fetch_tables_without_primary_key(&mut self) -> ResultAnyError<Vec<PsqlTableIdentity>> {
+    let query_str = format!(
+      "{} AND t.table_schema NOT IN {} ORDER BY t.table_schema, t.table_name",
+      TABLE_WITHOUT_PRIMARY_KEY_QUERY, SYSTEM_SCHEMAS
+    );
+
+    return self
+      .connection
+      .borrow_mut()
+      .get()
+      .query_map(query_str, |(table_schema, table_name): (String, String)| {
+        return PsqlTableIdentity::new(table_schema, table_name);
+      })
+      .map_err(anyhow::Error::from);
+  }
+
+  fn fetch_schema_fingerprint(&mut self) -> ResultAnyError<String> {
+    let fingerprint: Option<String> = self
+      .connection
+      .borrow_mut()
+      .get()
+      .query_first(SCHEMA_FINGERPRINT_QUERY)?;
+
+    return Ok(fingerprint.unwrap_or_default());
+  }
+}
+
+/// Mysql counterpart of [crate::psql::db_metadata::DbMetadataImpl],
+/// relations are loaded from `information_schema.key_column_usage`.
+pub struct MysqlDbMetadata {
+  /// We know that we own this query so it's ok
+  /// to directl borrow_mut() without checking ownership
+  query: RefCell<Query>,
+}
+
+impl MysqlDbMetadata {
+  pub fn new(mysql_connection: Rc<RefCell<MysqlConnection>>) -> MysqlDbMetadata {
+    return MysqlDbMetadata {
+      query: RefCell::new(Query {
+        connection: mysql_connection,
+      }),
+    };
+  }
+}
+
+impl DbMetadata for MysqlDbMetadata {
+  fn load_table_structure(
+    &self,
+    schema: &str,
+  ) -> ResultAnyError<HashMap<PsqlTableIdentity, PsqlTable>> {
+    let fk_info_rows = self.query.borrow_mut().fetch_fk_info(schema)?;
+
+    let column_info_rows = self.query.borrow_mut().fetch_column_info()?;
+
+    let unique_constraint_info_rows = self.query.borrow_mut().fetch_unique_constraint_info()?;
+
+    let check_constraint_info_rows = self.query.borrow_mut().fetch_check_constraint_info()?;
+
+    let mut table_by_id = self.query.borrow_mut().get_table_by_id()?;
+
+    psql_table_map_from_foreign_key_info_rows(&mut table_by_id, &fk_info_rows);
+    psql_table_map_from_column_info_rows(&mut table_by_id, column_info_rows);
+    psql_table_map_from_constraint_info_rows(
+      &mut table_by_id,
+      unique_constraint_info_rows,
+      check_constraint_info_rows,
+    );
+
+    return Ok(table_by_id);
+  }
+
+  fn schema_fingerprint(&self) -> ResultAnyError<String> {
+    return self.query.borrow_mut().fetch_schema_fingerprint();
+  }
+  fn tables_without_primary_key(&self) -> ResultAnyError<Vec<PsqlTableIdentity>> {
+    return self.query.borrow_mut().fetch_tables_without_primary_key();
+  }
+}
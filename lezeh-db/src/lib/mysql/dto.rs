@@ -0,0 +1,224 @@
+use std::convert::TryFrom;
+
+use ::mysql::consts::ColumnType;
+use ::mysql::Column;
+use ::mysql::Value;
+use chrono::{NaiveDate, NaiveTime};
+use postgres_types::Type as PsqlType;
+use rust_decimal::Decimal;
+
+use crate::psql::dto::FromSqlSink;
+use lezeh_common::types::ResultAnyError;
+
+/// Mysql character set id of binary strings,
+/// see `SHOW COLLATION WHERE Charset = 'binary'`.
+const BINARY_CHARSET: u16 = 63;
+
+/// Convert mysql value into [FromSqlSink]. Mysql values are mapped into their closest
+/// postgres type so rows from both drivers can be processed the same way.
+pub fn from_sql_sink(value: &Value, column: &Column) -> ResultAnyError<FromSqlSink> {
+  return match value {
+    Value::NULL => Ok(FromSqlSink::null()),
+    Value::Int(val) => FromSqlSink::from_value(val, PsqlType::INT8),
+    Value::UInt(val) => match i64::try_from(*val) {
+      Ok(val) => FromSqlSink::from_value(&val, PsqlType::INT8),
+      Err(_) => FromSqlSink::from_value(&Decimal::from(*val), PsqlType::NUMERIC),
+    },
+    Value::Float(val) => FromSqlSink::from_value(val, PsqlType::FLOAT4),
+    Value::Double(val) => FromSqlSink::from_value(val, PsqlType::FLOAT8),
+    Value::Date(year, month, day, hour, minute, second, micro_second) => {
+      let date = NaiveDate::from_ymd_opt(*year as i32, *month as u32, *day as u32);
+      let date_time = date.and_then(|date| {
+        return date.and_hms_micro_opt(*hour as u32, *minute as u32, *second as u32, *micro_second);
+      });
+
+      match (column.column_type(), date, date_time) {
+        (ColumnType::MYSQL_TYPE_DATE, Some(date), _)
+        | (ColumnType::MYSQL_TYPE_NEWDATE, Some(date), _) => {
+          FromSqlSink::from_value(&date, PsqlType::DATE)
+        }
+        (_, _, Some(date_time)) => FromSqlSink::from_value(&date_time, PsqlType::TIMESTAMP),
+
+        // Zero dates such as 0000-00-00 can not be represented by chrono
+        _ => FromSqlSink::from_value(
+          &format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}",
+            year, month, day, hour, minute, second, micro_second
+          ),
+          PsqlType::TEXT,
+        ),
+      }
+    }
+    Value::Time(is_negative, days, hours, minutes, seconds, micro_seconds) => {
+      let time = NaiveTime::from_hms_micro_opt(
+        *hours as u32,
+        *minutes as u32,
+        *seconds as u32,
+        *micro_seconds,
+      );
+
+      match time {
+        Some(time) if !is_negative && *days == 0 => FromSqlSink::from_value(&time, PsqlType::TIME),
+
+        // Mysql time could be negative or longer than 24 hours
+        _ => FromSqlSink::from_value(
+          &format!(
+            "{}{:02}:{:02}:{:02}.{:06}",
+            if *is_negative { "-" } else { "" },
+            days * 24 + *hours as u32,
+            minutes,
+            seconds,
+            micro_seconds
+          ),
+          PsqlType::TEXT,
+        ),
+      }
+    }
+    Value::Bytes(bytes) => match column.column_type() {
+      ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+        let decimal = std::str::from_utf8(bytes)
+          .map_err(anyhow::Error::from)
+          .and_then(|val| val.parse::<Decimal>().map_err(anyhow::Error::from));
+
+        match decimal {
+          Ok(decimal) => FromSqlSink::from_value(&decimal, PsqlType::NUMERIC),
+
+          // Decimal with precision that could not fit into rust decimal
+          Err(_) => FromSqlSink::from_value(&String::from_utf8(bytes.clone())?, PsqlType::TEXT),
+        }
+      }
+      ColumnType::MYSQL_TYPE_JSON => {
+        FromSqlSink::from_value(&String::from_utf8(bytes.clone())?, PsqlType::TEXT)
+      }
+      _ if column.character_set() == BINARY_CHARSET => {
+        FromSqlSink::from_value(bytes, PsqlType::BYTEA)
+      }
+      _ => match String::from_utf8(bytes.clone()) {
+        Ok(text) => FromSqlSink::from_value(&text, PsqlType::TEXT),
+        Err(_) => FromSqlSink::from_value(bytes, PsqlType::BYTEA),
+      },
+    },
+  };
+}
+
+/// Convert [FromSqlSink] into mysql query param.
+pub fn mysql_value_from_sink(sink: &FromSqlSink) -> ResultAnyError<Value> {
+  let text = sink.to_text()?;
+
+  return match (sink.get_type(), text) {
+    (None, _) | (_, None) => Ok(Value::NULL),
+    (Some(&PsqlType::INT2), Some(text))
+    | (Some(&PsqlType::INT4), Some(text))
+    | (Some(&PsqlType::INT8), Some(text)) => Ok(Value::Int(text.parse::<i64>()?)),
+    (Some(&PsqlType::BYTEA), _) => Ok(Value::Bytes(sink.get_raw().to_vec())),
+    (_, Some(text)) => Ok(Value::Bytes(text.into_bytes())),
+  };
+}
+
+pub fn escape_string(val: &str) -> String {
+  // Backslash is an escape character in mysql string literal
+  // unless NO_BACKSLASH_ESCAPES sql mode is enabled.
+  return format!("'{}'", val.replace('\\', "\\\\").replace('\'', "''"));
+}
+
+pub fn escape_identifier(val: &str) -> String {
+  return format!("`{}`", val.replace('`', "``"));
+}
+
+/// Mysql counterpart of [FromSqlSink::to_string_for_statement]
+pub fn to_string_for_statement(sink: &FromSqlSink) -> ResultAnyError<String> {
+  if sink.get_type() == Some(&PsqlType::BYTEA) {
+    return Ok(format!(
+      "X'{}'",
+      sink
+        .get_raw()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>()
+    ));
+  }
+
+  return match sink.to_text()? {
+    None => Ok("null".into()),
+    Some(text) if sink.is_unquoted_literal() => Ok(text),
+    Some(text) => Ok(escape_string(&text)),
+  };
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  mod escape_string {
+    use super::*;
+
+    #[test]
+    fn it_should_escape_quote_and_backslash() {
+      assert_eq!(escape_string("it's"), "'it''s'");
+      assert_eq!(escape_string("c:\\temp"), "'c:\\\\temp'");
+    }
+  }
+
+  mod to_string_for_statement {
+    use super::*;
+
+    #[test]
+    fn it_should_not_quote_numbers() -> ResultAnyError<()> {
+      let sink = FromSqlSink::from_value(&123_i64, PsqlType::INT8)?;
+
+      assert_eq!(to_string_for_statement(&sink)?, "123");
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_render_null() -> ResultAnyError<()> {
+      assert_eq!(to_string_for_statement(&FromSqlSink::null())?, "null");
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_escape_text() -> ResultAnyError<()> {
+      let sink = FromSqlSink::from_value(&"O'Reilly \\ co", PsqlType::TEXT)?;
+
+      assert_eq!(to_string_for_statement(&sink)?, "'O''Reilly \\\\ co'");
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_render_bytes_as_hex() -> ResultAnyError<()> {
+      let sink = FromSqlSink::from_value(&vec![0xde_u8, 0xad], PsqlType::BYTEA)?;
+
+      assert_eq!(to_string_for_statement(&sink)?, "X'dead'");
+
+      return Ok(());
+    }
+  }
+
+  mod mysql_value_from_sink {
+    use super::*;
+
+    #[test]
+    fn it_should_convert_integer() -> ResultAnyError<()> {
+      let sink = FromSqlSink::from_value(&10_i32, PsqlType::INT4)?;
+
+      assert_eq!(mysql_value_from_sink(&sink)?, Value::Int(10));
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_convert_text() -> ResultAnyError<()> {
+      let sink = FromSqlSink::from_value(&"abc", PsqlType::VARCHAR)?;
+
+      assert_eq!(
+        mysql_value_from_sink(&sink)?,
+        Value::Bytes("abc".as_bytes().to_vec())
+      );
+
+      return Ok(());
+    }
+  }
+}
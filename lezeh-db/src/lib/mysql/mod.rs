@@ -0,0 +1,5 @@
+pub mod connection;
+pub mod db_metadata;
+pub mod dto;
+pub mod statement_executor;
+pub mod table_metadata;
@@ -0,0 +1,62 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use ::mysql::prelude::Queryable;
+use ::mysql::TxOpts;
+
+use crate::mysql::connection::MysqlConnection;
+use crate::psql::statement_executor::{StatementError, StatementExecutor};
+use lezeh_common::types::ResultAnyError;
+
+const DUPLICATE_ENTRY_ERROR_CODE: u16 = 1062;
+
+pub struct MysqlStatementExecutor {
+  connection: Rc<RefCell<MysqlConnection>>,
+}
+
+impl MysqlStatementExecutor {
+  pub fn new(mysql_connection: Rc<RefCell<MysqlConnection>>) -> MysqlStatementExecutor {
+    return MysqlStatementExecutor {
+      connection: mysql_connection,
+    };
+  }
+}
+
+impl StatementExecutor for MysqlStatementExecutor {
+  fn execute_in_transaction(&self, statements: &[String]) -> ResultAnyError<()> {
+    let mut connection = self.connection.borrow_mut();
+    let mut transaction = connection.get().start_transaction(TxOpts::default())?;
+
+    // Mysql does not accept multiple statements in one prepared query
+    for statement in statements.iter() {
+      transaction.query_drop(statement)?;
+    }
+
+    return transaction.commit().map_err(anyhow::Error::from);
+  }
+
+  fn execute(&self, statement: &str) -> Result<(), StatementError> {
+    return match self.connection.borrow_mut().get().query_drop(statement) {
+      Ok(_) => Ok(()),
+      Err(::mysql::Error::MySqlError(err)) if err.code == DUPLICATE_ENTRY_ERROR_CODE => {
+        // Duplicate entry '1' for key 'PRIMARY', mysql 8 prefixes the key with the table name
+        let constraint_name = err
+          .message
+          .rsplit("for key ")
+          .next()
+          .unwrap_or_default()
+          .trim_matches('\'')
+          .rsplit('.')
+          .next()
+          .unwrap_or_default()
+          .to_owned();
+
+        Err(StatementError::UniqueViolation {
+          is_primary_key: constraint_name == "PRIMARY",
+          constraint_name,
+        })
+      }
+      Err(err) => Err(StatementError::Other(err.into())),
+    };
+  }
+}
@@ -0,0 +1,304 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use ::mysql::prelude::Queryable;
+use ::mysql::Row;
+use ::mysql::Value;
+use anyhow::anyhow;
+
+use crate::mysql::connection::MysqlConnection;
+use crate::mysql::dto as mysql_dto;
+use crate::psql::dto::*;
+use crate::psql::table_metadata::OrphanRows;
+use crate::psql::table_metadata::QueryError;
+use crate::psql::table_metadata::RowFilter;
+use crate::psql::table_metadata::RowLimit;
+use crate::psql::table_metadata::RowRelation;
+use crate::psql::table_metadata::TableMetadata;
+use lezeh_common::types::ResultAnyError;
+
+pub struct Query {
+  connection: Rc<RefCell<MysqlConnection>>,
+}
+
+impl Query {
+  fn find_rows(
+    &mut self,
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+    column_value: Value,
+    limit: Option<&RowLimit>,
+    predicates: Vec<&str>,
+  ) -> ResultAnyError<Vec<Row>> {
+    let mut query_str = format!(
+      "SELECT * FROM {}.{} WHERE {} = ?",
+      mysql_dto::escape_identifier(&table_id.schema),
+      mysql_dto::escape_identifier(&table_id.name),
+      mysql_dto::escape_identifier(column_name)
+    );
+
+    for predicate in predicates.iter() {
+      query_str = format!("{} AND ({})", query_str, predicate);
+    }
+
+    if let Some(limit) = limit {
+      query_str = format!(
+        "{} ORDER BY {} {} LIMIT {}",
+        query_str,
+        mysql_dto::escape_identifier(&limit.order_column_name),
+        if limit.descending { "DESC" } else { "ASC" },
+        limit.max_rows
+      );
+    }
+
+    return self
+      .connection
+      .borrow_mut()
+      .get()
+      .exec(query_str, (column_value,))
+      .map_err(anyhow::Error::from);
+  }
+
+  fn count_rows(
+    &mut self,
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+    column_value: Value,
+    predicates: Vec<&str>,
+  ) -> ResultAnyError<u64> {
+    let mut query_str = format!(
+      "SELECT COUNT(*) FROM {}.{} WHERE {} = ?",
+      mysql_dto::escape_identifier(&table_id.schema),
+      mysql_dto::escape_identifier(&table_id.name),
+      mysql_dto::escape_identifier(column_name)
+    );
+
+    for predicate in predicates.iter() {
+      query_str = format!("{} AND ({})", query_str, predicate);
+    }
+
+    let row_count: Option<u64> = self
+      .connection
+      .borrow_mut()
+      .get()
+      .exec_first(query_str, (column_value,))?;
+
+    return Ok(row_count.unwrap_or_default());
+  }
+
+  fn find_max_id(
+    &mut self,
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+  ) -> ResultAnyError<Option<i64>> {
+    let query_str = format!(
+      "SELECT CAST(MAX({}) AS SIGNED) FROM {}.{}",
+      mysql_dto::escape_identifier(column_name),
+      mysql_dto::escape_identifier(&table_id.schema),
+      mysql_dto::escape_identifier(&table_id.name)
+    );
+
+    let max_id: Option<Option<i64>> = self.connection.borrow_mut().get().query_first(query_str)?;
+
+    return Ok(max_id.flatten());
+  }
+
+  fn find_orphan_rows(
+    &mut self,
+    relation: &RowRelation,
+    sample_size: usize,
+  ) -> ResultAnyError<OrphanRows> {
+    let table_identifier = |table_id: &PsqlTableIdentity| {
+      return format!(
+        "{}.{}",
+        mysql_dto::escape_identifier(&table_id.schema),
+        mysql_dto::escape_identifier(&table_id.name)
+      );
+    };
+    let column = mysql_dto::escape_identifier(&relation.column_name);
+    let primary_column = mysql_dto::escape_identifier(&relation.primary_column_name);
+    let from_str = format!(
+      "FROM {} AS c WHERE c.{} IS NOT NULL AND NOT EXISTS (SELECT 1 FROM {} AS p WHERE p.{} = c.{})",
+      table_identifier(&relation.table_id),
+      column,
+      table_identifier(&relation.foreign_table_id),
+      mysql_dto::escape_identifier(&relation.foreign_column_name),
+      column
+    );
+
+    let mut connection = self.connection.borrow_mut();
+    let connection = connection.get();
+
+    let row_count: Option<i64> = connection.query_first(format!("SELECT COUNT(*) {}", from_str))?;
+    let sample_keys: Vec<(String, String)> = connection.query(format!(
+      "SELECT CAST(c.{} AS CHAR), CAST(c.{} AS CHAR) {} ORDER BY c.{} LIMIT {}",
+      primary_column, column, from_str, primary_column, sample_size
+    ))?;
+
+    return Ok(OrphanRows {
+      row_count: row_count.unwrap_or_default() as usize,
+      sample_keys,
+    });
+  }
+
+  fn get_column_data_type(
+    &mut self,
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+  ) -> ResultAnyError<Option<String>> {
+    let query_str =
+      "SELECT data_type FROM information_schema.columns where table_schema = ? and table_name = ? and column_name = ?";
+
+    return self
+      .connection
+      .borrow_mut()
+      .get()
+      .exec_first(
+        query_str,
+        (
+          table_id.schema.to_string(),
+          table_id.name.to_string(),
+          column_name.to_string(),
+        ),
+      )
+      .map_err(anyhow::Error::from);
+  }
+}
+
+pub struct MysqlTableMetadata {
+  /// We know that we own this query so it's ok
+  /// to directl borrow_mut() without checking ownership
+  query: RefCell<Query>,
+
+  /// Applied when fetching relations, the root row is never filtered
+  row_filters: Vec<RowFilter>,
+}
+
+impl MysqlTableMetadata {
+  pub fn new(
+    mysql_connection: Rc<RefCell<MysqlConnection>>,
+    row_filters: Vec<RowFilter>,
+  ) -> MysqlTableMetadata {
+    return MysqlTableMetadata {
+      query: RefCell::new(Query {
+        connection: mysql_connection,
+      }),
+      row_filters,
+    };
+  }
+
+  fn psql_table_row(table: &PsqlTable, row: &Row) -> ResultAnyError<PsqlTableRow> {
+    let values = row
+      .columns_ref()
+      .iter()
+      .enumerate()
+      .map(|(index, column)| {
+        let value = row.as_ref(index).unwrap_or(&Value::NULL);
+
+        return mysql_dto::from_sql_sink(value, column)
+          .map(|value| (column.name_str().to_string(), value));
+      })
+      .collect::<ResultAnyError<PsqlRowValues>>()?;
+
+    return PsqlTableRow::new(table.clone(), values);
+  }
+}
+
+impl TableMetadata for MysqlTableMetadata {
+  fn get_column(
+    &self,
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+  ) -> ResultAnyError<PsqlTableColumn> {
+    let data_type = self
+      .query
+      .borrow_mut()
+      .get_column_data_type(table_id, column_name)?
+      .ok_or_else(|| anyhow!("Column {} is not found in table {}", column_name, table_id))?;
+
+    return Ok(PsqlTableColumn::new(column_name.to_string(), data_type));
+  }
+
+  fn get_rows(
+    &self,
+    table: PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+    limit: Option<&RowLimit>,
+  ) -> ResultAnyError<Vec<PsqlTableRow>> {
+    let rows = self.query.borrow_mut().find_rows(
+      &table.id,
+      column_name,
+      mysql_dto::mysql_value_from_sink(id)?,
+      limit,
+      RowFilter::predicates(&self.row_filters, &table),
+    )?;
+
+    return rows
+      .iter()
+      .map(|row| MysqlTableMetadata::psql_table_row(&table, row))
+      .collect::<ResultAnyError<Vec<PsqlTableRow>>>();
+  }
+
+  fn count_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+  ) -> ResultAnyError<usize> {
+    let row_count = self.query.borrow_mut().count_rows(
+      &table.id,
+      column_name,
+      mysql_dto::mysql_value_from_sink(id)?,
+      RowFilter::predicates(&self.row_filters, table),
+    )?;
+
+    return Ok(row_count as usize);
+  }
+
+  fn get_max_id(&self, table: &PsqlTable, column_name: &str) -> ResultAnyError<Option<i64>> {
+    return self.query.borrow_mut().find_max_id(&table.id, column_name);
+  }
+
+  fn find_orphan_rows(
+    &self,
+    relation: &RowRelation,
+    sample_size: usize,
+  ) -> ResultAnyError<OrphanRows> {
+    return self
+      .query
+      .borrow_mut()
+      .find_orphan_rows(relation, sample_size);
+  }
+
+  fn get_one_row(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &str,
+  ) -> ResultAnyError<PsqlTableRow> {
+    // Mysql will cast the string param into the column type by itself
+    let mut rows =
+      self
+        .query
+        .borrow_mut()
+        .find_rows(&table.id, column_name, Value::from(id), None, vec![])?;
+
+    if rows.len() > 1 {
+      return Err(anyhow!(QueryError::TooManyRows {
+        row_count: rows.len(),
+        expected_row_count: 1,
+      }));
+    }
+
+    if rows.is_empty() {
+      return Err(anyhow!(QueryError::RowNotFound {
+        table_id: format!("{:#?}", table.id),
+        column: column_name.into(),
+        identifier: id.into(),
+      }));
+    }
+
+    return MysqlTableMetadata::psql_table_row(table, &rows.remove(0));
+  }
+}
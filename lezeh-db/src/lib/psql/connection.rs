@@ -13,33 +13,65 @@ pub struct PsqlConnection {
   client: PsqlClient,
 }
 
+#[derive(Clone)]
 pub struct PsqlCreds {
   pub host: String,
   pub database_name: String,
   pub username: String,
   pub password: Option<String>,
+
+  /// Sets `default_transaction_read_only` so the session can't write
+  pub read_only: bool,
+  pub statement_timeout_ms: Option<u64>,
 }
 
 impl PsqlConnection {
   pub fn new(creds: &PsqlCreds) -> ResultAnyError<PsqlConnection> {
-    return Ok(PsqlConnection {
-      client: PsqlConfig::new()
-        .user(&creds.username)
-        .password(
-          creds
-            .password
-            .as_ref()
-            .or(Some(&String::from("")))
-            .as_ref()
-            .unwrap(),
-        )
-        .host(&creds.host)
-        .dbname(&creds.database_name)
-        .connect(postgres::NoTls)
+    let mut client = PsqlConfig::new()
+      .user(&creds.username)
+      .password(
+        creds
+          .password
+          .as_ref()
+          .or(Some(&String::from("")))
+          .as_ref()
+          .unwrap(),
+      )
+      .host(&creds.host)
+      .dbname(&creds.database_name)
+      .connect(postgres::NoTls)
+      .map_err(|err| {
+        return PsqlConnectionError::InitializeConnectionError(err.to_string());
+      })?;
+
+    let session_statements = creds.session_statements();
+
+    if !session_statements.is_empty() {
+      client
+        .batch_execute(&session_statements.join("; "))
         .map_err(|err| {
           return PsqlConnectionError::InitializeConnectionError(err.to_string());
-        })?,
-    });
+        })?;
+    }
+
+    return Ok(PsqlConnection { client });
+  }
+}
+
+impl PsqlCreds {
+  /// Statements that are run right after connecting
+  pub fn session_statements(&self) -> Vec<String> {
+    let mut session_statements: Vec<String> = vec![];
+
+    if self.read_only {
+      session_statements.push("SET default_transaction_read_only = on".to_owned());
+    }
+
+    if let Some(statement_timeout_ms) = self.statement_timeout_ms {
+      session_statements.push(format!("SET statement_timeout = {}", statement_timeout_ms));
+    }
+
+    return session_statements;
   }
 }
 
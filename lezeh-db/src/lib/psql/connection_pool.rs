@@ -0,0 +1,97 @@
+use std::cell::RefCell;
+use std::ops::Deref;
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio_postgres::Client as AsyncPsqlClient;
+use tokio_postgres::Config as AsyncPsqlConfig;
+
+use crate::psql::connection::{PsqlConnectionError, PsqlCreds};
+use lezeh_common::types::ResultAnyError;
+
+/// Small pool of async connections, at most `max_size` connections are borrowed at a time.
+/// Connections are opened lazily and reused once they are returned, the pool is meant to be
+/// used within 1 thread so it must be created inside the tokio runtime that drives it.
+pub struct PsqlConnectionPool {
+  creds: PsqlCreds,
+  idle_clients: RefCell<Vec<AsyncPsqlClient>>,
+  semaphore: Semaphore,
+}
+
+/// Connection borrowed from [PsqlConnectionPool], it's returned to the pool on drop
+pub struct PooledPsqlClient<'a> {
+  client: Option<AsyncPsqlClient>,
+  pool: &'a PsqlConnectionPool,
+  _permit: SemaphorePermit<'a>,
+}
+
+impl PsqlConnectionPool {
+  pub fn new(creds: PsqlCreds, max_size: usize) -> PsqlConnectionPool {
+    return PsqlConnectionPool {
+      creds,
+      idle_clients: Default::default(),
+      semaphore: Semaphore::new(max_size.max(1)),
+    };
+  }
+
+  pub async fn get(&self) -> ResultAnyError<PooledPsqlClient<'_>> {
+    let permit = self.semaphore.acquire().await?;
+    let idle_client = self.idle_clients.borrow_mut().pop();
+
+    let client = match idle_client {
+      Some(client) if !client.is_closed() => client,
+      _ => self.connect().await?,
+    };
+
+    return Ok(PooledPsqlClient {
+      client: Some(client),
+      pool: self,
+      _permit: permit,
+    });
+  }
+
+  async fn connect(&self) -> ResultAnyError<AsyncPsqlClient> {
+    let (client, connection) = AsyncPsqlConfig::new()
+      .user(&self.creds.username)
+      .password(self.creds.password.as_deref().unwrap_or(""))
+      .host(&self.creds.host)
+      .dbname(&self.creds.database_name)
+      .connect(tokio_postgres::NoTls)
+      .await
+      .map_err(|err| {
+        return PsqlConnectionError::InitializeConnectionError(err.to_string());
+      })?;
+
+    // The connection performs the actual communication with the db,
+    // it resolves once the client is dropped.
+    tokio::spawn(connection);
+
+    let session_statements = self.creds.session_statements();
+
+    if !session_statements.is_empty() {
+      client
+        .batch_execute(&session_statements.join("; "))
+        .await
+        .map_err(|err| {
+          return PsqlConnectionError::InitializeConnectionError(err.to_string());
+        })?;
+    }
+
+    return Ok(client);
+  }
+}
+
+impl<'a> Deref for PooledPsqlClient<'a> {
+  type Target = AsyncPsqlClient;
+
+  fn deref(&self) -> &AsyncPsqlClient {
+    return self.client.as_ref().unwrap();
+  }
+}
+
+impl<'a> Drop for PooledPsqlClient<'a> {
+  fn drop(&mut self) {
+    if let Some(client) = self.client.take() {
+      self.pool.idle_clients.borrow_mut().push(client);
+    }
+  }
+}
@@ -22,7 +22,15 @@ const TABLE_WITH_FK_QUERY: &'static str = "
       ccu.table_schema AS foreign_table_schema,
       ccu.table_name AS foreign_table_name,
       ccu.column_name AS foreign_column_name,
-      foreign_c_meta.data_type AS foreign_column_data_type
+      foreign_c_meta.data_type AS foreign_column_data_type,
+      coalesce((
+        SELECT con.convalidated
+        FROM
+          pg_catalog.pg_constraint AS con
+            JOIN pg_catalog.pg_namespace AS n ON n.oid = con.connamespace
+        WHERE con.conname = tc.constraint_name AND n.nspname = tc.constraint_schema
+        LIMIT 1
+      ), true) AS is_validated
     FROM
       information_schema.table_constraints AS tc
         JOIN information_schema.key_column_usage AS kcu ON
@@ -40,21 +48,172 @@ const TABLE_WITH_FK_QUERY: &'static str = "
     WHERE tc.constraint_type = 'FOREIGN KEY';
 ";
 
+const COLUMN_QUERY: &'static str = "
+    SELECT
+      c.table_schema,
+      c.table_name,
+      c.column_name,
+      c.data_type,
+      c.is_identity = 'YES' AS is_identity,
+      c.is_generated = 'ALWAYS' AS is_generated,
+      c.column_default,
+      pg_get_serial_sequence(format('%I.%I', c.table_schema, c.table_name), c.column_name) AS sequence_name,
+      c.is_nullable = 'YES' AS is_nullable,
+      c.ordinal_position::int AS ordinal_position,
+      c.character_maximum_length::int AS character_maximum_length,
+      format_type(a.atttypid, a.atttypmod) AS column_type,
+      c.generation_expression,
+      CASE WHEN t.typtype = 'e' THEN tn.nspname::text END AS enum_schema,
+      CASE WHEN t.typtype = 'e' THEN t.typname::text END AS enum_name,
+      CASE WHEN t.typtype = 'e' THEN ARRAY(
+        SELECT e.enumlabel::text FROM pg_catalog.pg_enum AS e
+        WHERE e.enumtypid = t.oid
+        ORDER BY e.enumsortorder
+      ) END AS enum_labels
+    FROM
+      information_schema.columns AS c
+        JOIN pg_catalog.pg_attribute AS a ON
+          a.attrelid = format('%I.%I', c.table_schema, c.table_name)::regclass AND
+          a.attname = c.column_name
+        JOIN pg_catalog.pg_type AS t ON t.oid = a.atttypid
+        JOIN pg_catalog.pg_namespace AS tn ON tn.oid = t.typnamespace
+    WHERE c.table_schema NOT IN ('pg_catalog', 'information_schema');
+";
+
+/// Unique indexes cover unique constraints as well,
+/// partial and expression indexes can't be represented as a column list.
+const UNIQUE_CONSTRAINT_QUERY: &'static str = "
+    SELECT
+      n.nspname AS table_schema,
+      t.relname AS table_name,
+      i.relname AS constraint_name,
+      array_agg(a.attname::text ORDER BY k.ordinality) AS column_names
+    FROM
+      pg_catalog.pg_index AS ix
+        JOIN pg_catalog.pg_class AS i ON i.oid = ix.indexrelid
+        JOIN pg_catalog.pg_class AS t ON t.oid = ix.indrelid
+        JOIN pg_catalog.pg_namespace AS n ON n.oid = t.relnamespace
+        CROSS JOIN LATERAL unnest(ix.indkey::int2[]) WITH ORDINALITY AS k(attnum, ordinality)
+        JOIN pg_catalog.pg_attribute AS a ON a.attrelid = t.oid AND a.attnum = k.attnum
+    WHERE ix.indisunique AND
+      NOT ix.indisprimary AND
+      ix.indpred IS NULL AND
+      ix.indexprs IS NULL AND
+      n.nspname NOT IN ('pg_catalog', 'information_schema')
+    GROUP BY n.nspname, t.relname, i.relname;
+";
+
+const CHECK_CONSTRAINT_QUERY: &'static str = "
+    SELECT
+      n.nspname AS table_schema,
+      c.relname AS table_name,
+      con.conname AS constraint_name,
+      pg_get_constraintdef(con.oid) AS definition
+    FROM
+      pg_catalog.pg_constraint AS con
+        JOIN pg_catalog.pg_class AS c ON c.oid = con.conrelid
+        JOIN pg_catalog.pg_namespace AS n ON n.oid = c.relnamespace
+    WHERE con.contype = 'c' AND
+      n.nspname NOT IN ('pg_catalog', 'information_schema');
+";
+
+/// Tables are loaded by their primary key, these ones are left out
+const TABLE_WITHOUT_PRIMARY_KEY_QUERY: &str = "
+    SELECT
+      n.nspname AS table_schema,
+      c.relname AS table_name
+    FROM
+      pg_catalog.pg_class AS c
+        JOIN pg_catalog.pg_namespace AS n ON n.oid = c.relnamespace
+    WHERE c.relkind IN ('r', 'p') AND
+      n.nspname NOT IN ('pg_catalog', 'information_schema') AND
+      NOT EXISTS (
+        SELECT 1
+        FROM pg_catalog.pg_constraint AS con
+        WHERE con.conrelid = c.oid AND con.contype = 'p'
+      )
+    ORDER BY n.nspname, c.relname;
+";
+
+/// Hash of the catalog entries that affect the loaded table structure,
+/// it's a lot cheaper than loading the structure itself.
+const SCHEMA_FINGERPRINT_QUERY: &'static str = "
+    SELECT md5(coalesce(string_agg(entry, E'\\n' ORDER BY entry), '')) AS fingerprint
+    FROM (
+      SELECT concat_ws(':', n.nspname, c.relname, a::text) AS entry
+      FROM
+        pg_catalog.pg_attribute AS a
+          JOIN pg_catalog.pg_class AS c ON c.oid = a.attrelid
+          JOIN pg_catalog.pg_namespace AS n ON n.oid = c.relnamespace
+      WHERE c.relkind IN ('r', 'p') AND
+        a.attnum > 0 AND
+        n.nspname NOT IN ('pg_catalog', 'information_schema')
+
+      UNION ALL
+
+      SELECT concat_ws(':', con.conrelid::regclass::text, con.conname, pg_get_constraintdef(con.oid))
+      FROM
+        pg_catalog.pg_constraint AS con
+          JOIN pg_catalog.pg_namespace AS n ON n.oid = con.connamespace
+      WHERE n.nspname NOT IN ('pg_catalog', 'information_schema')
+
+      UNION ALL
+
+      SELECT concat_ws(':', d.adrelid::regclass::text, d.adnum, pg_get_expr(d.adbin, d.adrelid))
+      FROM pg_catalog.pg_attrdef AS d
+
+      UNION ALL
+
+      SELECT pg_get_indexdef(ix.indexrelid)
+      FROM pg_catalog.pg_index AS ix
+      WHERE ix.indisunique
+
+      UNION ALL
+
+      SELECT concat_ws(':', enumtypid::regtype::text, enumsortorder, enumlabel)
+      FROM pg_catalog.pg_enum
+    ) AS entries;
+";
+
 #[derive(PartialEq, Debug)]
 pub struct ForeignKeyInformationRow {
-  constraint_name: String,
+  pub(crate) constraint_name: String,
 
   // From table X
-  table_schema: String,
-  table_name: String,
-  column_name: String,
-  column_data_type: String,
+  pub(crate) table_schema: String,
+  pub(crate) table_name: String,
+  pub(crate) column_name: String,
+  pub(crate) column_data_type: String,
 
   // referencing to table Y
-  foreign_table_schema: String,
-  foreign_table_name: String,
-  foreign_column_name: String,
-  foreign_column_data_type: String,
+  pub(crate) foreign_table_schema: String,
+  pub(crate) foreign_table_name: String,
+  pub(crate) foreign_column_name: String,
+  pub(crate) foreign_column_data_type: String,
+
+  /// False for `NOT VALID` constraints
+  pub(crate) is_validated: bool,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct ColumnInformationRow {
+  pub(crate) table_schema: String,
+  pub(crate) table_name: String,
+  pub(crate) column: PsqlTableColumn,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct UniqueConstraintInformationRow {
+  pub(crate) table_schema: String,
+  pub(crate) table_name: String,
+  pub(crate) constraint: PsqlUniqueConstraint,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct CheckConstraintInformationRow {
+  pub(crate) table_schema: String,
+  pub(crate) table_name: String,
+  pub(crate) constraint: PsqlCheckConstraint,
 }
 
 pub struct Query {
@@ -84,6 +243,7 @@ impl Query {
           foreign_table_name: row.get("foreign_table_name"),
           foreign_column_name: row.get("foreign_column_name"),
           foreign_column_data_type: row.get("foreign_column_data_type"),
+          is_validated: row.get("is_validated"),
         };
       })
       .collect();
@@ -91,6 +251,97 @@ impl Query {
     return Ok(fk_info_rows);
   }
 
+  fn fetch_column_info(&mut self) -> ResultAnyError<Vec<ColumnInformationRow>> {
+    let rows: Vec<Row> = self
+      .connection
+      .borrow_mut()
+      .get()
+      .query(COLUMN_QUERY, &[])?;
+
+    let column_info_rows: Vec<ColumnInformationRow> = rows
+      .into_iter()
+      .map(|row: Row| -> ColumnInformationRow {
+        return ColumnInformationRow {
+          table_schema: row.get("table_schema"),
+          table_name: row.get("table_name"),
+          column: PsqlTableColumn {
+            name: row.get("column_name"),
+            data_type: row.get("data_type"),
+            is_identity: row.get("is_identity"),
+            is_generated: row.get("is_generated"),
+            default_value: row.get("column_default"),
+            sequence_name: row.get("sequence_name"),
+            is_nullable: row.get("is_nullable"),
+            ordinal_position: row.get("ordinal_position"),
+            character_maximum_length: row.get("character_maximum_length"),
+            column_type: row.get("column_type"),
+            enum_type: row
+              .get::<_, Option<String>>("enum_name")
+              .map(|enum_name| PsqlEnumType {
+                schema: row.get("enum_schema"),
+                name: enum_name,
+                labels: row.get("enum_labels"),
+              }),
+            generation_expression: row.get("generation_expression"),
+            is_auto_increment: false,
+          },
+        };
+      })
+      .collect();
+
+    return Ok(column_info_rows);
+  }
+
+  fn fetch_unique_constraint_info(
+    &mut self,
+  ) -> ResultAnyError<Vec<UniqueConstraintInformationRow>> {
+    let rows: Vec<Row> = self
+      .connection
+      .borrow_mut()
+      .get()
+      .query(UNIQUE_CONSTRAINT_QUERY, &[])?;
+
+    return Ok(
+      rows
+        .into_iter()
+        .map(|row: Row| -> UniqueConstraintInformationRow {
+          return UniqueConstraintInformationRow {
+            table_schema: row.get("table_schema"),
+            table_name: row.get("table_name"),
+            constraint: PsqlUniqueConstraint {
+              name: row.get("constraint_name"),
+              column_names: row.get("column_names"),
+            },
+          };
+        })
+        .collect(),
+    );
+  }
+
+  fn fetch_check_constraint_info(&mut self) -> ResultAnyError<Vec<CheckConstraintInformationRow>> {
+    let rows: Vec<Row> = self
+      .connection
+      .borrow_mut()
+      .get()
+      .query(CHECK_CONSTRAINT_QUERY, &[])?;
+
+    return Ok(
+      rows
+        .into_iter()
+        .map(|row: Row| -> CheckConstraintInformationRow {
+          return CheckConstraintInformationRow {
+            table_schema: row.get("table_schema"),
+            table_name: row.get("table_name"),
+            constraint: PsqlCheckConstraint {
+              name: row.get("constraint_name"),
+              definition: row.get("definition"),
+            },
+          };
+        })
+        .collect(),
+    );
+  }
+
   fn get_table_by_id(&mut self) -> ResultAnyError<HashMap<PsqlTableIdentity, PsqlTable>> {
     let rows: Vec<Row> = self.connection.borrow_mut().get().query(
       "
@@ -136,17 +387,62 @@ impl Query {
 
     return Ok(psql_table_by_id);
   }
+
+  fn fetch_tables_without_primary_key(&mut self) -> ResultAnyError<Vec<PsqlTableIdentity>> {
+    let rows: Vec<Row> = self
+      .connection
+      .borrow_mut()
+      .get()
+      .query(TABLE_WITHOUT_PRIMARY_KEY_QUERY, &[])?;
+
+    return Ok(
+      rows
+        .into_iter()
+        .map(|row| {
+          return PsqlTableIdentity::new(
+            row.get::<_, String>("table_schema"),
+            row.get::<_, String>("table_name"),
+          );
+        })
+        .collect(),
+    );
+  }
+
+  fn fetch_schema_fingerprint(&mut self) -> ResultAnyError<String> {
+    let row: Row = self
+      .connection
+      .borrow_mut()
+      .get()
+      .query_one(SCHEMA_FINGERPRINT_QUERY, &[])?;
+
+    return Ok(row.get("fingerprint"));
+  }
+}
+
+#[cfg_attr(test, mockall::automock)]
+pub trait DbMetadata {
+  fn load_table_structure(
+    &self,
+    schema: &str,
+  ) -> ResultAnyError<HashMap<PsqlTableIdentity, PsqlTable>>;
+
+  /// Value that changes whenever the table structure changes,
+  /// used to invalidate cached table structure.
+  fn schema_fingerprint(&self) -> ResultAnyError<String>;
+
+  /// Tables that are not part of the loaded table structure since they have no primary key
+  fn tables_without_primary_key(&self) -> ResultAnyError<Vec<PsqlTableIdentity>>;
 }
 
-pub struct DbMetadata {
+pub struct DbMetadataImpl {
   /// We know that we own this query so it's ok
   /// to directl borrow_mut() without checking ownership
   query: RefCell<Query>,
 }
 
-impl DbMetadata {
-  pub fn new(psql_connection: Rc<RefCell<PsqlConnection>>) -> DbMetadata {
-    return DbMetadata {
+impl DbMetadataImpl {
+  pub fn new(psql_connection: Rc<RefCell<PsqlConnection>>) -> DbMetadataImpl {
+    return DbMetadataImpl {
       query: RefCell::new(Query {
         connection: psql_connection,
       }),
@@ -154,22 +450,41 @@ impl DbMetadata {
   }
 }
 
-impl DbMetadata {
-  pub fn load_table_structure(
+impl DbMetadata for DbMetadataImpl {
+  fn load_table_structure(
     &self,
     schema: &str,
   ) -> ResultAnyError<HashMap<PsqlTableIdentity, PsqlTable>> {
     let fk_info_rows = self.query.borrow_mut().fetch_fk_info(schema)?;
 
+    let column_info_rows = self.query.borrow_mut().fetch_column_info()?;
+
+    let unique_constraint_info_rows = self.query.borrow_mut().fetch_unique_constraint_info()?;
+
+    let check_constraint_info_rows = self.query.borrow_mut().fetch_check_constraint_info()?;
+
     let mut table_by_id = self.query.borrow_mut().get_table_by_id()?;
 
     psql_table_map_from_foreign_key_info_rows(&mut table_by_id, &fk_info_rows);
+    psql_table_map_from_column_info_rows(&mut table_by_id, column_info_rows);
+    psql_table_map_from_constraint_info_rows(
+      &mut table_by_id,
+      unique_constraint_info_rows,
+      check_constraint_info_rows,
+    );
 
     return Ok(table_by_id);
   }
+
+  fn schema_fingerprint(&self) -> ResultAnyError<String> {
+    return self.query.borrow_mut().fetch_schema_fingerprint();
+  }
+  fn tables_without_primary_key(&self) -> ResultAnyError<Vec<PsqlTableIdentity>> {
+    return self.query.borrow_mut().fetch_tables_without_primary_key();
+  }
 }
 
-fn psql_table_map_from_foreign_key_info_rows(
+pub(crate) fn psql_table_map_from_foreign_key_info_rows(
   table_by_id: &mut HashMap<PsqlTableIdentity, PsqlTable>,
   rows: &Vec<ForeignKeyInformationRow>,
 ) {
@@ -194,12 +509,15 @@ fn psql_table_map_from_foreign_key_info_rows(
         .map(|fk_row| {
           return (
             fk_row.constraint_name.clone(),
-            PsqlForeignKey::new(
-              fk_row.constraint_name.clone(),
-              PsqlTableColumn::new(fk_row.column_name.clone(), fk_row.column_data_type.clone()),
-              fk_row.foreign_table_schema.clone(),
-              fk_row.foreign_table_name.clone(),
-            ),
+            PsqlForeignKey {
+              is_validated: fk_row.is_validated,
+              ..PsqlForeignKey::new(
+                fk_row.constraint_name.clone(),
+                PsqlTableColumn::new(fk_row.column_name.clone(), fk_row.column_data_type.clone()),
+                fk_row.foreign_table_schema.clone(),
+                fk_row.foreign_table_name.clone(),
+              )
+            },
           );
         })
         .collect();
@@ -215,12 +533,15 @@ fn psql_table_map_from_foreign_key_info_rows(
         .map(|fk_row| {
           return (
             fk_row.constraint_name.clone(),
-            PsqlForeignKey::new(
-              fk_row.constraint_name.clone(),
-              PsqlTableColumn::new(fk_row.column_name.clone(), fk_row.column_data_type.clone()),
-              fk_row.table_schema.clone(),
-              fk_row.table_name.clone(),
-            ),
+            PsqlForeignKey {
+              is_validated: fk_row.is_validated,
+              ..PsqlForeignKey::new(
+                fk_row.constraint_name.clone(),
+                PsqlTableColumn::new(fk_row.column_name.clone(), fk_row.column_data_type.clone()),
+                fk_row.table_schema.clone(),
+                fk_row.table_name.clone(),
+              )
+            },
           );
         })
         .collect();
@@ -228,6 +549,48 @@ fn psql_table_map_from_foreign_key_info_rows(
   }
 }
 
+pub(crate) fn psql_table_map_from_column_info_rows(
+  table_by_id: &mut HashMap<PsqlTableIdentity, PsqlTable>,
+  rows: Vec<ColumnInformationRow>,
+) {
+  for row in rows.into_iter() {
+    // Tables without primary key are not loaded, we can't cherry pick them anyway
+    if let Some(table) =
+      table_by_id.get_mut(&PsqlTableIdentity::new(row.table_schema, row.table_name))
+    {
+      table.columns.insert(row.column);
+    }
+  }
+}
+
+pub(crate) fn psql_table_map_from_constraint_info_rows(
+  table_by_id: &mut HashMap<PsqlTableIdentity, PsqlTable>,
+  unique_constraint_rows: Vec<UniqueConstraintInformationRow>,
+  check_constraint_rows: Vec<CheckConstraintInformationRow>,
+) {
+  for row in unique_constraint_rows.into_iter() {
+    if let Some(table) =
+      table_by_id.get_mut(&PsqlTableIdentity::new(row.table_schema, row.table_name))
+    {
+      table.unique_constraints.push(row.constraint);
+    }
+  }
+
+  for row in check_constraint_rows.into_iter() {
+    if let Some(table) =
+      table_by_id.get_mut(&PsqlTableIdentity::new(row.table_schema, row.table_name))
+    {
+      table.check_constraints.push(row.constraint);
+    }
+  }
+
+  // Catalog order is not stable
+  for table in table_by_id.values_mut() {
+    table.unique_constraints.sort_by(|a, b| a.name.cmp(&b.name));
+    table.check_constraints.sort_by(|a, b| a.name.cmp(&b.name));
+  }
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
@@ -245,6 +608,8 @@ mod test {
         columns: Default::default(),
         referenced_fk_by_constraint_name: Default::default(),
         referencing_fk_by_constraint_name: Default::default(),
+        unique_constraints: Default::default(),
+        check_constraints: Default::default(),
       };
     }
   }
@@ -267,6 +632,7 @@ mod test {
           foreign_table_name: "stores".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "integer".into(),
+          is_validated: true,
         },
         ForeignKeyInformationRow {
           table_schema: "public".into(),
@@ -278,6 +644,7 @@ mod test {
           foreign_table_name: "stores".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "integer".into(),
+          is_validated: true,
         },
         ForeignKeyInformationRow {
           table_schema: "public".into(),
@@ -289,6 +656,7 @@ mod test {
           foreign_table_name: "products".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "integer".into(),
+          is_validated: true,
         },
         ForeignKeyInformationRow {
           table_schema: "public".into(),
@@ -300,6 +668,7 @@ mod test {
           foreign_table_name: "products".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "integer".into(),
+          is_validated: true,
         },
         ForeignKeyInformationRow {
           table_schema: "public".into(),
@@ -311,6 +680,7 @@ mod test {
           foreign_table_name: "stores".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "integer".into(),
+          is_validated: true,
         },
         ForeignKeyInformationRow {
           table_schema: "public".into(),
@@ -322,6 +692,7 @@ mod test {
           foreign_table_name: "store_staff_roles".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "uuid".into(),
+          is_validated: true,
         },
         ForeignKeyInformationRow {
           table_schema: "public".into(),
@@ -333,6 +704,7 @@ mod test {
           foreign_table_name: "store_staffs".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "integer".into(),
+          is_validated: true,
         },
         ForeignKeyInformationRow {
           table_schema: "public".into(),
@@ -344,6 +716,7 @@ mod test {
           foreign_table_name: "stores".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "integer".into(),
+          is_validated: true,
         },
         ForeignKeyInformationRow {
           table_schema: "public".into(),
@@ -355,6 +728,7 @@ mod test {
           foreign_table_name: "stores".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "integer".into(),
+          is_validated: true,
         },
         ForeignKeyInformationRow {
           table_schema: "public".into(),
@@ -366,6 +740,7 @@ mod test {
           foreign_table_name: "orders".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "integer".into(),
+          is_validated: true,
         },
         ForeignKeyInformationRow {
           table_schema: "public".into(),
@@ -377,6 +752,7 @@ mod test {
           foreign_table_name: "products".into(),
           foreign_column_name: "id".into(),
           foreign_column_data_type: "integer".into(),
+          is_validated: true,
         },
       ];
 
@@ -384,46 +760,57 @@ mod test {
         PsqlTableIdentity::new("public", "stores") => PsqlTable::basic("public", "stores", PsqlTableColumn{
           name: "id".into(),
           data_type: "integer".into(),
+          ..Default::default()
         }),
         PsqlTableIdentity::new("public", "orders") => PsqlTable::basic("public", "orders", PsqlTableColumn{
           name: "id".into(),
           data_type: "integer".into(),
+          ..Default::default()
         }),
         PsqlTableIdentity::new("public", "order_items") => PsqlTable::basic("public", "order_items", PsqlTableColumn{
           name: "id".into(),
           data_type: "integer".into(),
+          ..Default::default()
         }),
         PsqlTableIdentity::new("public", "order_statuses") => PsqlTable::basic("public", "order_statuses", PsqlTableColumn{
           name: "id".into(),
           data_type: "integer".into(),
+          ..Default::default()
         }),
         PsqlTableIdentity::new("public", "products") => PsqlTable::basic("public", "products", PsqlTableColumn{
           name: "id".into(),
           data_type: "integer".into(),
+          ..Default::default()
         }),
         PsqlTableIdentity::new("public", "product_images") => PsqlTable::basic("public", "product_images", PsqlTableColumn{
           name: "id".into(),
           data_type: "integer".into(),
+          ..Default::default()
         }),
         PsqlTableIdentity::new("public", "product_stock_ledgers") => PsqlTable::basic("public", "product_stock_ledgers", PsqlTableColumn{
           name: "id".into(),
           data_type: "integer".into(),
+          ..Default::default()
         }),
         PsqlTableIdentity::new("public", "store_customers") => PsqlTable::basic("public", "store_customers", PsqlTableColumn{
           name: "id".into(),
           data_type: "integer".into(),
+          ..Default::default()
         }),
         PsqlTableIdentity::new("public", "store_staffs_stores") => PsqlTable::basic("public", "store_staffs_stores", PsqlTableColumn{
           name: "id".into(),
           data_type: "uuid".into(),
+          ..Default::default()
         }),
         PsqlTableIdentity::new("public", "store_staff_roles") => PsqlTable::basic("public", "store_staff_roles", PsqlTableColumn{
           name: "id".into(),
           data_type: "uuid".into(),
+          ..Default::default()
         }),
         PsqlTableIdentity::new("public", "store_staffs") => PsqlTable::basic("public", "store_staffs", PsqlTableColumn{
           name: "id".into(),
           data_type: "integer".into(),
+          ..Default::default()
         }),
       };
 
@@ -489,4 +876,70 @@ mod test {
       assert_eq!(psql_table_by_id.len(), 11)
     }
   }
+
+  mod psql_table_map_from_constraint_info_rows {
+    use super::*;
+
+    #[test]
+    fn it_should_attach_sorted_constraints_to_loaded_tables() {
+      let mut table_by_id: HashMap<PsqlTableIdentity, PsqlTable> = vec![PsqlTable::basic(
+        "public",
+        "orders",
+        PsqlTableColumn::new("id", "integer"),
+      )]
+      .into_iter()
+      .map(|table| (table.id.clone(), table))
+      .collect();
+
+      let unique_constraint_row = |table_name: &str, name: &str, column_names: Vec<&str>| {
+        return UniqueConstraintInformationRow {
+          table_schema: "public".into(),
+          table_name: table_name.into(),
+          constraint: PsqlUniqueConstraint {
+            name: name.into(),
+            column_names: column_names.into_iter().map(ToOwned::to_owned).collect(),
+          },
+        };
+      };
+
+      psql_table_map_from_constraint_info_rows(
+        &mut table_by_id,
+        vec![
+          unique_constraint_row(
+            "orders",
+            "orders_tenant_id_number_key",
+            vec!["tenant_id", "number"],
+          ),
+          unique_constraint_row("orders", "orders_code_key", vec!["code"]),
+          // Tables without primary key are not loaded
+          unique_constraint_row("audit_logs", "audit_logs_code_key", vec!["code"]),
+        ],
+        vec![CheckConstraintInformationRow {
+          table_schema: "public".into(),
+          table_name: "orders".into(),
+          constraint: PsqlCheckConstraint {
+            name: "orders_total_check".into(),
+            definition: "CHECK ((total >= 0))".into(),
+          },
+        }],
+      );
+
+      let orders = &table_by_id[&PsqlTableIdentity::new("public", "orders")];
+
+      assert_eq!(
+        orders
+          .unique_constraints
+          .iter()
+          .map(|constraint| constraint.name.as_str())
+          .collect::<Vec<&str>>(),
+        vec!["orders_code_key", "orders_tenant_id_number_key"]
+      );
+      assert_eq!(
+        orders.unique_constraints[1].column_names,
+        vec!["tenant_id", "number"]
+      );
+      assert_eq!(orders.check_constraints.len(), 1);
+      assert_eq!(table_by_id.len(), 1);
+    }
+  }
 }
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::psql::db_metadata::DbMetadata;
+use crate::psql::dto::{PsqlTable, PsqlTableIdentity};
+use lezeh_common::types::ResultAnyError;
+
+/// Bump this whenever [PsqlTable] structure changes
+/// so that outdated cache files are ignored.
+const CACHE_VERSION: u32 = 7;
+
+#[derive(Serialize, Deserialize)]
+struct DbMetadataCacheEntry {
+  version: u32,
+  fingerprint: String,
+  tables: Vec<PsqlTable>,
+}
+
+/// [DbMetadata] that stores the loaded table structure on disk,
+/// the cache is invalidated whenever the db schema fingerprint changes.
+pub struct CachedDbMetadata {
+  inner: Box<dyn DbMetadata>,
+  cache_file_path: PathBuf,
+
+  /// Ignore the cached table structure and load it from db
+  refresh: bool,
+}
+
+impl CachedDbMetadata {
+  pub fn new(
+    inner: Box<dyn DbMetadata>,
+    cache_file_path: PathBuf,
+    refresh: bool,
+  ) -> CachedDbMetadata {
+    return CachedDbMetadata {
+      inner,
+      cache_file_path,
+      refresh,
+    };
+  }
+}
+
+impl CachedDbMetadata {
+  /// Unreadable or outdated cache file is treated as a cache miss
+  fn read_cache(&self) -> Option<DbMetadataCacheEntry> {
+    let cache_str = fs::read_to_string(&self.cache_file_path).ok()?;
+    let entry: DbMetadataCacheEntry = serde_json::from_str(&cache_str).ok()?;
+
+    if entry.version != CACHE_VERSION {
+      return None;
+    }
+
+    return Some(entry);
+  }
+
+  fn write_cache(&self, entry: &DbMetadataCacheEntry) -> ResultAnyError<()> {
+    if let Some(cache_dir) = self.cache_file_path.parent() {
+      fs::create_dir_all(cache_dir)?;
+    }
+
+    // Write into temporary file first so concurrent runs never read a partially
+    // written cache, the pid keeps concurrent runs from writing the same file.
+    let tmp_file_path = self
+      .cache_file_path
+      .with_extension(format!("json.{}.tmp", std::process::id()));
+
+    let written = fs::write(&tmp_file_path, serde_json::to_string(entry)?)
+      .and_then(|_| fs::rename(&tmp_file_path, &self.cache_file_path));
+
+    if written.is_err() {
+      let _ = fs::remove_file(&tmp_file_path);
+    }
+
+    return written.map_err(anyhow::Error::from);
+  }
+}
+
+impl DbMetadata for CachedDbMetadata {
+  fn load_table_structure(
+    &self,
+    schema: &str,
+  ) -> ResultAnyError<HashMap<PsqlTableIdentity, PsqlTable>> {
+    let fingerprint = self.inner.schema_fingerprint()?;
+
+    if !self.refresh {
+      let cached_entry = self
+        .read_cache()
+        .filter(|entry| entry.fingerprint == fingerprint);
+
+      if let Some(entry) = cached_entry {
+        return Ok(
+          entry
+            .tables
+            .into_iter()
+            .map(|table| (table.id.clone(), table))
+            .collect(),
+        );
+      }
+    }
+
+    let table_by_id = self.inner.load_table_structure(schema)?;
+
+    // The table structure is already loaded, a failing cache only slows down the next run
+    let cache_entry = DbMetadataCacheEntry {
+      version: CACHE_VERSION,
+      fingerprint,
+      tables: table_by_id.values().cloned().collect(),
+    };
+
+    if let Err(err) = self.write_cache(&cache_entry) {
+      eprintln!(
+        "Could not write metadata cache {}: {}",
+        self.cache_file_path.display(),
+        err
+      );
+    }
+
+    return Ok(table_by_id);
+  }
+
+  fn schema_fingerprint(&self) -> ResultAnyError<String> {
+    return self.inner.schema_fingerprint();
+  }
+
+  fn tables_without_primary_key(&self) -> ResultAnyError<Vec<PsqlTableIdentity>> {
+    return self.inner.tables_without_primary_key();
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::db_metadata::MockDbMetadata;
+
+  fn create_table_by_id() -> HashMap<PsqlTableIdentity, PsqlTable> {
+    let table = PsqlTable::fixture("orders").build();
+
+    return HashMap::from([(table.id.clone(), table)]);
+  }
+
+  fn create_cache_file_path(test_name: &str) -> PathBuf {
+    let cache_file_path = std::env::temp_dir()
+      .join(format!("lezeh-db-metadata-cache-{}", std::process::id()))
+      .join(format!("{}.json", test_name));
+
+    let _ = fs::remove_file(&cache_file_path);
+
+    return cache_file_path;
+  }
+
+  fn create_db_metadata(
+    fingerprint: &'static str,
+    expected_load_count: usize,
+  ) -> Box<MockDbMetadata> {
+    let mut db_metadata = MockDbMetadata::new();
+
+    db_metadata
+      .expect_schema_fingerprint()
+      .returning(move || Ok(fingerprint.into()));
+
+    db_metadata
+      .expect_load_table_structure()
+      .times(expected_load_count)
+      .returning(|_| Ok(create_table_by_id()));
+
+    return Box::new(db_metadata);
+  }
+
+  mod load_table_structure {
+    use super::*;
+
+    #[test]
+    fn it_should_use_cache_when_fingerprint_is_unchanged() -> ResultAnyError<()> {
+      let cache_file_path = create_cache_file_path("unchanged");
+
+      let loaded =
+        CachedDbMetadata::new(create_db_metadata("a", 1), cache_file_path.clone(), false)
+          .load_table_structure("public")?;
+
+      let cached = CachedDbMetadata::new(create_db_metadata("a", 0), cache_file_path, false)
+        .load_table_structure("public")?;
+
+      assert_eq!(cached, loaded);
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_reload_when_fingerprint_changes() -> ResultAnyError<()> {
+      let cache_file_path = create_cache_file_path("changed");
+
+      CachedDbMetadata::new(create_db_metadata("a", 1), cache_file_path.clone(), false)
+        .load_table_structure("public")?;
+
+      CachedDbMetadata::new(create_db_metadata("b", 1), cache_file_path, false)
+        .load_table_structure("public")?;
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_load_tables_when_cache_can_not_be_written() -> ResultAnyError<()> {
+      // Parent of the cache file is a file so the cache dir can't be created
+      let blocking_file_path = create_cache_file_path("unwritable");
+
+      fs::create_dir_all(blocking_file_path.parent().unwrap())?;
+      fs::write(&blocking_file_path, "")?;
+
+      let loaded = CachedDbMetadata::new(
+        create_db_metadata("a", 1),
+        blocking_file_path.join("cache.json"),
+        false,
+      )
+      .load_table_structure("public")?;
+
+      assert_eq!(loaded, create_table_by_id());
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_reload_when_refresh_is_requested() -> ResultAnyError<()> {
+      let cache_file_path = create_cache_file_path("refresh");
+
+      CachedDbMetadata::new(create_db_metadata("a", 1), cache_file_path.clone(), false)
+        .load_table_structure("public")?;
+
+      CachedDbMetadata::new(create_db_metadata("a", 1), cache_file_path, true)
+        .load_table_structure("public")?;
+
+      return Ok(());
+    }
+  }
+}
@@ -1,26 +1,86 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::hash::Hash;
 use std::rc::Rc;
 
 use anyhow::anyhow;
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use petgraph::graph::{DiGraph, NodeIndex};
 use postgres::types::to_sql_checked;
 use postgres::types::FromSql;
 use postgres::types::ToSql;
 use postgres::Row;
 use postgres_types::Type as PsqlType;
+use serde::Deserialize;
+use serde::Serialize;
 
 use lezeh_common::types::ResultAnyError;
 
 type AnyString<'a> = Cow<'a, str>;
 pub type PsqlParamValue = Box<dyn ToSql + Sync>;
 
-#[derive(PartialEq, Hash, Eq, Debug, Clone)]
+/// Quote an identifier so that mixed case and reserved word names are kept as is, e.g. `"Order"`
+pub fn escape_identifier(identifier: &str) -> String {
+  return postgres_protocol::escape::escape_identifier(identifier);
+}
+
+#[derive(PartialEq, Hash, Eq, Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PsqlTableColumn {
   pub name: String,
   pub data_type: String,
+
+  /// Column is `GENERATED ... AS IDENTITY`
+  #[serde(default)]
+  pub is_identity: bool,
+
+  /// Column value is computed from other columns, it can't be inserted
+  #[serde(default)]
+  pub is_generated: bool,
+
+  /// Default expression of the column
+  #[serde(default)]
+  pub default_value: Option<String>,
+
+  /// Sequence owned by the column, e.g. serial or identity column
+  #[serde(default)]
+  pub sequence_name: Option<String>,
+
+  #[serde(default)]
+  pub is_nullable: bool,
+
+  /// 1 based position of the column in the table
+  #[serde(default)]
+  pub ordinal_position: i32,
+
+  /// Max length of character columns, e.g. 255 for `varchar(255)`
+  #[serde(default)]
+  pub character_maximum_length: Option<i32>,
+
+  /// Type as written in DDL, e.g. `numeric(10,2)` or mysql `enum('a','b')`
+  #[serde(default)]
+  pub column_type: Option<String>,
+
+  /// Postgres enum type of the column
+  #[serde(default)]
+  pub enum_type: Option<PsqlEnumType>,
+
+  /// Expression of generated column
+  #[serde(default)]
+  pub generation_expression: Option<String>,
+
+  /// Mysql AUTO_INCREMENT column
+  #[serde(default)]
+  pub is_auto_increment: bool,
+}
+
+#[derive(PartialEq, Hash, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct PsqlEnumType {
+  pub schema: String,
+  pub name: String,
+  pub labels: Vec<String>,
 }
 
 impl PsqlTableColumn {
@@ -31,16 +91,21 @@ impl PsqlTableColumn {
     return PsqlTableColumn {
       name: name.into().to_string(),
       data_type: data_type.into().to_string(),
+      ..Default::default()
     };
   }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub struct PsqlForeignKey {
   pub name: String,
   pub column: PsqlTableColumn,
   pub foreign_table_schema: String,
   pub foreign_table_name: String,
+
+  /// False for postgres `NOT VALID` constraints, existing rows are not checked against them
+  #[serde(default = "PsqlForeignKey::default_is_validated")]
+  pub is_validated: bool,
 }
 
 impl PsqlForeignKey {
@@ -58,11 +123,16 @@ impl PsqlForeignKey {
       column,
       foreign_table_schema: foreign_table_schema.into().to_string(),
       foreign_table_name: foreign_table_name.into().to_string(),
+      is_validated: true,
     };
   }
+
+  fn default_is_validated() -> bool {
+    return true;
+  }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Debug, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Debug, Clone, Serialize, Deserialize)]
 pub struct PsqlTableIdentity {
   pub schema: String,
   pub name: String,
@@ -74,10 +144,21 @@ impl std::convert::TryFrom<&str> for PsqlTableIdentity {
   /// Try to create PsqlTableIdentity from string with format `{schema}.{tableName}`
   /// for example public.users
   fn try_from(value: &str) -> Result<Self, Self::Error> {
+    return PsqlTableIdentity::from_str_with_default_schema(value, "public");
+  }
+}
+
+impl PsqlTableIdentity {
+  /// Same as `PsqlTableIdentity::try_from` but falls back to the given schema
+  /// when the string does not contain schema.
+  pub fn from_str_with_default_schema(
+    value: &str,
+    default_schema: &str,
+  ) -> ResultAnyError<PsqlTableIdentity> {
     let mut splitted: Vec<&str> = value.split('.').collect();
 
     if splitted.len() == 1 {
-      splitted.insert(0, "public");
+      splitted.insert(0, default_schema);
     }
 
     if splitted.len() != 2 {
@@ -103,6 +184,15 @@ impl PsqlTableIdentity {
       name: name.into().to_string(),
     };
   }
+
+  /// Quoted schema and name to be used in sql, e.g. `"public"."Order"`
+  pub fn quoted(&self) -> String {
+    return format!(
+      "{}.{}",
+      escape_identifier(&self.schema),
+      escape_identifier(&self.name)
+    );
+  }
 }
 
 impl std::fmt::Display for PsqlTableIdentity {
@@ -118,13 +208,35 @@ impl Hash for PsqlTableIdentity {
   }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct PsqlUniqueConstraint {
+  pub name: String,
+  pub column_names: Vec<String>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+pub struct PsqlCheckConstraint {
+  pub name: String,
+
+  /// Full constraint definition, e.g. `CHECK ((total >= 0))`
+  pub definition: String,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub struct PsqlTable {
   pub id: PsqlTableIdentity,
   pub primary_column: PsqlTableColumn,
   pub columns: HashSet<PsqlTableColumn>,
   pub referenced_fk_by_constraint_name: HashMap<String, PsqlForeignKey>,
   pub referencing_fk_by_constraint_name: HashMap<String, PsqlForeignKey>,
+
+  /// Unique constraints and unique indexes without predicate or expression,
+  /// primary key is not included.
+  #[serde(default)]
+  pub unique_constraints: Vec<PsqlUniqueConstraint>,
+
+  #[serde(default)]
+  pub check_constraints: Vec<PsqlCheckConstraint>,
 }
 
 impl PsqlTable {
@@ -145,15 +257,192 @@ impl PsqlTable {
       columns,
       referenced_fk_by_constraint_name,
       referencing_fk_by_constraint_name,
+      unique_constraints: Default::default(),
+      check_constraints: Default::default(),
+    };
+  }
+
+  pub fn has_column(&self, column_name: &str) -> bool {
+    return self.primary_column.name == column_name
+      || self.columns.iter().any(|column| column.name == column_name);
+  }
+
+  /// Columns in the same order as the table definition
+  pub fn ordered_columns(&self) -> Vec<&PsqlTableColumn> {
+    let mut columns: Vec<&PsqlTableColumn> = self.columns.iter().collect();
+
+    columns.sort_by(|a, b| {
+      return a
+        .ordinal_position
+        .cmp(&b.ordinal_position)
+        .then_with(|| a.name.cmp(&b.name));
+    });
+
+    return columns;
+  }
+
+  /// Topological order by the foreign keys between the given tables, parents first.
+  /// Ties and tables of the same cycle are ordered by the table name.
+  pub fn dependency_ordered<'a>(tables: impl Iterator<Item = &'a PsqlTable>) -> Vec<&'a PsqlTable> {
+    let mut remaining_tables: BTreeMap<String, &'a PsqlTable> =
+      tables.map(|table| (table.id.to_string(), table)).collect();
+    let cycle_index_by_table_id = PsqlTable::cycle_index_by_table_id(remaining_tables.values());
+    let mut ordered_tables: Vec<&'a PsqlTable> = vec![];
+    let mut ordered_table_ids: HashSet<PsqlTableIdentity> = Default::default();
+
+    while !remaining_tables.is_empty() {
+      let next_table_key: String = remaining_tables
+        .iter()
+        .find(|(_, table)| {
+          return table.foreign_table_ids().all(|foreign_table_id| {
+            return match cycle_index_by_table_id.get(&foreign_table_id) {
+              Some(cycle_index) => {
+                cycle_index == &cycle_index_by_table_id[&table.id]
+                  || ordered_table_ids.contains(&foreign_table_id)
+              }
+              None => true,
+            };
+          });
+        })
+        .or_else(|| remaining_tables.iter().next())
+        .map(|(key, _)| key.clone())
+        .unwrap();
+
+      let table = remaining_tables.remove(&next_table_key).unwrap();
+
+      ordered_table_ids.insert(table.id.clone());
+      ordered_tables.push(table);
+    }
+
+    return ordered_tables;
+  }
+
+  fn foreign_table_ids(&self) -> impl Iterator<Item = PsqlTableIdentity> + '_ {
+    return self.referencing_fk_by_constraint_name.values().map(|fk| {
+      return PsqlTableIdentity::new(
+        fk.foreign_table_schema.as_str(),
+        fk.foreign_table_name.as_str(),
+      );
+    });
+  }
+
+  /// Tables that reference each other directly or indirectly share the same index
+  fn cycle_index_by_table_id<'a>(
+    tables: impl Iterator<Item = &'a &'a PsqlTable>,
+  ) -> HashMap<PsqlTableIdentity, usize> {
+    let mut graph: DiGraph<&PsqlTable, ()> = Default::default();
+    let node_index_by_table_id: HashMap<&PsqlTableIdentity, NodeIndex> = tables
+      .map(|table| (&table.id, graph.add_node(*table)))
+      .collect();
+
+    for node_index in node_index_by_table_id.values() {
+      for foreign_table_id in graph[*node_index].foreign_table_ids() {
+        if let Some(foreign_node_index) = node_index_by_table_id.get(&foreign_table_id) {
+          graph.add_edge(*node_index, *foreign_node_index, ());
+        }
+      }
+    }
+
+    return petgraph::algo::tarjan_scc(&graph)
+      .into_iter()
+      .enumerate()
+      .flat_map(|(cycle_index, node_indexes)| {
+        return node_indexes
+          .into_iter()
+          .map(move |node_index| (node_index, cycle_index));
+      })
+      .map(|(node_index, cycle_index)| (graph[node_index].id.clone(), cycle_index))
+      .collect();
+  }
+}
+
+/// Test fixture of a table, e.g.
+/// `PsqlTable::fixture("orders").references("user_id", "users").build()`
+#[cfg(test)]
+pub struct PsqlTableFixture {
+  table: PsqlTable,
+}
+
+#[cfg(test)]
+impl PsqlTable {
+  /// Table in the public schema with an integer `id` primary column and no other columns
+  pub fn fixture(name: &str) -> PsqlTableFixture {
+    return PsqlTableFixture {
+      table: PsqlTable::new(
+        "public",
+        name,
+        PsqlTableColumn::new("id", "integer"),
+        Default::default(),
+        Default::default(),
+        Default::default(),
+      ),
     };
   }
 }
 
+#[cfg(test)]
+impl PsqlTableFixture {
+  pub fn schema(mut self, schema: &str) -> PsqlTableFixture {
+    self.table.id.schema = schema.to_owned();
+
+    return self;
+  }
+
+  pub fn primary_column(mut self, column: PsqlTableColumn) -> PsqlTableFixture {
+    self.table.primary_column = column;
+
+    return self;
+  }
+
+  pub fn columns(mut self, columns: Vec<PsqlTableColumn>) -> PsqlTableFixture {
+    self.table.columns.extend(columns);
+
+    return self;
+  }
+
+  /// Integer foreign key named `{table}_{column}_foreign` to a public table
+  pub fn references(self, column_name: &str, foreign_table_name: &str) -> PsqlTableFixture {
+    let fk = PsqlForeignKey::new(
+      format!("{}_{}_foreign", self.table.id.name, column_name).as_str(),
+      PsqlTableColumn::new(column_name, "integer"),
+      "public",
+      foreign_table_name,
+    );
+
+    return self.referencing_fks(vec![fk]);
+  }
+
+  pub fn referencing_fks(mut self, fks: Vec<PsqlForeignKey>) -> PsqlTableFixture {
+    self
+      .table
+      .referencing_fk_by_constraint_name
+      .extend(fks.into_iter().map(|fk| (fk.name.clone(), fk)));
+
+    return self;
+  }
+
+  pub fn referenced_fks(mut self, fks: Vec<PsqlForeignKey>) -> PsqlTableFixture {
+    self
+      .table
+      .referenced_fk_by_constraint_name
+      .extend(fks.into_iter().map(|fk| (fk.name.clone(), fk)));
+
+    return self;
+  }
+
+  pub fn build(self) -> PsqlTable {
+    return self.table;
+  }
+}
+
+/// Column name and value pairs of a row, ordered the same way as the db returns them.
+pub type PsqlRowValues = Vec<(String, FromSqlSink)>;
+
 #[derive(Clone)]
 pub struct PsqlTableRow {
   pub table: PsqlTable,
   pub row_id_representation: String,
-  inner_row: Rc<Row>,
+  inner_row: Rc<PsqlRowValues>,
 }
 
 impl Ord for PsqlTableRow {
@@ -191,51 +480,81 @@ impl std::fmt::Display for PsqlTableRow {
 }
 
 impl PsqlTableRow {
-  pub fn new(table: PsqlTable, row: Rc<Row>) -> ResultAnyError<PsqlTableRow> {
-    let row_id_sql_sink = row.get::<'_, _, FromSqlSink>("id");
+  pub fn new(table: PsqlTable, values: PsqlRowValues) -> ResultAnyError<PsqlTableRow> {
+    let row_id_sql_sink = values
+      .iter()
+      .find(|(column_name, _)| *column_name == table.primary_column.name)
+      .map(|(_, value)| value)
+      .ok_or_else(|| {
+        return anyhow!(
+          "Row of table {} does not contain primary column {}",
+          table.id,
+          table.primary_column.name
+        );
+      })?;
 
     return row_id_sql_sink.to_string_for_statement().map(|row_id| {
       return PsqlTableRow {
         table,
         row_id_representation: row_id.trim_matches('\'').to_string(),
-        inner_row: row,
+        inner_row: Rc::new(values),
       };
     });
   }
+
+  pub fn from_row(table: PsqlTable, row: &Row) -> ResultAnyError<PsqlTableRow> {
+    let values = row
+      .columns()
+      .iter()
+      .enumerate()
+      .map(|(index, column)| {
+        return row
+          .try_get::<'_, _, FromSqlSink>(index)
+          .map(|value| (column.name().to_string(), value))
+          .map_err(anyhow::Error::from);
+      })
+      .collect::<ResultAnyError<PsqlRowValues>>()?;
+
+    return PsqlTableRow::new(table, values);
+  }
 }
 
 impl PsqlTableRow {
-  pub fn get_id(&self, id_column_spec: &PsqlTableColumn) -> PsqlParamValue {
-    let inner_row = &self.inner_row;
-
-    if id_column_spec.data_type == "integer" {
-      return Box::new(inner_row.get::<_, i32>(id_column_spec.name.as_str()));
-    }
+  pub fn get_id(&self, id_column_spec: &PsqlTableColumn) -> ResultAnyError<&FromSqlSink> {
+    return self.get_value(&id_column_spec.name).ok_or_else(|| {
+      return anyhow!(
+        "Row {} does not contain column {}",
+        self,
+        id_column_spec.name
+      );
+    });
+  }
 
-    if id_column_spec.data_type == "uuid" {
-      return Box::new(inner_row.get::<_, Uuid>(id_column_spec.name.as_str()));
-    }
+  pub fn get_value(&self, column_name: &str) -> Option<&FromSqlSink> {
+    return self
+      .inner_row
+      .iter()
+      .find(|(name, _)| name == column_name)
+      .map(|(_, value)| value);
+  }
 
-    return Box::new(inner_row.get::<_, String>(id_column_spec.name.as_str()));
+  pub fn get_values(&self) -> &PsqlRowValues {
+    return &self.inner_row;
   }
 
   pub fn get_column_names(&self) -> Vec<&str> {
-    return self.inner_row.columns().iter().map(|c| c.name()).collect();
+    return self
+      .inner_row
+      .iter()
+      .map(|(column_name, _)| column_name.as_str())
+      .collect();
   }
 
-  pub fn get_column_value_map<'a, T>(&'a self) -> HashMap<&str, T>
-  where
-    T: FromSql<'a>,
-  {
+  pub fn get_column_value_map(&self) -> HashMap<&str, &FromSqlSink> {
     return self
       .inner_row
-      .columns()
       .iter()
-      .map(|c| {
-        let val = self.inner_row.get::<'a, _, T>(c.name());
-
-        return (c.name(), val);
-      })
+      .map(|(column_name, value)| (column_name.as_str(), value))
       .collect();
   }
 }
@@ -323,6 +642,7 @@ impl ToString for Uuid {
 
 /// Structure that act as a sink to drain bytes
 /// from postgres::row::Row
+#[derive(Debug, Clone)]
 pub struct FromSqlSink {
   raw: Vec<u8>,
   ty: Option<postgres::types::Type>, // None if null
@@ -342,18 +662,122 @@ impl<'a> FromSql<'a> for FromSqlSink {
   }
 
   fn from_sql_null(_ty: &PsqlType) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
-    return Ok(FromSqlSink {
-      raw: vec![],
-      ty: None,
-    });
+    return Ok(FromSqlSink::null());
+  }
+
+  fn accepts(_ty: &PsqlType) -> bool {
+    return true;
+  }
+}
+
+/// Allows a fetched value to be passed back as a query param, for example
+/// when we fetch the children of a row using its primary key value.
+impl ToSql for FromSqlSink {
+  fn to_sql(
+    &self,
+    ty: &PsqlType,
+    out: &mut postgres_types::private::BytesMut,
+  ) -> Result<postgres_types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+    let own_ty: &PsqlType = match self.ty.as_ref() {
+      None => return Ok(postgres_types::IsNull::Yes),
+      Some(own_ty) => own_ty,
+    };
+
+    if own_ty == ty || (FromSqlSink::is_text_type(own_ty) && FromSqlSink::is_text_type(ty)) {
+      out.extend_from_slice(&self.raw);
+
+      return Ok(postgres_types::IsNull::No);
+    }
+
+    // Foreign key column could have different integer width
+    // than the column it references e.g. int4 referencing int8.
+    let integer: i64 = match *own_ty {
+      PsqlType::INT2 => postgres_protocol::types::int2_from_sql(&self.raw)? as i64,
+      PsqlType::INT4 => postgres_protocol::types::int4_from_sql(&self.raw)? as i64,
+      PsqlType::INT8 => postgres_protocol::types::int8_from_sql(&self.raw)?,
+      _ => return Err(format!("Could not convert {} value into {}", own_ty, ty).into()),
+    };
+
+    return match *ty {
+      PsqlType::INT2 => i16::try_from(integer)?.to_sql(ty, out),
+      PsqlType::INT4 => i32::try_from(integer)?.to_sql(ty, out),
+      PsqlType::INT8 => integer.to_sql(ty, out),
+      _ => Err(format!("Could not convert {} value into {}", own_ty, ty).into()),
+    };
   }
 
   fn accepts(_ty: &PsqlType) -> bool {
     return true;
   }
+
+  to_sql_checked!();
 }
 
 impl FromSqlSink {
+  pub fn null() -> FromSqlSink {
+    return FromSqlSink {
+      raw: vec![],
+      ty: None,
+    };
+  }
+
+  /// Create sink from a rust value, the value will be serialized
+  /// into postgres binary format of the given type.
+  pub fn from_value(value: &(dyn ToSql + Sync), ty: PsqlType) -> ResultAnyError<FromSqlSink> {
+    let mut raw = postgres_types::private::BytesMut::new();
+
+    return match value.to_sql_checked(&ty, &mut raw) {
+      Ok(postgres_types::IsNull::Yes) => Ok(FromSqlSink::null()),
+      Ok(postgres_types::IsNull::No) => Ok(FromSqlSink {
+        raw: raw.to_vec(),
+        ty: Some(ty),
+      }),
+      Err(err) => Err(anyhow::Error::msg(err)),
+    };
+  }
+
+  /// Create sink from raw bytes that are already in postgres binary format of the given type.
+  pub fn from_raw(raw: Vec<u8>, ty: PsqlType) -> FromSqlSink {
+    return FromSqlSink { raw, ty: Some(ty) };
+  }
+
+  pub fn get_type(&self) -> Option<&PsqlType> {
+    return self.ty.as_ref();
+  }
+
+  pub fn is_null(&self) -> bool {
+    return self.ty.is_none();
+  }
+
+  pub fn get_raw(&self) -> &[u8] {
+    return &self.raw;
+  }
+
+  pub fn is_text_type(ty: &PsqlType) -> bool {
+    return matches!(
+      *ty,
+      PsqlType::TEXT | PsqlType::VARCHAR | PsqlType::BPCHAR | PsqlType::NAME
+    );
+  }
+
+  /// Whether the value can be written as is in a statement without quotes.
+  pub fn is_unquoted_literal(&self) -> bool {
+    return match self.ty {
+      Some(PsqlType::BOOL)
+      | Some(PsqlType::INT2)
+      | Some(PsqlType::INT4)
+      | Some(PsqlType::INT8)
+      | Some(PsqlType::NUMERIC) => true,
+      Some(PsqlType::FLOAT4) => postgres_protocol::types::float4_from_sql(&self.raw)
+        .map(f32::is_finite)
+        .unwrap_or(false),
+      Some(PsqlType::FLOAT8) => postgres_protocol::types::float8_from_sql(&self.raw)
+        .map(f64::is_finite)
+        .unwrap_or(false),
+      _ => false,
+    };
+  }
+
   pub fn escape_string<T>(val: T) -> String
   where
     T: ToString,
@@ -362,14 +786,15 @@ impl FromSqlSink {
     return postgres_protocol::escape::escape_literal(&val.to_string());
   }
 
-  pub fn to_string_for_statement(&self) -> ResultAnyError<String> {
+  /// Text representation of the value without any quoting, None if null.
+  pub fn to_text(&self) -> ResultAnyError<Option<String>> {
     if self.ty.is_none() {
-      return Ok("null".into());
+      return Ok(None);
     }
 
     let ty: &PsqlType = self.ty.as_ref().unwrap();
 
-    return match *ty {
+    let text: ResultAnyError<String> = match *ty {
       PsqlType::BOOL => postgres_protocol::types::bool_from_sql(&self.raw[..])
         .map(|val| val.to_string())
         .map_err(anyhow::Error::msg),
@@ -386,35 +811,74 @@ impl FromSqlSink {
         .map(|val| val.to_string())
         .map_err(anyhow::Error::msg),
 
+      PsqlType::FLOAT4 => postgres_protocol::types::float4_from_sql(&self.raw[..])
+        .map(|val| FromSqlSink::float_to_text(val as f64))
+        .map_err(anyhow::Error::msg),
+
+      PsqlType::FLOAT8 => postgres_protocol::types::float8_from_sql(&self.raw[..])
+        .map(FromSqlSink::float_to_text)
+        .map_err(anyhow::Error::msg),
+
       // https://github.com/sfackler/rust-postgres/blob/master/postgres-types/src/chrono_04.rs
-      PsqlType::DATE => {
-        return NaiveDate::from_sql(ty, &self.raw[..])
-          .map(FromSqlSink::escape_string)
-          .map_err(anyhow::Error::msg);
-      }
+      PsqlType::DATE => NaiveDate::from_sql(ty, &self.raw[..])
+        .map(|val| val.to_string())
+        .map_err(anyhow::Error::msg),
 
-      PsqlType::TIMESTAMP | PsqlType::TIMESTAMPTZ => {
-        return NaiveDateTime::from_sql(ty, &self.raw[..])
-          .map(FromSqlSink::escape_string)
-          .map_err(anyhow::Error::msg);
-      }
+      PsqlType::TIME => NaiveTime::from_sql(ty, &self.raw[..])
+        .map(|val| val.to_string())
+        .map_err(anyhow::Error::msg),
 
-      PsqlType::NUMERIC => rust_decimal::Decimal::from_sql(&ty, &self.raw)
+      PsqlType::TIMESTAMP | PsqlType::TIMESTAMPTZ => NaiveDateTime::from_sql(ty, &self.raw[..])
         .map(|val| val.to_string())
         .map_err(anyhow::Error::msg),
 
-      PsqlType::UUID => {
-        return Uuid::from_sql(ty, &self.raw)
-          .map(|val| {
-            return format!("'{}'", val.to_string());
-          })
-          .map_err(anyhow::Error::msg);
-      }
+      PsqlType::NUMERIC => rust_decimal::Decimal::from_sql(ty, &self.raw)
+        .map(|val| val.to_string())
+        .map_err(anyhow::Error::msg),
+
+      PsqlType::UUID => Uuid::from_sql(ty, &self.raw)
+        .map(|val| val.to_string())
+        .map_err(anyhow::Error::msg),
+
+      PsqlType::BYTEA => Ok(format!(
+        "\\x{}",
+        self
+          .raw
+          .iter()
+          .map(|byte| format!("{:02x}", byte))
+          .collect::<String>()
+      )),
 
       _ => postgres_protocol::types::text_from_sql(&self.raw[..])
-        .map(FromSqlSink::escape_string)
+        .map(ToOwned::to_owned)
         .map_err(anyhow::Error::msg),
     };
+
+    return text.map(Some);
+  }
+
+  pub fn to_string_for_statement(&self) -> ResultAnyError<String> {
+    return match self.to_text()? {
+      None => Ok("null".into()),
+      Some(text) if self.is_unquoted_literal() => Ok(text),
+      Some(text) => Ok(FromSqlSink::escape_string(text)),
+    };
+  }
+
+  fn float_to_text(val: f64) -> String {
+    if val.is_nan() {
+      return "NaN".into();
+    }
+
+    if val.is_infinite() {
+      return if val.is_sign_positive() {
+        "Infinity".into()
+      } else {
+        "-Infinity".into()
+      };
+    }
+
+    return val.to_string();
   }
 }
 
@@ -462,6 +926,66 @@ mod test {
           "83166f85-d37a-4fe7-a0f6-ad5103d03f8a"
         );
 
+        return Ok(());
+      }
+    }
+  }
+  mod from_sql_sink {
+    use super::*;
+
+    mod to_string_for_statement {
+      use super::*;
+
+      #[test]
+      fn it_should_not_quote_numbers() -> ResultAnyError<()> {
+        let sink = FromSqlSink::from_value(&12.5_f64, PsqlType::FLOAT8)?;
+
+        assert_eq!(sink.to_string_for_statement()?, "12.5");
+
+        return Ok(());
+      }
+
+      #[test]
+      fn it_should_quote_non_finite_float() -> ResultAnyError<()> {
+        let sink = FromSqlSink::from_value(&f64::NAN, PsqlType::FLOAT8)?;
+
+        assert_eq!(sink.to_string_for_statement()?, "'NaN'");
+
+        return Ok(());
+      }
+
+      #[test]
+      fn it_should_escape_text() -> ResultAnyError<()> {
+        let sink = FromSqlSink::from_value(&"it's", PsqlType::TEXT)?;
+
+        assert_eq!(sink.to_string_for_statement()?, "'it''s'");
+
+        return Ok(());
+      }
+    }
+
+    mod to_sql {
+      use super::*;
+
+      #[test]
+      fn it_should_convert_integer_width() -> Result<(), Box<dyn std::error::Error + Sync + Send>> {
+        let sink = FromSqlSink::from_value(&7_i32, PsqlType::INT4)?;
+        let mut out = postgres_types::private::BytesMut::new();
+
+        sink.to_sql(&PsqlType::INT8, &mut out)?;
+
+        assert_eq!(i64::from_sql(&PsqlType::INT8, &out)?, 7);
+
+        return Ok(());
+      }
+
+      #[test]
+      fn it_should_reject_incompatible_type() -> ResultAnyError<()> {
+        let sink = FromSqlSink::from_value(&"abc", PsqlType::TEXT)?;
+        let mut out = postgres_types::private::BytesMut::new();
+
+        assert!(sink.to_sql(&PsqlType::INT8, &mut out).is_err());
+
         return Ok(());
       }
     }
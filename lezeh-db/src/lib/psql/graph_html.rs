@@ -0,0 +1,277 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use serde::Serialize;
+
+use crate::asset::Asset;
+use crate::psql::dto::PsqlTableRow;
+use crate::psql::relation_fetcher::RowGraph;
+use lezeh_common::graph as graph_util;
+use lezeh_common::handlebars::HandlebarsRenderer;
+use lezeh_common::types::ResultAnyError;
+
+const TEMPLATE_PATH: &str = "graph.html.hbs";
+
+// Keep in sync with the constants of the template
+const LEVEL_WIDTH: i32 = 320;
+const LINE_HEIGHT: i32 = 16;
+const NODE_PADDING: i32 = 8;
+const NODE_GAP: i32 = 24;
+
+#[derive(Serialize, Debug, PartialEq)]
+struct HtmlGraphNode {
+  id: usize,
+  table: String,
+  row_id: String,
+  label: String,
+  x: i32,
+  y: i32,
+  height: i32,
+  is_root: bool,
+
+  /// Text representation of every column value, None if null
+  values: Vec<(String, Option<String>)>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct HtmlGraphEdge {
+  from: usize,
+  to: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct HtmlGraph {
+  title: String,
+  tables: Vec<String>,
+  nodes: Vec<HtmlGraphNode>,
+  edges: Vec<HtmlGraphEdge>,
+}
+
+#[derive(Serialize)]
+struct HtmlGraphTemplateData {
+  graph_json: String,
+}
+
+/// Single html page to explore the row graph, it does not load any external resources
+/// so the rows never leave the machine. Parents are laid out on the left of their children.
+pub struct GraphHtml<'a> {
+  graph: &'a RowGraph,
+  root_node_indexes: Vec<NodeIndex>,
+}
+
+impl<'a> GraphHtml<'a> {
+  pub fn new(graph: &'a RowGraph, root_node_indexes: &[NodeIndex]) -> GraphHtml<'a> {
+    return GraphHtml {
+      graph,
+      root_node_indexes: root_node_indexes.to_vec(),
+    };
+  }
+
+  /// `node_label` renders the text displayed on each node
+  pub fn render(
+    &self,
+    title: &str,
+    node_label: impl Fn(&PsqlTableRow) -> String,
+  ) -> ResultAnyError<String> {
+    let template =
+      Asset::get(TEMPLATE_PATH).ok_or_else(|| anyhow!("Cannot get template {}", TEMPLATE_PATH))?;
+
+    // Values could contain </script> or <!--<script that break the script element,
+    // these characters only appear in json strings so they're escaped as unicode.
+    let graph_json = serde_json::to_string(&self.create_html_graph(title, node_label)?)?
+      .replace('<', "\\u003c")
+      .replace('>', "\\u003e")
+      .replace('&', "\\u0026");
+
+    return HandlebarsRenderer::new()
+      .render_from_template_path(&mut template.as_ref(), HtmlGraphTemplateData { graph_json });
+  }
+
+  fn create_html_graph(
+    &self,
+    title: &str,
+    node_label: impl Fn(&PsqlTableRow) -> String,
+  ) -> ResultAnyError<HtmlGraph> {
+    let level_by_row: HashMap<&Rc<PsqlTableRow>, i32> =
+      graph_util::create_level_by_node_from_roots(self.graph, &self.root_node_indexes);
+
+    let mut node_indexes_by_level: BTreeMap<i32, Vec<NodeIndex>> = Default::default();
+
+    for node_index in self.graph.node_indices() {
+      let level = level_by_row
+        .get(&self.graph[node_index])
+        .cloned()
+        .unwrap_or_default();
+
+      node_indexes_by_level
+        .entry(level)
+        .or_default()
+        .push(node_index);
+    }
+
+    let mut nodes: Vec<HtmlGraphNode> = vec![];
+
+    for (level, mut node_indexes) in node_indexes_by_level {
+      node_indexes.sort_by(|a, b| self.graph[*a].cmp(&self.graph[*b]));
+
+      let mut level_nodes: Vec<HtmlGraphNode> = vec![];
+      let mut y = 0;
+
+      for node_index in node_indexes {
+        let row: &PsqlTableRow = &self.graph[node_index];
+        let label = node_label(row);
+
+        // Table name is rendered above the label
+        let height = NODE_PADDING * 2 + LINE_HEIGHT * (label.lines().count() as i32 + 1);
+
+        let values = row
+          .get_values()
+          .iter()
+          .map(|(column_name, value)| value.to_text().map(|text| (column_name.clone(), text)))
+          .collect::<ResultAnyError<Vec<(String, Option<String>)>>>()?;
+
+        level_nodes.push(HtmlGraphNode {
+          id: node_index.index(),
+          table: row.table.id.to_string(),
+          row_id: row.row_id_representation.clone(),
+          label,
+          x: level * LEVEL_WIDTH,
+          y,
+          height,
+          is_root: self.root_node_indexes.contains(&node_index),
+          values,
+        });
+
+        y += height + NODE_GAP;
+      }
+
+      // Center every level vertically
+      for node in level_nodes.iter_mut() {
+        node.y -= y / 2;
+      }
+
+      nodes.extend(level_nodes);
+    }
+
+    nodes.sort_by_key(|node| node.id);
+
+    let tables: BTreeSet<String> = nodes.iter().map(|node| node.table.clone()).collect();
+    let edges: Vec<HtmlGraphEdge> = self
+      .graph
+      .edge_references()
+      .map(|edge| HtmlGraphEdge {
+        from: edge.source().index(),
+        to: edge.target().index(),
+      })
+      .collect();
+
+    return Ok(HtmlGraph {
+      title: title.to_owned(),
+      tables: tables.into_iter().collect(),
+      nodes,
+      edges,
+    });
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::dto::*;
+  use postgres_types::Type as PsqlType;
+
+  fn create_row(table: &PsqlTable, values: Vec<(&str, Option<i32>)>) -> Rc<PsqlTableRow> {
+    let values = values
+      .into_iter()
+      .map(|(column_name, value)| {
+        let value = match value {
+          Some(value) => FromSqlSink::from_value(&value, PsqlType::INT4).unwrap(),
+          None => FromSqlSink::null(),
+        };
+
+        return (column_name.to_owned(), value);
+      })
+      .collect();
+
+    return Rc::new(PsqlTableRow::new(table.clone(), values).unwrap());
+  }
+
+  mod render {
+    use super::*;
+
+    #[test]
+    fn it_should_lay_out_parents_left_and_embed_row_values() -> ResultAnyError<()> {
+      let users = PsqlTable::fixture("users").build();
+      let orders = PsqlTable::fixture("orders").build();
+
+      let mut graph = RowGraph::new();
+      let order_index = graph.add_node(create_row(
+        &orders,
+        vec![("id", Some(1)), ("user_id", Some(7)), ("coupon_id", None)],
+      ));
+      let user_index = graph.add_node(create_row(&users, vec![("id", Some(7))]));
+
+      graph.update_edge(order_index, user_index, -1);
+
+      let graph_html = GraphHtml::new(&graph, &[order_index]);
+      let node_label = |row: &PsqlTableRow| format!("`id` {}", row.row_id_representation);
+
+      assert_eq!(
+        graph_html.create_html_graph("public.orders.id = 1", node_label)?,
+        HtmlGraph {
+          title: "public.orders.id = 1".into(),
+          tables: vec!["public.orders".into(), "public.users".into()],
+          nodes: vec![
+            HtmlGraphNode {
+              id: 0,
+              table: "public.orders".into(),
+              row_id: "1".into(),
+              label: "`id` 1".into(),
+              x: LEVEL_WIDTH,
+              y: -(48 + NODE_GAP) / 2,
+              height: 48,
+              is_root: true,
+              values: vec![
+                ("id".into(), Some("1".into())),
+                ("user_id".into(), Some("7".into())),
+                ("coupon_id".into(), None),
+              ],
+            },
+            HtmlGraphNode {
+              id: 1,
+              table: "public.users".into(),
+              row_id: "7".into(),
+              label: "`id` 7".into(),
+              x: 0,
+              y: -(48 + NODE_GAP) / 2,
+              height: 48,
+              is_root: false,
+              values: vec![("id".into(), Some("7".into()))],
+            },
+          ],
+          edges: vec![HtmlGraphEdge { from: 0, to: 1 }],
+        }
+      );
+
+      let title = "</script><!--<script> & more";
+      let html = graph_html.render(title, node_label)?;
+      let graph_json = html
+        .split("<script id=\"graph-data\" type=\"application/json\">")
+        .nth(1)
+        .and_then(|html| html.split("</script>").next())
+        .unwrap_or_default();
+
+      assert!(!graph_json.contains(['<', '>', '&']));
+      assert_eq!(
+        serde_json::from_str::<serde_json::Value>(graph_json)?["title"],
+        title
+      );
+      assert!(!html.contains("{{graph_json}}"));
+
+      return Ok(());
+    }
+  }
+}
@@ -0,0 +1,330 @@
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use postgres_types::Type as PsqlType;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::psql::dto::{FromSqlSink, PsqlTableRow};
+use crate::psql::relation_fetcher::RowGraph;
+use lezeh_common::graph as graph_util;
+use lezeh_common::handlebars::HandlebarsRenderer;
+use lezeh_common::types::ResultAnyError;
+
+const TEMPLATE_NAME: &str = "graph";
+
+pub struct CompiledGraphTemplate {
+  renderer: HandlebarsRenderer,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct TemplateColumn {
+  name: String,
+  data_type: String,
+
+  /// Bools and integers are json bools and numbers, null is json null
+  /// and every other value is its text representation.
+  value: JsonValue,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct TemplateRow {
+  id: usize,
+  table: String,
+  schema: String,
+  table_name: String,
+  row_id: String,
+
+  /// Insert order of the row, parents have lower levels than their children
+  level: i32,
+  is_root: bool,
+
+  /// Column value by column name, e.g. `{{values.email}}`
+  values: BTreeMap<String, JsonValue>,
+
+  /// Columns in the order of the table, e.g. `{{#each columns}}{{name}}{{/each}}`
+  columns: Vec<TemplateColumn>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct TemplateTable<'a> {
+  id: String,
+  schema: String,
+  name: String,
+  primary_column: String,
+  rows: Vec<&'a TemplateRow>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct TemplateLevel<'a> {
+  level: i32,
+  rows: Vec<&'a TemplateRow>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct TemplateEdge {
+  child_id: usize,
+  parent_id: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct GraphTemplateData<'a> {
+  root: String,
+  rows: &'a [TemplateRow],
+
+  /// Ordered by the level of their first row so parent tables come first
+  tables: Vec<TemplateTable<'a>>,
+  levels: Vec<TemplateLevel<'a>>,
+  edges: Vec<TemplateEdge>,
+}
+
+/// Renders the row graph through a user handlebars template, e.g. to generate
+/// factory code, fixtures or reports. Rows are ordered the same way as the insert statements.
+pub struct GraphTemplate<'a> {
+  graph: &'a RowGraph,
+  root_node_indexes: Vec<NodeIndex>,
+}
+
+impl<'a> GraphTemplate<'a> {
+  pub fn new(graph: &'a RowGraph, root_node_indexes: &[NodeIndex]) -> GraphTemplate<'a> {
+    return GraphTemplate {
+      graph,
+      root_node_indexes: root_node_indexes.to_vec(),
+    };
+  }
+
+  /// Compiled before the graph is fetched so a broken template fails fast
+  pub fn compile(template: &str) -> ResultAnyError<CompiledGraphTemplate> {
+    let mut renderer = HandlebarsRenderer::new();
+
+    renderer.register_template(TEMPLATE_NAME, template)?;
+
+    return Ok(CompiledGraphTemplate { renderer });
+  }
+
+  /// `root` describes the cherry-picked rows, e.g. `public.orders.id = 1`
+  pub fn render(&self, template: &CompiledGraphTemplate, root: &str) -> ResultAnyError<String> {
+    let rows = self.create_template_rows()?;
+
+    return template.renderer.render_registered(
+      TEMPLATE_NAME,
+      GraphTemplate::create_data(&rows, root, self.graph),
+    );
+  }
+
+  fn create_template_rows(&self) -> ResultAnyError<Vec<TemplateRow>> {
+    let level_by_row: HashMap<&Rc<PsqlTableRow>, i32> =
+      graph_util::create_level_by_node_from_roots(self.graph, &self.root_node_indexes);
+    let mut rows: Vec<TemplateRow> = vec![];
+
+    for node_index in self.graph.node_indices() {
+      let row: &Rc<PsqlTableRow> = &self.graph[node_index];
+      let mut columns: Vec<TemplateColumn> = vec![];
+
+      for (column_name, value) in row.get_values().iter() {
+        let data_type: String = row
+          .table
+          .columns
+          .iter()
+          .find(|column| column.name == *column_name)
+          .map(|column| column.data_type.clone())
+          .or_else(|| value.get_type().map(|ty| ty.name().to_owned()))
+          .unwrap_or_default();
+
+        columns.push(TemplateColumn {
+          name: column_name.clone(),
+          data_type,
+          value: GraphTemplate::json_value(value)?,
+        });
+      }
+
+      rows.push(TemplateRow {
+        id: node_index.index(),
+        table: row.table.id.to_string(),
+        schema: row.table.id.schema.clone(),
+        table_name: row.table.id.name.clone(),
+        row_id: row.row_id_representation.clone(),
+        level: level_by_row.get(row).cloned().unwrap_or_default(),
+        is_root: self.root_node_indexes.contains(&node_index),
+        values: columns
+          .iter()
+          .map(|column| (column.name.clone(), column.value.clone()))
+          .collect(),
+        columns,
+      });
+    }
+
+    rows.sort_by(|a, b| {
+      return a
+        .level
+        .cmp(&b.level)
+        .then_with(|| self.graph[NodeIndex::new(a.id)].cmp(&self.graph[NodeIndex::new(b.id)]));
+    });
+
+    return Ok(rows);
+  }
+
+  fn create_data<'r>(
+    rows: &'r [TemplateRow],
+    root: &str,
+    graph: &RowGraph,
+  ) -> GraphTemplateData<'r> {
+    let mut tables: Vec<TemplateTable> = vec![];
+    let mut table_index_by_id: HashMap<&str, usize> = Default::default();
+    let mut levels: Vec<TemplateLevel> = vec![];
+
+    // Rows are sorted by level so tables and levels keep the insert order
+    for row in rows.iter() {
+      let table_index = *table_index_by_id.entry(&row.table).or_insert_with(|| {
+        let table = &graph[NodeIndex::new(row.id)].table;
+
+        tables.push(TemplateTable {
+          id: row.table.clone(),
+          schema: row.schema.clone(),
+          name: row.table_name.clone(),
+          primary_column: table.primary_column.name.clone(),
+          rows: vec![],
+        });
+
+        return tables.len() - 1;
+      });
+
+      tables[table_index].rows.push(row);
+
+      match levels.last_mut() {
+        Some(level) if level.level == row.level => level.rows.push(row),
+        _ => levels.push(TemplateLevel {
+          level: row.level,
+          rows: vec![row],
+        }),
+      }
+    }
+
+    let edges: Vec<TemplateEdge> = graph
+      .edge_references()
+      .map(|edge| TemplateEdge {
+        child_id: edge.source().index(),
+        parent_id: edge.target().index(),
+      })
+      .collect();
+
+    return GraphTemplateData {
+      root: root.to_owned(),
+      rows,
+      tables,
+      levels,
+      edges,
+    };
+  }
+
+  fn json_value(value: &FromSqlSink) -> ResultAnyError<JsonValue> {
+    let text: String = match value.to_text()? {
+      None => return Ok(JsonValue::Null),
+      Some(text) => text,
+    };
+
+    let json_value: Option<JsonValue> = match value.get_type() {
+      Some(&PsqlType::BOOL) => text.parse::<bool>().ok().map(JsonValue::from),
+      Some(&PsqlType::INT2) | Some(&PsqlType::INT4) | Some(&PsqlType::INT8) => {
+        text.parse::<i64>().ok().map(JsonValue::from)
+      }
+      Some(&PsqlType::FLOAT4) | Some(&PsqlType::FLOAT8) => text
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map(JsonValue::Number),
+      _ => None,
+    };
+
+    return Ok(json_value.unwrap_or(JsonValue::String(text)));
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::dto::*;
+
+  mod render {
+    use super::*;
+
+    #[test]
+    fn it_should_render_rows_in_insert_order_with_typed_values() -> ResultAnyError<()> {
+      let create_table = |name: &str| {
+        return PsqlTable::fixture(name)
+          .columns(vec![
+            PsqlTableColumn::new("id", "integer"),
+            PsqlTableColumn::new("name", "text"),
+          ])
+          .build();
+      };
+
+      let users = create_table("users");
+      let orders = create_table("orders");
+
+      let mut graph = RowGraph::new();
+      let order_index = graph.add_node(Rc::new(PsqlTableRow::new(
+        orders,
+        vec![
+          (
+            "id".to_owned(),
+            FromSqlSink::from_value(&1_i32, PsqlType::INT4)?,
+          ),
+          (
+            "user_id".to_owned(),
+            FromSqlSink::from_value(&7_i32, PsqlType::INT4)?,
+          ),
+          ("name".to_owned(), FromSqlSink::null()),
+        ],
+      )?));
+      let user_index = graph.add_node(Rc::new(PsqlTableRow::new(
+        users,
+        vec![
+          (
+            "id".to_owned(),
+            FromSqlSink::from_value(&7_i32, PsqlType::INT4)?,
+          ),
+          (
+            "name".to_owned(),
+            FromSqlSink::from_value(&"john", PsqlType::TEXT)?,
+          ),
+        ],
+      )?));
+
+      graph.update_edge(order_index, user_index, -1);
+
+      let graph_template = GraphTemplate::new(&graph, &[order_index]);
+      let rows = graph_template.create_template_rows()?;
+
+      assert_eq!(rows[0].values["id"], JsonValue::from(7));
+      assert_eq!(rows[1].values["name"], JsonValue::Null);
+
+      let template = indoc::indoc! {"
+        # {{root}}
+        {{#each tables}}
+        {{name}}, {{primary_column}}:{{#each rows}} {{row_id}}@{{level}}{{/each}}
+        {{/each}}
+        {{#each levels}}
+        {{level}}:{{#each rows}} {{table_name}}({{#each columns}}{{name}} {{data_type}}={{value}}{{#unless @last}}, {{/unless}}{{/each}}){{/each}}
+        {{/each}}
+        {{#each edges}}{{child_id}} -> {{parent_id}}{{/each}}
+      "};
+
+      assert_eq!(
+        graph_template.render(&GraphTemplate::compile(template)?, "public.orders.id = 1")?,
+        indoc::indoc! {"
+          # public.orders.id = 1
+          users, id: 7@0
+          orders, id: 1@1
+          0: users(id integer=7, name text=john)
+          1: orders(id integer=1, user_id int4=7, name text=)
+          0 -> 1
+        "}
+      );
+
+      return Ok(());
+    }
+  }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::config::DbDriver;
+
+#[derive(Default, Clone, Copy, Debug)]
+pub struct InsertScriptConfig {
+  /// Do not fire triggers of the target db, requires superuser on postgres
+  pub disable_triggers: bool,
+
+  /// Check foreign keys at commit instead of after each statement,
+  /// on postgres it only applies to `DEFERRABLE` constraints.
+  pub defer_constraints: bool,
+}
+
+pub struct InsertScriptHeader {
+  /// Db or snapshot that the rows are fetched from
+  pub source: String,
+
+  /// Root row selection, e.g. `public.orders.id = 1`
+  pub root: String,
+  pub created_at: DateTime<Utc>,
+  pub row_count_by_table: BTreeMap<String, usize>,
+}
+
+/// Insert statements wrapped in a transaction so the whole
+/// cherry-pick is applied atomically.
+pub struct InsertScript {
+  pub driver: DbDriver,
+  pub config: InsertScriptConfig,
+  pub header: InsertScriptHeader,
+  pub statements: Vec<String>,
+}
+
+impl InsertScript {
+  fn comment(value: &str) -> String {
+    return value.replace(['\n', '\r'], " ");
+  }
+
+  fn session_statements(&self) -> (Vec<&'static str>, Vec<&'static str>) {
+    let mut setup_statements: Vec<&'static str> = vec![];
+    let mut teardown_statements: Vec<&'static str> = vec![];
+
+    match self.driver {
+      DbDriver::Postgres => {
+        // SET LOCAL is reverted on COMMIT/ROLLBACK
+        if self.config.disable_triggers {
+          setup_statements.push("SET LOCAL session_replication_role = replica;");
+        }
+
+        if self.config.defer_constraints {
+          setup_statements.push("SET CONSTRAINTS ALL DEFERRED;");
+        }
+      }
+      DbDriver::Mysql => {
+        // Mysql can't disable triggers for a session,
+        // foreign key checks is the closest thing to deferred constraints.
+        if self.config.defer_constraints {
+          setup_statements.push("SET FOREIGN_KEY_CHECKS = 0;");
+          teardown_statements.push("SET FOREIGN_KEY_CHECKS = 1;");
+        }
+      }
+    }
+
+    return (setup_statements, teardown_statements);
+  }
+}
+
+impl std::fmt::Display for InsertScript {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let (setup_statements, teardown_statements) = self.session_statements();
+
+    writeln!(f, "-- Generated by lezeh db cherry-pick")?;
+    writeln!(
+      f,
+      "-- source: {}",
+      InsertScript::comment(&self.header.source)
+    )?;
+    writeln!(f, "-- root: {}", InsertScript::comment(&self.header.root))?;
+    writeln!(
+      f,
+      "-- created at: {}",
+      self
+        .header
+        .created_at
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+    )?;
+    writeln!(f, "-- rows:")?;
+
+    for (table, row_count) in self.header.row_count_by_table.iter() {
+      writeln!(f, "--   {}: {}", table, row_count)?;
+    }
+
+    writeln!(f)?;
+    writeln!(f, "BEGIN;")?;
+
+    for statement in setup_statements {
+      writeln!(f, "{}", statement)?;
+    }
+
+    writeln!(f)?;
+    writeln!(f, "{}", self.statements.join("\n"))?;
+
+    for statement in teardown_statements {
+      writeln!(f, "{}", statement)?;
+    }
+
+    return writeln!(f, "COMMIT;");
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn create_script(driver: DbDriver, config: InsertScriptConfig) -> InsertScript {
+    return InsertScript {
+      driver,
+      config,
+      header: InsertScriptHeader {
+        source: "testdb".to_owned(),
+        root: "public.orders.id = 1".to_owned(),
+        created_at: DateTime::parse_from_rfc3339("2022-02-10T08:30:00Z")
+          .unwrap()
+          .with_timezone(&Utc),
+        row_count_by_table: vec![
+          ("public.orders".to_owned(), 1),
+          ("public.order_items".to_owned(), 3),
+        ]
+        .into_iter()
+        .collect(),
+      },
+      statements: vec!["insert into public.orders (\"id\") VALUES\n  (1);".to_owned()],
+    };
+  }
+
+  mod fmt {
+    use super::*;
+
+    #[test]
+    fn it_should_wrap_statements_in_transaction() {
+      let script = create_script(
+        DbDriver::Postgres,
+        InsertScriptConfig {
+          disable_triggers: true,
+          defer_constraints: true,
+        },
+      );
+
+      assert_eq!(
+        script.to_string(),
+        indoc::indoc! {"
+          -- Generated by lezeh db cherry-pick
+          -- source: testdb
+          -- root: public.orders.id = 1
+          -- created at: 2022-02-10T08:30:00Z
+          -- rows:
+          --   public.order_items: 3
+          --   public.orders: 1
+
+          BEGIN;
+          SET LOCAL session_replication_role = replica;
+          SET CONSTRAINTS ALL DEFERRED;
+
+          insert into public.orders (\"id\") VALUES
+            (1);
+          COMMIT;
+        "}
+      );
+    }
+
+    #[test]
+    fn it_should_disable_foreign_key_checks_on_mysql() {
+      let script = create_script(
+        DbDriver::Mysql,
+        InsertScriptConfig {
+          disable_triggers: false,
+          defer_constraints: true,
+        },
+      );
+
+      assert!(script.to_string().ends_with(indoc::indoc! {"
+        BEGIN;
+        SET FOREIGN_KEY_CHECKS = 0;
+
+        insert into public.orders (\"id\") VALUES
+          (1);
+        SET FOREIGN_KEY_CHECKS = 1;
+        COMMIT;
+      "}));
+    }
+  }
+}
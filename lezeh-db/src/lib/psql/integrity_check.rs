@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+
+use crate::config::VirtualForeignKeyConfig;
+use crate::psql::dto::{PsqlForeignKey, PsqlTable, PsqlTableIdentity};
+use crate::psql::table_metadata::{OrphanRows, RowRelation, TableMetadata};
+use lezeh_common::types::ResultAnyError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelationSource {
+  /// Configured in `virtual_foreign_keys`, the db does not know about it
+  Virtual,
+
+  /// Declared with postgres `NOT VALID`, existing rows were never checked
+  NotValid,
+
+  /// Declared and validated, the db already guarantees it
+  Declared,
+}
+
+impl std::fmt::Display for RelationSource {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      RelationSource::Virtual => write!(f, "virtual"),
+      RelationSource::NotValid => write!(f, "not valid"),
+      RelationSource::Declared => write!(f, "declared"),
+    };
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckedRelation {
+  pub source: RelationSource,
+  pub relation: RowRelation,
+}
+
+impl CheckedRelation {
+  /// Relations of the given tables that should be checked, sorted by child table then column.
+  /// Validated foreign keys are only included with `include_declared`. Relations whose
+  /// foreign column can't be resolved are errors that come after the sorted relations.
+  pub fn collect(
+    psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+    virtual_foreign_keys: &[VirtualForeignKeyConfig],
+    default_schema: &str,
+    include_declared: bool,
+  ) -> Vec<ResultAnyError<CheckedRelation>> {
+    let mut checked_relations: Vec<CheckedRelation> = vec![];
+    let mut errors: Vec<anyhow::Error> = vec![];
+
+    for table in psql_table_by_id.values() {
+      for fk in table.referencing_fk_by_constraint_name.values() {
+        let source = if fk.is_validated {
+          RelationSource::Declared
+        } else {
+          RelationSource::NotValid
+        };
+
+        if source == RelationSource::Declared && !include_declared {
+          continue;
+        }
+
+        match CheckedRelation::relation_from_fk(psql_table_by_id, table, fk) {
+          Ok(relation) => checked_relations.push(CheckedRelation { source, relation }),
+          Err(err) => errors.push(anyhow!(
+            "Could not check {} {}.{} ({}), error: {}",
+            fk.name,
+            table.id,
+            fk.column.name,
+            source,
+            err
+          )),
+        }
+      }
+    }
+
+    for virtual_fk in virtual_foreign_keys {
+      match CheckedRelation::relation_from_virtual_fk(psql_table_by_id, virtual_fk, default_schema)
+      {
+        Ok(relation) => checked_relations.push(CheckedRelation {
+          source: RelationSource::Virtual,
+          relation,
+        }),
+        Err(err) => {
+          let table =
+            PsqlTableIdentity::from_str_with_default_schema(&virtual_fk.table, default_schema)
+              .map(|table_id| table_id.to_string())
+              .unwrap_or_else(|_| virtual_fk.table.clone());
+
+          errors.push(anyhow!(
+            "Could not check {} {}.{} ({}), error: {}",
+            CheckedRelation::virtual_fk_name(virtual_fk),
+            table,
+            virtual_fk.column,
+            RelationSource::Virtual,
+            err
+          ));
+        }
+      }
+    }
+
+    checked_relations.sort_by_key(|checked_relation| {
+      let relation = &checked_relation.relation;
+
+      return (
+        relation.table_id.to_string(),
+        relation.column_name.clone(),
+        relation.name.clone(),
+      );
+    });
+
+    errors.sort_by_key(|err| err.to_string());
+
+    return checked_relations
+      .into_iter()
+      .map(Ok)
+      .chain(errors.into_iter().map(Err))
+      .collect();
+  }
+
+  /// Only tables of the checked schema are loaded, a virtual foreign key
+  /// of another schema can't be checked.
+  fn relation_from_virtual_fk(
+    psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+    virtual_fk: &VirtualForeignKeyConfig,
+    default_schema: &str,
+  ) -> ResultAnyError<RowRelation> {
+    let table_id =
+      PsqlTableIdentity::from_str_with_default_schema(&virtual_fk.table, default_schema)?;
+    let table = psql_table_by_id
+      .get(&table_id)
+      .ok_or_else(|| anyhow!("Table {} is not loaded", table_id))?;
+    let foreign_table_id =
+      PsqlTableIdentity::from_str_with_default_schema(&virtual_fk.foreign_table, default_schema)?;
+    let foreign_column_name = match virtual_fk.foreign_column.as_ref() {
+      Some(foreign_column_name) => foreign_column_name.clone(),
+      None => CheckedRelation::primary_column_name(psql_table_by_id, &foreign_table_id)?,
+    };
+
+    return Ok(RowRelation {
+      name: CheckedRelation::virtual_fk_name(virtual_fk),
+      table_id,
+      primary_column_name: table.primary_column.name.clone(),
+      column_name: virtual_fk.column.clone(),
+      foreign_table_id,
+      foreign_column_name,
+    });
+  }
+
+  fn virtual_fk_name(virtual_fk: &VirtualForeignKeyConfig) -> String {
+    let table_name = virtual_fk.table.rsplit('.').next().unwrap_or_default();
+
+    return format!("{}_{}_virtual_fkey", table_name, virtual_fk.column);
+  }
+
+  fn relation_from_fk(
+    psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+    table: &PsqlTable,
+    fk: &PsqlForeignKey,
+  ) -> ResultAnyError<RowRelation> {
+    let foreign_table_id = PsqlTableIdentity::new(
+      fk.foreign_table_schema.as_str(),
+      fk.foreign_table_name.as_str(),
+    );
+
+    let foreign_table = psql_table_by_id
+      .get(&foreign_table_id)
+      .ok_or_else(|| anyhow!("Table {} is not loaded", foreign_table_id))?;
+
+    return Ok(RowRelation {
+      name: fk.name.clone(),
+      table_id: table.id.clone(),
+      primary_column_name: table.primary_column.name.clone(),
+      column_name: fk.column.name.clone(),
+      foreign_column_name: foreign_table.primary_column.name.clone(),
+      foreign_table_id,
+    });
+  }
+
+  fn primary_column_name(
+    psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+    table_id: &PsqlTableIdentity,
+  ) -> ResultAnyError<String> {
+    return psql_table_by_id
+      .get(table_id)
+      .map(|table| table.primary_column.name.clone())
+      .ok_or_else(|| {
+        return anyhow!(
+          "Table {} is not loaded, set foreign_column of the relation explicitly",
+          table_id
+        );
+      });
+  }
+}
+
+pub struct IntegrityCheckResult {
+  pub checked_relation: CheckedRelation,
+  pub orphan_rows: OrphanRows,
+}
+
+/// Orphaned child rows per relation, printed by `lezeh db integrity-check`
+pub struct IntegrityReport {
+  pub results: Vec<IntegrityCheckResult>,
+
+  /// Relations that could not be checked, one failure does not stop the others
+  pub errors: Vec<anyhow::Error>,
+}
+
+impl IntegrityReport {
+  pub fn orphan_row_count(&self) -> usize {
+    return self
+      .results
+      .iter()
+      .map(|result| result.orphan_rows.row_count)
+      .sum();
+  }
+
+  pub fn failed_count(&self) -> usize {
+    return self.errors.len();
+  }
+}
+
+impl std::fmt::Display for IntegrityReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let orphaned_relation_count = self
+      .results
+      .iter()
+      .filter(|result| result.orphan_rows.row_count > 0)
+      .count();
+
+    writeln!(
+      f,
+      "{} relations checked, {} with orphaned rows, {} orphaned rows, {} failed",
+      self.results.len(),
+      orphaned_relation_count,
+      self.orphan_row_count(),
+      self.failed_count()
+    )?;
+
+    for result in self.results.iter() {
+      let relation = &result.checked_relation.relation;
+
+      writeln!(f)?;
+      writeln!(f, "{} ({})", relation, result.checked_relation.source)?;
+      writeln!(f, "  orphaned rows: {}", result.orphan_rows.row_count)?;
+
+      for (primary_key, value) in result.orphan_rows.sample_keys.iter() {
+        writeln!(
+          f,
+          "  {} {} -> {} {}",
+          relation.primary_column_name, primary_key, relation.column_name, value
+        )?;
+      }
+    }
+
+    if !self.errors.is_empty() {
+      writeln!(f)?;
+      writeln!(f, "Failed:")?;
+
+      for err in self.errors.iter() {
+        writeln!(f, "  {}", err.to_string().replace('\n', " "))?;
+      }
+    }
+
+    return Ok(());
+  }
+}
+
+pub struct IntegrityCheck<'a> {
+  table_metadata: &'a dyn TableMetadata,
+  sample_size: usize,
+}
+
+impl<'a> IntegrityCheck<'a> {
+  pub fn new(table_metadata: &'a dyn TableMetadata, sample_size: usize) -> IntegrityCheck<'a> {
+    return IntegrityCheck {
+      table_metadata,
+      sample_size,
+    };
+  }
+
+  /// Relations that fail to be checked are reported as errors, the rest are still checked
+  pub fn check(&self, checked_relations: Vec<ResultAnyError<CheckedRelation>>) -> IntegrityReport {
+    let mut results: Vec<IntegrityCheckResult> = vec![];
+    let mut errors: Vec<anyhow::Error> = vec![];
+
+    for checked_relation in checked_relations {
+      let checked_relation = match checked_relation {
+        Ok(checked_relation) => checked_relation,
+        Err(err) => {
+          errors.push(err);
+
+          continue;
+        }
+      };
+
+      match self
+        .table_metadata
+        .find_orphan_rows(&checked_relation.relation, self.sample_size)
+      {
+        Ok(orphan_rows) => results.push(IntegrityCheckResult {
+          checked_relation,
+          orphan_rows,
+        }),
+        Err(err) => errors.push(anyhow!(
+          "Could not check {} ({}), error: {}",
+          checked_relation.relation,
+          checked_relation.source,
+          err
+        )),
+      }
+    }
+
+    return IntegrityReport { results, errors };
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::dto::PsqlTableColumn;
+  use crate::psql::table_metadata::MockTableMetadata;
+
+  mod check {
+    use super::*;
+
+    #[test]
+    fn it_should_check_not_valid_and_virtual_relations() -> ResultAnyError<()> {
+      let orders = PsqlTable::fixture("orders")
+        .referencing_fks(vec![
+          PsqlForeignKey {
+            is_validated: false,
+            ..PsqlForeignKey::new(
+              "orders_user_id_fkey",
+              PsqlTableColumn::new("user_id", "integer"),
+              "public",
+              "users",
+            )
+          },
+          PsqlForeignKey::new(
+            "orders_shop_id_fkey",
+            PsqlTableColumn::new("shop_id", "integer"),
+            "public",
+            "shops",
+          ),
+          PsqlForeignKey {
+            is_validated: false,
+            ..PsqlForeignKey::new(
+              "orders_warehouse_id_fkey",
+              PsqlTableColumn::new("warehouse_id", "integer"),
+              "archive",
+              "warehouses",
+            )
+          },
+        ])
+        .build();
+
+      let psql_table_by_id: HashMap<PsqlTableIdentity, PsqlTable> = vec![
+        orders,
+        PsqlTable::fixture("users")
+          .primary_column(PsqlTableColumn::new("user_id", "integer"))
+          .build(),
+        PsqlTable::fixture("shops").build(),
+        PsqlTable::fixture("coupons")
+          .primary_column(PsqlTableColumn::new("code", "integer"))
+          .build(),
+      ]
+      .into_iter()
+      .map(|table| (table.id.clone(), table))
+      .collect();
+
+      let virtual_foreign_keys = [
+        VirtualForeignKeyConfig {
+          table: "orders".into(),
+          column: "coupon_code".into(),
+          foreign_table: "public.coupons".into(),
+          foreign_column: None,
+        },
+        VirtualForeignKeyConfig {
+          table: "archive.orders".into(),
+          column: "shop_id".into(),
+          foreign_table: "shops".into(),
+          foreign_column: None,
+        },
+        VirtualForeignKeyConfig {
+          table: "public.orders.v2".into(),
+          column: "user_id".into(),
+          foreign_table: "users".into(),
+          foreign_column: None,
+        },
+      ];
+
+      let checked_relations =
+        CheckedRelation::collect(&psql_table_by_id, &virtual_foreign_keys, "public", false);
+
+      assert_eq!(
+        checked_relations
+          .iter()
+          .map(|checked_relation| {
+            return match checked_relation {
+              Ok(checked_relation) => Ok(checked_relation.clone()),
+              Err(err) => Err(err.to_string()),
+            };
+          })
+          .collect::<Vec<Result<CheckedRelation, String>>>(),
+        vec![
+          Ok(CheckedRelation {
+            source: RelationSource::Virtual,
+            relation: RowRelation {
+              name: "orders_coupon_code_virtual_fkey".into(),
+              table_id: PsqlTableIdentity::new("public", "orders"),
+              primary_column_name: "id".into(),
+              column_name: "coupon_code".into(),
+              foreign_table_id: PsqlTableIdentity::new("public", "coupons"),
+              foreign_column_name: "code".into(),
+            },
+          }),
+          Ok(CheckedRelation {
+            source: RelationSource::NotValid,
+            relation: RowRelation {
+              name: "orders_user_id_fkey".into(),
+              table_id: PsqlTableIdentity::new("public", "orders"),
+              primary_column_name: "id".into(),
+              column_name: "user_id".into(),
+              foreign_table_id: PsqlTableIdentity::new("public", "users"),
+              foreign_column_name: "user_id".into(),
+            },
+          }),
+          Err("Could not check orders_shop_id_virtual_fkey archive.orders.shop_id (virtual), error: Table archive.orders is not loaded".to_owned()),
+          Err("Could not check orders_warehouse_id_fkey public.orders.warehouse_id (not valid), error: Table archive.warehouses is not loaded".to_owned()),
+          Err("Could not check v2_user_id_virtual_fkey public.orders.v2.user_id (virtual), error: Invalid psql table identity string format, expected in format {schema}.{tableName}".to_owned()),
+        ]
+      );
+
+      let mut table_metadata = MockTableMetadata::new();
+
+      table_metadata
+        .expect_find_orphan_rows()
+        .times(2)
+        .returning(|relation, _| {
+          if relation.column_name == "coupon_code" {
+            return Err(anyhow!("canceling statement due to statement timeout"));
+          }
+
+          return Ok(OrphanRows {
+            row_count: 12,
+            sample_keys: vec![("3".into(), "99".into()), ("8".into(), "100".into())],
+          });
+        });
+
+      let report = IntegrityCheck::new(&table_metadata, 2).check(checked_relations);
+
+      assert_eq!(report.orphan_row_count(), 12);
+      assert_eq!(report.failed_count(), 4);
+      assert_eq!(
+        report.to_string(),
+        indoc::indoc! {"
+          1 relations checked, 1 with orphaned rows, 12 orphaned rows, 4 failed
+
+          orders_user_id_fkey public.orders.user_id -> public.users.user_id (not valid)
+            orphaned rows: 12
+            id 3 -> user_id 99
+            id 8 -> user_id 100
+
+          Failed:
+            Could not check orders_coupon_code_virtual_fkey public.orders.coupon_code -> public.coupons.code (virtual), error: canceling statement due to statement timeout
+            Could not check orders_shop_id_virtual_fkey archive.orders.shop_id (virtual), error: Table archive.orders is not loaded
+            Could not check orders_warehouse_id_fkey public.orders.warehouse_id (not valid), error: Table archive.warehouses is not loaded
+            Could not check v2_user_id_virtual_fkey public.orders.v2.user_id (virtual), error: Invalid psql table identity string format, expected in format {schema}.{tableName}
+        "}
+      );
+
+      return Ok(());
+    }
+  }
+}
@@ -0,0 +1,223 @@
+use std::collections::{BTreeSet, HashMap};
+
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+
+use crate::psql::dto::{PsqlForeignKey, PsqlTable, PsqlTableIdentity};
+
+/// Foreign key between 2 tables of the same cycle
+#[derive(Debug, PartialEq)]
+pub struct CycleForeignKey<'a> {
+  pub table: &'a PsqlTable,
+  pub fk: &'a PsqlForeignKey,
+
+  /// The load order inserts this table before its parent,
+  /// the constraint has to be deferred or the column filled in later.
+  pub is_loaded_before_parent: bool,
+}
+
+/// Tables that reference each other through their foreign keys,
+/// a self referencing table is a cycle of 1 table.
+#[derive(Debug, PartialEq)]
+pub struct TableCycle<'a> {
+  pub tables: Vec<&'a PsqlTable>,
+  pub fks: Vec<CycleForeignKey<'a>>,
+}
+
+/// Load and truncate order of every table, printed by `lezeh db load-order`
+pub struct LoadOrder<'a> {
+  /// Parents first
+  pub tables: Vec<&'a PsqlTable>,
+  pub cycles: Vec<TableCycle<'a>>,
+}
+
+impl<'a> LoadOrder<'a> {
+  pub fn new(psql_table_by_id: &'a HashMap<PsqlTableIdentity, PsqlTable>) -> LoadOrder<'a> {
+    let tables = PsqlTable::dependency_ordered(psql_table_by_id.values());
+    let cycles = LoadOrder::find_cycles(&tables);
+
+    return LoadOrder { tables, cycles };
+  }
+
+  /// Children first, the reverse of the load order
+  pub fn truncate_order(&self) -> Vec<&'a PsqlTable> {
+    return self.tables.iter().rev().cloned().collect();
+  }
+
+  fn find_cycles(ordered_tables: &[&'a PsqlTable]) -> Vec<TableCycle<'a>> {
+    let mut graph: DiGraph<&'a PsqlTable, &'a PsqlForeignKey> = DiGraph::new();
+    let node_index_by_table_id: HashMap<&PsqlTableIdentity, NodeIndex> = ordered_tables
+      .iter()
+      .map(|table| (&table.id, graph.add_node(table)))
+      .collect();
+
+    // Edges point from the child table to the parent table
+    for table in ordered_tables {
+      for fk in table.referencing_fk_by_constraint_name.values() {
+        let foreign_table_id = PsqlTableIdentity::new(
+          fk.foreign_table_schema.as_str(),
+          fk.foreign_table_name.as_str(),
+        );
+
+        if let Some(foreign_node_index) = node_index_by_table_id.get(&foreign_table_id) {
+          graph.add_edge(node_index_by_table_id[&table.id], *foreign_node_index, fk);
+        }
+      }
+    }
+
+    let position_by_node_index: HashMap<NodeIndex, usize> = ordered_tables
+      .iter()
+      .enumerate()
+      .map(|(position, table)| (node_index_by_table_id[&table.id], position))
+      .collect();
+
+    let mut cycles: Vec<TableCycle<'a>> = petgraph::algo::tarjan_scc(&graph)
+      .into_iter()
+      .filter_map(|node_indexes| {
+        let node_index_set: BTreeSet<NodeIndex> = node_indexes.iter().cloned().collect();
+        let mut fks: Vec<CycleForeignKey<'a>> = graph
+          .edge_references()
+          .filter(|edge| {
+            return node_index_set.contains(&edge.source())
+              && node_index_set.contains(&edge.target());
+          })
+          .map(|edge| CycleForeignKey {
+            table: graph[edge.source()],
+            fk: edge.weight(),
+            is_loaded_before_parent: position_by_node_index[&edge.source()]
+              <= position_by_node_index[&edge.target()],
+          })
+          .collect();
+
+        // A single table without self referencing foreign key is not a cycle
+        if fks.is_empty() {
+          return None;
+        }
+
+        let mut tables: Vec<&'a PsqlTable> = node_indexes
+          .into_iter()
+          .map(|node_index| graph[node_index])
+          .collect();
+
+        tables.sort_by_key(|table| position_by_node_index[&node_index_by_table_id[&table.id]]);
+        fks.sort_by_key(|cycle_fk| (cycle_fk.table.id.to_string(), cycle_fk.fk.name.clone()));
+
+        return Some(TableCycle { tables, fks });
+      })
+      .collect();
+
+    cycles
+      .sort_by_key(|cycle| position_by_node_index[&node_index_by_table_id[&cycle.tables[0].id]]);
+
+    return cycles;
+  }
+}
+
+impl<'a> std::fmt::Display for LoadOrder<'a> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "Load order (parents first):")?;
+
+    for (index, table) in self.tables.iter().enumerate() {
+      writeln!(f, "  {}. {}", index + 1, table.id)?;
+    }
+
+    writeln!(f)?;
+    writeln!(f, "Truncate order (children first):")?;
+
+    for (index, table) in self.truncate_order().iter().enumerate() {
+      writeln!(f, "  {}. {}", index + 1, table.id)?;
+    }
+
+    writeln!(f)?;
+
+    if self.cycles.is_empty() {
+      writeln!(f, "Cycles: none")?;
+
+      return Ok(());
+    }
+
+    writeln!(f, "Cycles:")?;
+
+    for cycle in self.cycles.iter() {
+      let table_ids: Vec<String> = cycle
+        .tables
+        .iter()
+        .map(|table| table.id.to_string())
+        .collect();
+
+      writeln!(f, "  {}", table_ids.join(", "))?;
+
+      for cycle_fk in cycle.fks.iter() {
+        writeln!(
+          f,
+          "    {} {}.{} -> {}.{}{}",
+          cycle_fk.fk.name,
+          cycle_fk.table.id,
+          cycle_fk.fk.column.name,
+          cycle_fk.fk.foreign_table_schema,
+          cycle_fk.fk.foreign_table_name,
+          if cycle_fk.is_loaded_before_parent {
+            " (loaded before its parent)"
+          } else {
+            ""
+          }
+        )?;
+      }
+    }
+
+    return Ok(());
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  mod to_string {
+    use super::*;
+
+    #[test]
+    fn it_should_order_tables_and_report_cycles() {
+      let psql_table_by_id: HashMap<PsqlTableIdentity, PsqlTable> = vec![
+        PsqlTable::fixture("users")
+          .references("team_id", "teams")
+          .build(),
+        PsqlTable::fixture("teams")
+          .references("owner_id", "users")
+          .build(),
+        PsqlTable::fixture("orders")
+          .references("user_id", "users")
+          .references("parent_id", "orders")
+          .build(),
+        PsqlTable::fixture("countries").build(),
+      ]
+      .into_iter()
+      .map(|table| (table.id.clone(), table))
+      .collect();
+
+      assert_eq!(
+        LoadOrder::new(&psql_table_by_id).to_string(),
+        indoc::indoc! {"
+          Load order (parents first):
+            1. public.countries
+            2. public.teams
+            3. public.users
+            4. public.orders
+
+          Truncate order (children first):
+            1. public.orders
+            2. public.users
+            3. public.teams
+            4. public.countries
+
+          Cycles:
+            public.teams, public.users
+              teams_owner_id_foreign public.teams.owner_id -> public.users (loaded before its parent)
+              users_team_id_foreign public.users.team_id -> public.teams
+            public.orders
+              orders_parent_id_foreign public.orders.parent_id -> public.orders (loaded before its parent)
+        "}
+      );
+    }
+  }
+}
@@ -1,6 +1,22 @@
 pub mod connection;
+pub mod connection_pool;
 pub mod db_metadata;
+pub mod db_metadata_cache;
 pub mod dto;
+pub mod graph_html;
+pub mod graph_template;
+pub mod insert_script;
+pub mod integrity_check;
+pub mod load_order;
 pub mod relation_fetcher;
 pub mod relation_insert;
+pub mod relation_path;
+pub mod root_values;
+pub mod schema_diff;
+pub mod seed;
+pub mod statement_apply;
+pub mod statement_executor;
+pub mod table_ddl;
+pub mod table_description;
 pub mod table_metadata;
+pub mod table_metadata_snapshot;
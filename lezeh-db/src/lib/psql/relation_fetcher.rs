@@ -1,24 +1,212 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 use anyhow::anyhow;
+use futures::stream::{self, StreamExt, TryStreamExt};
 use petgraph::graph::Graph as BaseGraph;
 use petgraph::graph::NodeIndex;
 use petgraph::Directed as DirectedGraph;
 
 use crate::psql::dto::*;
-use crate::psql::table_metadata::TableMetadata;
+use crate::psql::table_metadata::{
+  AsyncTableMetadata, BlockingTableMetadata, RowLimit, TableMetadata,
+};
 use lezeh_common::types::ResultAnyError;
 
 pub type RowGraph = BaseGraph<Rc<PsqlTableRow>, i32, DirectedGraph>;
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChildRowOrder {
+  pub column_name: String,
+  pub descending: bool,
+}
+
+/// Limits the children fetched per foreign key so that huge relations
+/// still produce a small but representative graph.
+#[derive(Debug, Clone, Default)]
+pub struct ChildRowLimitConfig {
+  /// Applied to every relation that has no specific limit
+  pub max_children: Option<usize>,
+  pub max_children_by_table_id: HashMap<PsqlTableIdentity, usize>,
+
+  /// Keyed by child table id and its foreign key column name
+  pub max_children_by_fk: HashMap<(PsqlTableIdentity, String), usize>,
+
+  /// Applied to every child table that has no specific order and contains the column,
+  /// children are ordered by their primary column otherwise.
+  pub default_order: Option<ChildRowOrder>,
+  pub order_by_table_id: HashMap<PsqlTableIdentity, ChildRowOrder>,
+}
+
+impl ChildRowLimitConfig {
+  /// Columns are given by the user, check them before they end up in a query
+  fn validate(
+    &self,
+    psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+  ) -> ResultAnyError<()> {
+    let fk_columns = self
+      .max_children_by_fk
+      .keys()
+      .map(|(table_id, column_name)| (table_id, column_name));
+    let order_columns = self
+      .order_by_table_id
+      .iter()
+      .map(|(table_id, order)| (table_id, &order.column_name));
+
+    for (table_id, column_name) in fk_columns.chain(order_columns) {
+      let table = psql_table_by_id
+        .get(table_id)
+        .ok_or_else(|| anyhow!("Table {} not found", table_id))?;
+
+      if !table.has_column(column_name) {
+        return Err(anyhow!(
+          "Column {} not found in table {}",
+          column_name,
+          table_id
+        ));
+      }
+    }
+
+    return Ok(());
+  }
+
+  fn row_limit(&self, child_table: &PsqlTable, fk_column_name: &str) -> Option<RowLimit> {
+    let max_rows: usize = *self
+      .max_children_by_fk
+      .get(&(child_table.id.clone(), fk_column_name.to_owned()))
+      .or_else(|| self.max_children_by_table_id.get(&child_table.id))
+      .or(self.max_children.as_ref())?;
+
+    let order: Option<&ChildRowOrder> = self.order_by_table_id.get(&child_table.id).or_else(|| {
+      return self.default_order.as_ref().filter(|order| {
+        return child_table
+          .columns
+          .iter()
+          .any(|column| column.name == order.column_name);
+      });
+    });
+
+    return Some(match order {
+      Some(order) => RowLimit {
+        max_rows,
+        order_column_name: order.column_name.clone(),
+        descending: order.descending,
+      },
+      None => RowLimit {
+        max_rows,
+        order_column_name: child_table.primary_column.name.clone(),
+        descending: false,
+      },
+    });
+  }
+}
+
+/// Children that are not fetched due to [ChildRowLimitConfig]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TruncatedRelation {
+  pub table_id: PsqlTableIdentity,
+  pub fk_column_name: String,
+  pub truncated_row_count: usize,
+}
+
+/// Parents that are referenced by fetched rows but are not found, either they're
+/// excluded by the row filters or the foreign key is not enforced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingParentRelation {
+  pub table_id: PsqlTableIdentity,
+  pub fk_column_name: String,
+  pub foreign_table_id: PsqlTableIdentity,
+
+  /// Fetched rows whose parent is not found
+  pub row_count: usize,
+}
+
+/// Which relations of a row are fetched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RelationDirection {
+  /// Rows that are referenced by the row
+  Parents,
+
+  /// Rows that reference the row
+  Children,
+}
+
+/// Rows of 1 foreign key relation of a row
+struct FetchedRelation {
+  row: Rc<PsqlTableRow>,
+  direction: RelationDirection,
+  related_rows: Vec<Rc<PsqlTableRow>>,
+  truncated_relation: Option<TruncatedRelation>,
+  missing_parent_relation: Option<MissingParentRelation>,
+}
+
 pub struct RelationFetcher {
-  table_metadata: Box<dyn TableMetadata>,
+  table_metadata: Box<dyn AsyncTableMetadata>,
+  child_row_limit_config: ChildRowLimitConfig,
+
+  /// Max foreign key relations that are fetched at the same time
+  parallelism: usize,
+  truncated_relation_by_key: HashMap<(PsqlTableIdentity, String), TruncatedRelation>,
+  missing_parent_relation_by_key: HashMap<(PsqlTableIdentity, String), MissingParentRelation>,
 }
 
 impl RelationFetcher {
-  pub fn new(table_metadata: Box<dyn TableMetadata>) -> RelationFetcher {
-    return RelationFetcher { table_metadata };
+  /// Relations are fetched 1 by 1
+  pub fn new(
+    table_metadata: Box<dyn TableMetadata>,
+    child_row_limit_config: ChildRowLimitConfig,
+  ) -> RelationFetcher {
+    return RelationFetcher::with_parallelism(
+      Box::new(BlockingTableMetadata::new(table_metadata)),
+      child_row_limit_config,
+      1,
+    );
+  }
+
+  pub fn with_parallelism(
+    table_metadata: Box<dyn AsyncTableMetadata>,
+    child_row_limit_config: ChildRowLimitConfig,
+    parallelism: usize,
+  ) -> RelationFetcher {
+    return RelationFetcher {
+      table_metadata,
+      child_row_limit_config,
+      parallelism: parallelism.max(1),
+      truncated_relation_by_key: Default::default(),
+      missing_parent_relation_by_key: Default::default(),
+    };
+  }
+
+  pub fn truncated_relations(&self) -> Vec<TruncatedRelation> {
+    let mut truncated_relations: Vec<TruncatedRelation> =
+      self.truncated_relation_by_key.values().cloned().collect();
+
+    truncated_relations.sort_by_key(|relation| {
+      return (
+        relation.table_id.to_string(),
+        relation.fk_column_name.clone(),
+      );
+    });
+
+    return truncated_relations;
+  }
+
+  /// The generated inserts of these relations violate their foreign key
+  pub fn missing_parent_relations(&self) -> Vec<MissingParentRelation> {
+    let mut missing_parent_relations: Vec<MissingParentRelation> = self
+      .missing_parent_relation_by_key
+      .values()
+      .cloned()
+      .collect();
+
+    missing_parent_relations.sort_by_key(|relation| {
+      return (
+        relation.table_id.to_string(),
+        relation.fk_column_name.clone(),
+      );
+    });
+
+    return missing_parent_relations;
   }
 }
 
@@ -28,214 +216,634 @@ pub struct FetchRowsAsRoseTreeInput<'a> {
   pub column_value: &'a str,
 }
 
+/// Graph that is being filled, rows are added once even if they're reached through many relations
+#[derive(Default)]
+struct RowGraphState {
+  row_graph: RowGraph,
+  node_index_by_row: HashMap<Rc<PsqlTableRow>, NodeIndex>,
+  fetched_relations: HashSet<(Rc<PsqlTableRow>, RelationDirection)>,
+}
+
+impl RowGraphState {
+  fn node_index(&mut self, row: &Rc<PsqlTableRow>) -> NodeIndex {
+    let row_graph = &mut self.row_graph;
+
+    return *self
+      .node_index_by_row
+      .entry(row.clone())
+      .or_insert_with(|| row_graph.add_node(row.clone()));
+  }
+}
+
 impl RelationFetcher {
-  pub fn fetch_as_graphs<'a>(
+  pub async fn fetch_as_graphs<'a>(
     &mut self,
-    input: FetchRowsAsRoseTreeInput,
+    input: FetchRowsAsRoseTreeInput<'_>,
     psql_table_by_id: &'a HashMap<PsqlTableIdentity, PsqlTable>,
   ) -> ResultAnyError<(RowGraph, NodeIndex)> {
-    let psql_table = psql_table_by_id.get(&input.table_id);
+    let (row_graph, root_node_indexes) = self
+      .fetch_roots_as_graph(
+        input.table_id,
+        input.column_name,
+        &[input.column_value.to_owned()],
+        psql_table_by_id,
+        |_, _| {},
+      )
+      .await?;
+
+    return Ok((row_graph, root_node_indexes[0]));
+  }
+
+  /// Fetch every root row with its relations into 1 graph, rows that are related
+  /// to many roots are added once. `on_root_fetched` is called after every root
+  /// with the number of fetched roots and the root value.
+  pub async fn fetch_roots_as_graph(
+    &mut self,
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+    column_values: &[String],
+    psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+    mut on_root_fetched: impl FnMut(usize, &str),
+  ) -> ResultAnyError<(RowGraph, Vec<NodeIndex>)> {
+    let psql_table = psql_table_by_id.get(table_id);
 
     if psql_table.is_none() {
-      return Err(anyhow!("Table {} not found", input.table_id));
+      return Err(anyhow!("Table {} not found", table_id));
     }
 
     let psql_table: &PsqlTable = psql_table.unwrap();
 
-    let row: Rc<PsqlTableRow> = Rc::new(self.table_metadata.get_one_row(
-      psql_table,
-      input.column_name,
-      input.column_value,
-    )?);
+    if !psql_table.has_column(column_name) {
+      return Err(anyhow!(
+        "Column {} not found in table {}",
+        column_name,
+        table_id
+      ));
+    }
+
+    self.child_row_limit_config.validate(psql_table_by_id)?;
 
-    let mut row_graph: RowGraph = RowGraph::new();
-    let node_index = row_graph.add_node(row.clone());
-    let mut node_index_by_row: HashMap<Rc<PsqlTableRow>, NodeIndex> = Default::default();
+    let mut state = RowGraphState::default();
+    let mut root_node_indexes: Vec<NodeIndex> = vec![];
 
-    node_index_by_row.insert(row.clone(), node_index);
+    for (index, column_value) in column_values.iter().enumerate() {
+      let row: Rc<PsqlTableRow> = Rc::new(
+        self
+          .table_metadata
+          .get_one_row(psql_table, column_name, column_value)
+          .await?,
+      );
 
-    // Fill parents but we do not need to fill our siblings bcs it's not required
-    self.fill_referencing_rows(
-      &mut row_graph,
-      row.clone(),
-      &psql_table_by_id,
-      &mut node_index_by_row,
-    )?;
+      let node_index = state.node_index(&row);
+
+      // Parents are only expanded to their parents, we do not need our siblings.
+      // Children are expanded to their children and their parents.
+      self
+        .fill_relations(
+          &mut state,
+          vec![
+            (row.clone(), RelationDirection::Parents),
+            (row, RelationDirection::Children),
+          ],
+          psql_table_by_id,
+        )
+        .await?;
 
-    // Fill children and its parents
-    self.fill_referenced_rows(
-      &mut row_graph,
-      row.clone(),
-      &psql_table_by_id,
-      &mut node_index_by_row,
-    )?;
+      if !root_node_indexes.contains(&node_index) {
+        root_node_indexes.push(node_index);
+      }
 
-    return Ok((row_graph, node_index));
+      on_root_fetched(index + 1, column_value);
+    }
+
+    return Ok((state.row_graph, root_node_indexes));
   }
 
-  fn fill_referencing_rows(
+  /// Fetch the relations level by level, relations of the same level are fetched concurrently
+  async fn fill_relations(
     &mut self,
-    row_graph: &mut RowGraph,
-    current_row: Rc<PsqlTableRow>,
+    state: &mut RowGraphState,
+    mut pending_relations: Vec<(Rc<PsqlTableRow>, RelationDirection)>,
     psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
-    node_index_by_row: &mut HashMap<Rc<PsqlTableRow>, NodeIndex>,
   ) -> ResultAnyError<()> {
-    // This method should be called from lower level, so we just need to go to upper level
-    for (_key, psql_foreign_key) in current_row.table.referencing_fk_by_constraint_name.clone() {
-      let foreign_table_id = PsqlTableIdentity::new(
-        psql_foreign_key.foreign_table_schema.clone(),
-        psql_foreign_key.foreign_table_name.clone(),
-      );
-
-      let foreign_table = psql_table_by_id[&foreign_table_id].clone();
-
-      let parents: Vec<Rc<PsqlTableRow>> = self
-        .fetch_rows(
-          foreign_table.clone(),
-          &foreign_table.primary_column.name,
-          &current_row.get_id(&psql_foreign_key.column),
-        )?
-        .into_iter()
-        .map(Rc::new)
+    while !pending_relations.is_empty() {
+      let queries: Vec<(Rc<PsqlTableRow>, RelationDirection, PsqlForeignKey)> = pending_relations
+        .drain(..)
+        .filter(|pending_relation| state.fetched_relations.insert(pending_relation.clone()))
+        .flat_map(|(row, direction)| {
+          let fk_by_constraint_name = match direction {
+            RelationDirection::Parents => &row.table.referencing_fk_by_constraint_name,
+            RelationDirection::Children => &row.table.referenced_fk_by_constraint_name,
+          };
+
+          return fk_by_constraint_name
+            .values()
+            .cloned()
+            .map(|fk| (row.clone(), direction, fk))
+            .collect::<Vec<_>>();
+        })
         .collect();
 
-      let current_row_node_index = node_index_by_row.get(&current_row).unwrap().clone();
+      // Keeps the order of the queries so the graph does not depend on query timing
+      let fetched_relations: Vec<FetchedRelation> = stream::iter(queries)
+        .map(|(row, direction, fk)| self.fetch_relation(row, direction, fk, psql_table_by_id))
+        .buffered(self.parallelism)
+        .try_collect()
+        .await?;
+
+      for fetched_relation in fetched_relations {
+        let row_node_index = state.node_index(&fetched_relation.row);
+
+        for related_row in fetched_relation.related_rows {
+          let related_node_index = state.node_index(&related_row);
+
+          match fetched_relation.direction {
+            RelationDirection::Parents => {
+              state
+                .row_graph
+                .update_edge(row_node_index, related_node_index, -1);
+
+              pending_relations.push((related_row, RelationDirection::Parents));
+            }
+            RelationDirection::Children => {
+              state
+                .row_graph
+                .update_edge(related_node_index, row_node_index, -1);
+
+              pending_relations.push((related_row.clone(), RelationDirection::Parents));
+              pending_relations.push((related_row, RelationDirection::Children));
+            }
+          }
+        }
+
+        if let Some(truncated_relation) = fetched_relation.truncated_relation {
+          self
+            .truncated_relation_by_key
+            .entry((
+              truncated_relation.table_id.clone(),
+              truncated_relation.fk_column_name.clone(),
+            ))
+            .or_insert_with(|| TruncatedRelation {
+              truncated_row_count: 0,
+              ..truncated_relation.clone()
+            })
+            .truncated_row_count += truncated_relation.truncated_row_count;
+        }
+
+        if let Some(missing_parent_relation) = fetched_relation.missing_parent_relation {
+          self
+            .missing_parent_relation_by_key
+            .entry((
+              missing_parent_relation.table_id.clone(),
+              missing_parent_relation.fk_column_name.clone(),
+            ))
+            .or_insert_with(|| MissingParentRelation {
+              row_count: 0,
+              ..missing_parent_relation.clone()
+            })
+            .row_count += missing_parent_relation.row_count;
+        }
+      }
+    }
 
-      for parent_row in parents.iter() {
-        let parent_node_index = node_index_by_row
-          .entry(parent_row.clone())
-          .or_insert_with(|| row_graph.add_node(parent_row.clone()));
+    return Ok(());
+  }
 
-        row_graph.update_edge(current_row_node_index, *parent_node_index, -1);
+  async fn fetch_relation(
+    &self,
+    row: Rc<PsqlTableRow>,
+    direction: RelationDirection,
+    fk: PsqlForeignKey,
+    psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+  ) -> ResultAnyError<FetchedRelation> {
+    let foreign_table_id = PsqlTableIdentity::new(
+      fk.foreign_table_schema.clone(),
+      fk.foreign_table_name.clone(),
+    );
+
+    let foreign_table = &psql_table_by_id[&foreign_table_id];
+
+    let mut missing_parent_relation: Option<MissingParentRelation> = None;
+
+    let (related_rows, truncated_relation) = match direction {
+      RelationDirection::Parents => {
+        let parent_id = row.get_id(&fk.column)?;
+        let parents = self
+          .table_metadata
+          .get_rows(
+            foreign_table,
+            &foreign_table.primary_column.name,
+            parent_id,
+            None,
+          )
+          .await?;
+
+        if parents.is_empty() && !parent_id.is_null() {
+          missing_parent_relation = Some(MissingParentRelation {
+            table_id: row.table.id.clone(),
+            fk_column_name: fk.column.name.clone(),
+            foreign_table_id: foreign_table_id.clone(),
+            row_count: 1,
+          });
+        }
+
+        (parents, None)
+      }
+      RelationDirection::Children => {
+        self
+          .fetch_children(
+            foreign_table,
+            &fk.column.name,
+            row.get_id(&row.table.primary_column)?,
+          )
+          .await?
+      }
+    };
+
+    return Ok(FetchedRelation {
+      row,
+      direction,
+      related_rows: related_rows.into_iter().map(Rc::new).collect(),
+      truncated_relation,
+      missing_parent_relation,
+    });
+  }
 
-        self.fill_referencing_rows(
-          row_graph,
-          parent_row.clone(),
-          psql_table_by_id,
-          node_index_by_row,
-        )?;
+  async fn fetch_children(
+    &self,
+    table: &PsqlTable,
+    fk_column_name: &str,
+    id: &FromSqlSink,
+  ) -> ResultAnyError<(Vec<PsqlTableRow>, Option<TruncatedRelation>)> {
+    let limit = match self.child_row_limit_config.row_limit(table, fk_column_name) {
+      None => {
+        let rows = self
+          .table_metadata
+          .get_rows(table, fk_column_name, id, None)
+          .await?;
+
+        return Ok((rows, None));
       }
+      Some(limit) => limit,
+    };
+
+    let rows = self
+      .table_metadata
+      .get_rows(table, fk_column_name, id, Some(&limit))
+      .await?;
+
+    if rows.len() < limit.max_rows {
+      return Ok((rows, None));
     }
 
-    return Ok(());
+    let total_row_count = self
+      .table_metadata
+      .count_rows(table, fk_column_name, id)
+      .await?;
+
+    if total_row_count <= rows.len() {
+      return Ok((rows, None));
+    }
+
+    let truncated_relation = TruncatedRelation {
+      table_id: table.id.clone(),
+      fk_column_name: fk_column_name.to_owned(),
+      truncated_row_count: total_row_count - rows.len(),
+    };
+
+    return Ok((rows, Some(truncated_relation)));
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::table_metadata::MockTableMetadata;
+  use postgres_types::Type as PsqlType;
+
+  fn create_table_by_id() -> HashMap<PsqlTableIdentity, PsqlTable> {
+    let fk = PsqlForeignKey::new(
+      "order_items_order_id_foreign",
+      PsqlTableColumn::new("order_id", "integer"),
+      "public",
+      "orders",
+    );
+
+    let orders = PsqlTable::fixture("orders")
+      .referenced_fks(vec![PsqlForeignKey {
+        foreign_table_name: "order_items".to_owned(),
+        ..fk.clone()
+      }])
+      .build();
+
+    let order_items = PsqlTable::fixture("order_items")
+      .referencing_fks(vec![fk])
+      .build();
+
+    return vec![
+      (orders.id.clone(), orders),
+      (order_items.id.clone(), order_items),
+    ]
+    .into_iter()
+    .collect();
   }
 
-  /// Fetch child rows, it will also populate other parents' (siblings of current node)
-  /// of the current child rows
-  fn fill_referenced_rows(
-    &mut self,
-    row_graph: &mut RowGraph,
-    current_row: Rc<PsqlTableRow>,
-    psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
-    node_index_by_row: &mut HashMap<Rc<PsqlTableRow>, NodeIndex>,
-  ) -> ResultAnyError<()> {
-    for (_key, psql_foreign_key) in current_row.table.referenced_fk_by_constraint_name.clone() {
-      let foreign_table_id = PsqlTableIdentity::new(
-        psql_foreign_key.foreign_table_schema.clone(),
-        psql_foreign_key.foreign_table_name.clone(),
+  fn create_row(table: &PsqlTable, values: Vec<(&str, i32)>) -> PsqlTableRow {
+    return PsqlTableRow::new(
+      table.clone(),
+      values
+        .into_iter()
+        .map(|(column_name, value)| {
+          return (
+            column_name.to_owned(),
+            FromSqlSink::from_value(&value, PsqlType::INT4).unwrap(),
+          );
+        })
+        .collect(),
+    )
+    .unwrap();
+  }
+
+  mod fetch_as_graphs {
+    use super::*;
+
+    #[test]
+    fn it_should_limit_children_and_summarize_truncated_rows() -> ResultAnyError<()> {
+      let table_by_id = create_table_by_id();
+      let orders = table_by_id[&PsqlTableIdentity::new("public", "orders")].clone();
+      let order_items = table_by_id[&PsqlTableIdentity::new("public", "order_items")].clone();
+      let order_items_id = order_items.id.clone();
+      let mut table_metadata = MockTableMetadata::new();
+
+      let order = orders.clone();
+      table_metadata
+        .expect_get_one_row()
+        .returning(move |_, _, _| Ok(create_row(&order, vec![("id", 1)])));
+
+      let order = orders.clone();
+      table_metadata
+        .expect_get_rows()
+        .withf(|table, _, _, limit| table.id.name == "orders" && limit.is_none())
+        .returning(move |_, _, _, _| Ok(vec![create_row(&order, vec![("id", 1)])]));
+
+      table_metadata
+        .expect_get_rows()
+        .withf(|table, column_name, _, limit| {
+          return table.id.name == "order_items"
+            && column_name == "order_id"
+            && *limit
+              == Some(&RowLimit {
+                max_rows: 2,
+                order_column_name: "id".to_owned(),
+                descending: true,
+              });
+        })
+        .times(1)
+        .returning(move |_, _, _, _| {
+          return Ok(vec![
+            create_row(&order_items, vec![("id", 12), ("order_id", 1)]),
+            create_row(&order_items, vec![("id", 11), ("order_id", 1)]),
+          ]);
+        });
+
+      table_metadata
+        .expect_count_rows()
+        .times(1)
+        .returning(|_, _, _| Ok(5));
+
+      let mut relation_fetcher = RelationFetcher::new(
+        Box::new(table_metadata),
+        ChildRowLimitConfig {
+          max_children: Some(2),
+          order_by_table_id: vec![(
+            order_items_id,
+            ChildRowOrder {
+              column_name: "id".to_owned(),
+              descending: true,
+            },
+          )]
+          .into_iter()
+          .collect(),
+          ..Default::default()
+        },
       );
 
-      let foreign_table = psql_table_by_id[&foreign_table_id].clone();
+      let (graph, _) = futures::executor::block_on(relation_fetcher.fetch_as_graphs(
+        FetchRowsAsRoseTreeInput {
+          table_id: &orders.id,
+          column_name: "id",
+          column_value: "1",
+        },
+        &table_by_id,
+      ))?;
+
+      assert_eq!(graph.node_count(), 3);
+      assert_eq!(
+        relation_fetcher.truncated_relations(),
+        vec![TruncatedRelation {
+          table_id: PsqlTableIdentity::new("public", "order_items"),
+          fk_column_name: "order_id".to_owned(),
+          truncated_row_count: 3,
+        }]
+      );
 
-      let children_per_fk: Vec<Rc<PsqlTableRow>> = self
-        .fetch_rows(
-          foreign_table.clone(),
-          &psql_foreign_key.column.name,
-          &current_row.get_id(&current_row.table.primary_column),
-        )?
-        .into_iter()
-        .map(Rc::new)
-        .collect();
+      return Ok(());
+    }
+  }
+
+  mod missing_parent_relations {
+    use super::*;
+
+    #[test]
+    fn it_should_report_non_null_references_without_parent() -> ResultAnyError<()> {
+      let table_by_id = create_table_by_id();
+      let order_items = table_by_id[&PsqlTableIdentity::new("public", "order_items")].clone();
+      let mut table_metadata = MockTableMetadata::new();
+
+      let order_item = order_items.clone();
+      table_metadata
+        .expect_get_one_row()
+        .returning(move |_, _, _| {
+          return Ok(create_row(&order_item, vec![("id", 11), ("order_id", 1)]));
+        });
+
+      // The parent order is filtered out
+      table_metadata
+        .expect_get_rows()
+        .returning(|_, _, _, _| Ok(vec![]));
+
+      let mut relation_fetcher = RelationFetcher::new(Box::new(table_metadata), Default::default());
+
+      futures::executor::block_on(relation_fetcher.fetch_roots_as_graph(
+        &order_items.id,
+        "id",
+        &["11".to_owned()],
+        &table_by_id,
+        |_, _| {},
+      ))?;
+
+      assert_eq!(
+        relation_fetcher.missing_parent_relations(),
+        vec![MissingParentRelation {
+          table_id: order_items.id.clone(),
+          fk_column_name: "order_id".to_owned(),
+          foreign_table_id: PsqlTableIdentity::new("public", "orders"),
+          row_count: 1,
+        }]
+      );
 
-      let current_row_node_index = node_index_by_row.get(&current_row).unwrap().clone();
+      return Ok(());
+    }
+  }
 
-      for child_row in children_per_fk.iter() {
-        let child_node_index = node_index_by_row
-          .entry(child_row.clone())
-          .or_insert_with(|| row_graph.add_node(child_row.clone()));
+  mod fetch_roots_as_graph {
+    use super::*;
+    use std::cell::Cell;
 
-        row_graph.update_edge(*child_node_index, current_row_node_index, -1);
+    /// Every query yields once so the other queries of the same level can start
+    struct ConcurrentTableMetadata {
+      orders: PsqlTable,
+      in_flight_count: Cell<usize>,
+      max_in_flight_count: Rc<Cell<usize>>,
+    }
 
-        self.fill_referencing_rows(
-          row_graph,
-          child_row.clone(),
-          psql_table_by_id,
-          node_index_by_row,
-        )?;
+    #[async_trait::async_trait(?Send)]
+    impl AsyncTableMetadata for ConcurrentTableMetadata {
+      async fn get_rows(
+        &self,
+        table: &PsqlTable,
+        _column_name: &str,
+        _id: &FromSqlSink,
+        _limit: Option<&RowLimit>,
+      ) -> ResultAnyError<Vec<PsqlTableRow>> {
+        self.in_flight_count.set(self.in_flight_count.get() + 1);
+        self.max_in_flight_count.set(
+          self
+            .max_in_flight_count
+            .get()
+            .max(self.in_flight_count.get()),
+        );
+
+        let _ = tokio::task::yield_now().await;
+
+        self.in_flight_count.set(self.in_flight_count.get() - 1);
+
+        return Ok(vec![create_row(table, vec![("id", 1)])]);
+      }
 
-        self.fill_referenced_rows(
-          row_graph,
-          child_row.clone(),
-          psql_table_by_id,
-          node_index_by_row,
-        )?;
+      async fn count_rows(
+        &self,
+        _table: &PsqlTable,
+        _column_name: &str,
+        _id: &FromSqlSink,
+      ) -> ResultAnyError<usize> {
+        return Ok(0);
+      }
+
+      async fn get_one_row(
+        &self,
+        _table: &PsqlTable,
+        _column_name: &str,
+        _id: &str,
+      ) -> ResultAnyError<PsqlTableRow> {
+        return Ok(create_row(
+          &self.orders,
+          vec![("id", 1), ("user_id", 1), ("shop_id", 1), ("coupon_id", 1)],
+        ));
       }
     }
 
-    return Ok(());
-  }
+    #[test]
+    fn it_should_fetch_relations_concurrently_up_to_the_parallelism() -> ResultAnyError<()> {
+      let orders = PsqlTable::fixture("orders")
+        .references("user_id", "users")
+        .references("shop_id", "shops")
+        .references("coupon_id", "coupons")
+        .build();
+      let table_by_id: HashMap<PsqlTableIdentity, PsqlTable> = vec![
+        orders.clone(),
+        PsqlTable::fixture("users").build(),
+        PsqlTable::fixture("shops").build(),
+        PsqlTable::fixture("coupons").build(),
+      ]
+      .into_iter()
+      .map(|table| (table.id.clone(), table))
+      .collect();
+
+      let max_in_flight_count: Rc<Cell<usize>> = Default::default();
+      let mut relation_fetcher = RelationFetcher::with_parallelism(
+        Box::new(ConcurrentTableMetadata {
+          orders: orders.clone(),
+          in_flight_count: Cell::new(0),
+          max_in_flight_count: max_in_flight_count.clone(),
+        }),
+        Default::default(),
+        2,
+      );
 
-  fn fetch_rows<'a>(
-    &mut self,
-    table: PsqlTable,
-    column_name: &str,
-    id: &PsqlParamValue,
-  ) -> ResultAnyError<Vec<PsqlTableRow>> {
-    let rows = self
-      .table_metadata
-      .get_rows(table.clone(), column_name, id)?;
+      let mut fetched_roots: Vec<String> = vec![];
+      let (graph, root_node_indexes) =
+        futures::executor::block_on(relation_fetcher.fetch_roots_as_graph(
+          &orders.id,
+          "id",
+          &["1".to_owned(), "1".to_owned()],
+          &table_by_id,
+          |fetched_count, value| fetched_roots.push(format!("{} {}", fetched_count, value)),
+        ))?;
+
+      assert_eq!(graph.node_count(), 4);
+      assert_eq!(graph.edge_count(), 3);
+      assert_eq!(root_node_indexes.len(), 1);
+      assert_eq!(fetched_roots, vec!["1 1", "2 1"]);
+
+      // 3 parents of the same level, the 3rd one waits for a free slot
+      assert_eq!(max_in_flight_count.get(), 2);
+
+      return Ok(());
+    }
 
-    return Ok(rows);
+    #[test]
+    fn it_should_reject_unknown_columns_before_querying() {
+      let table_by_id = create_table_by_id();
+      let orders_id = PsqlTableIdentity::new("public", "orders");
+      let fetch = |child_row_limit_config: ChildRowLimitConfig, column_name: &str| {
+        // No expectation is set, any query would panic
+        let mut relation_fetcher =
+          RelationFetcher::new(Box::new(MockTableMetadata::new()), child_row_limit_config);
+
+        return futures::executor::block_on(relation_fetcher.fetch_roots_as_graph(
+          &orders_id,
+          column_name,
+          &["1".to_owned()],
+          &table_by_id,
+          |_, _| {},
+        ))
+        .err()
+        .map(|err| err.to_string());
+      };
+
+      assert_eq!(
+        fetch(Default::default(), "id; DROP TABLE orders"),
+        Some("Column id; DROP TABLE orders not found in table public.orders".to_owned())
+      );
+      assert_eq!(
+        fetch(
+          ChildRowLimitConfig {
+            order_by_table_id: vec![(
+              PsqlTableIdentity::new("public", "order_items"),
+              ChildRowOrder {
+                column_name: "created_at".to_owned(),
+                descending: true,
+              },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+          },
+          "id"
+        ),
+        Some("Column created_at not found in table public.order_items".to_owned())
+      );
+    }
   }
 }
-
-// #[cfg(test)]
-// mod test {
-//   use super::*;
-//   mod fetch_referenced_rows {
-//     use super::*;
-//     use crate::psql::table_metadata::MockTableMetadata;
-//
-//     fn create_dummy_table() -> PsqlTable {
-//       return PsqlTable::new(
-//         "public",
-//         "orders",
-//         PsqlTableColumn::new("id", "integer"),
-//         Default::default(),
-//         Default::default(),
-//         Default::default(),
-//       );
-//     }
-//
-//     #[test]
-//     fn if_children_is_empty() -> ResultAnyError<()> {
-//       let mut current_row = PsqlTableRow {
-//         row_id_representation: "".to_string(),
-//         table: create_dummy_table(),
-//         row: Row::default(),
-//       };
-//
-//       // mock_table_metadata
-//       // .expect_get_psql_table_rows()
-//       // .times(1)
-//       // .return_once(|_, _, _| {
-//       // Ok(PsqlTableRow {
-//       // row_id_representation: "foo".into(),
-//       // table: create_dummy_table(),
-//       // row: Default::default(),
-//       // })
-//       // });
-//
-//       let mut relation_fetcher = RelationFetcher::new(Box::new(MockTableMetadata::default()));
-//
-//       // let fk_column = PsqlTableColumn::new("id", "integer");
-//
-//       let referenced_rows =
-//         relation_fetcher.fetch_referenced_rows(&current_row, &Default::default())?;
-//
-//       assert_eq!(referenced_rows, vec![]);
-//
-//       return Ok(());
-//     }
-//   }
-// }
@@ -1,17 +1,26 @@
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
+use anyhow::anyhow;
 use itertools::Itertools;
 
+use crate::config::DbDriver;
+use crate::mysql::dto as mysql_dto;
+use crate::psql::dto::escape_identifier;
 use crate::psql::dto::FromSqlSink;
 use crate::psql::dto::PsqlTable;
+use crate::psql::dto::PsqlTableColumn;
 use crate::psql::dto::PsqlTableIdentity;
 use crate::psql::dto::PsqlTableRow;
 use lezeh_common::types::ResultAnyError;
 
 pub struct TableInsertStatement<'a> {
+  driver: DbDriver,
   table: PsqlTable,
   columns: TableInsertRowColumns<'a>,
+
+  /// Needed to insert explicit values into `GENERATED ALWAYS AS IDENTITY` columns
+  overriding_system_value: bool,
   row_values: Vec<TableInsertRowValues>,
 }
 
@@ -25,14 +34,19 @@ impl<'a> std::fmt::Display for TableInsertStatement<'a> {
         ------------------------------------------------
         -- insert into table {}
         ------------------------------------------------
-        insert into {} ({}) VALUES
+        insert into {} ({}){} VALUES
           {};
         ---------------
 
       "},
       self.table.id,
-      self.table.id,
+      RelationInsert::table_identifier(self.driver, &self.table.id),
       self.columns,
+      if self.overriding_system_value {
+        " OVERRIDING SYSTEM VALUE"
+      } else {
+        ""
+      },
       self
         .row_values
         .iter()
@@ -44,6 +58,7 @@ impl<'a> std::fmt::Display for TableInsertStatement<'a> {
 }
 
 pub struct TableInsertRowColumns<'a> {
+  driver: DbDriver,
   column_names: Vec<&'a str>,
 }
 
@@ -52,9 +67,7 @@ impl<'a> std::fmt::Display for TableInsertRowColumns<'a> {
     let column_string: String = self
       .column_names
       .iter()
-      .map(|column_name| {
-        return format!("\"{}\"", column_name);
-      })
+      .map(|column_name| RelationInsert::quote_identifier(self.driver, column_name))
       .collect::<Vec<String>>()
       .join(", ");
 
@@ -72,17 +85,107 @@ impl std::fmt::Display for TableInsertRowValues {
   }
 }
 
+/// `COPY ... FROM stdin` block, the rows are tab separated values in the COPY text format
+pub struct TableCopyStatement<'a> {
+  table: PsqlTable,
+  columns: TableInsertRowColumns<'a>,
+  rows: Vec<String>,
+}
+
+impl<'a> std::fmt::Display for TableCopyStatement<'a> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(
+      f,
+      indoc::indoc! {"
+        ------------------------------------------------
+        -- copy into table {}
+        ------------------------------------------------
+        COPY {} ({}) FROM stdin;
+        {}
+        \\.
+        ---------------
+
+      "},
+      self.table.id,
+      self.table.id.quoted(),
+      self.columns,
+      self.rows.join("\n"),
+    );
+  }
+}
+
+/// Options applied when rendering insert statements
+#[derive(Default, Clone)]
+pub struct InsertStatementConfig {
+  pub driver: DbDriver,
+
+  /// Columns that will not be inserted
+  pub excluded_columns_by_table_id: HashMap<PsqlTableIdentity, HashSet<String>>,
+
+  /// Raw sql value that replaces the fetched column value, e.g. `1` or `now()`
+  pub column_value_by_table_id: HashMap<PsqlTableIdentity, HashMap<String, String>>,
+}
+
+impl InsertStatementConfig {
+  pub fn new(driver: DbDriver) -> InsertStatementConfig {
+    return InsertStatementConfig {
+      driver,
+      ..Default::default()
+    };
+  }
+}
+
+/// Renders the rows of 1 table into 1 statement
+type TableRowIntoStatement =
+  fn(&PsqlTable, &Vec<&Rc<PsqlTableRow>>, &InsertStatementConfig) -> ResultAnyError<String>;
+
 pub struct RelationInsert {}
 
 impl RelationInsert {
   pub fn into_insert_statements(
+    rows_by_level: HashMap<i32, HashSet<&Rc<PsqlTableRow>>>,
+    config: &InsertStatementConfig,
+  ) -> ResultAnyError<Vec<String>> {
+    return RelationInsert::into_statements(
+      rows_by_level,
+      config,
+      RelationInsert::table_row_into_insert_statement,
+    );
+  }
+
+  /// Same order as [RelationInsert::into_insert_statements] but every table is loaded
+  /// with `COPY ... FROM stdin`, it's faster for big graphs and meant to be run with `psql -f`.
+  pub fn into_copy_statements(
+    rows_by_level: HashMap<i32, HashSet<&Rc<PsqlTableRow>>>,
+    config: &InsertStatementConfig,
+  ) -> ResultAnyError<Vec<String>> {
+    if config.driver != DbDriver::Postgres {
+      return Err(anyhow!("COPY statements are only supported on postgres"));
+    }
+
+    return RelationInsert::into_statements(
+      rows_by_level,
+      config,
+      RelationInsert::table_row_into_copy_statement,
+    );
+  }
+
+  fn into_statements(
     mut rows_by_level: HashMap<i32, HashSet<&Rc<PsqlTableRow>>>,
+    config: &InsertStatementConfig,
+    table_row_into_statement: TableRowIntoStatement,
   ) -> ResultAnyError<Vec<String>> {
     let mut levels: Vec<i32> = rows_by_level.keys().cloned().collect();
     let mut insert_statement_map: HashMap<String, bool> = Default::default();
 
     levels.sort();
 
+    let touched_table_by_id: HashMap<PsqlTableIdentity, PsqlTable> = rows_by_level
+      .values()
+      .flatten()
+      .map(|row| (row.table.id.clone(), row.table.clone()))
+      .collect();
+
     let insert_statements: ResultAnyError<Vec<Vec<String>>> = levels
       .iter()
       .map(|level| {
@@ -99,15 +202,86 @@ impl RelationInsert {
           return !found;
         });
 
-        return RelationInsert::table_rows_into_insert_statement(rows);
+        return RelationInsert::table_rows_into_statements(rows, config, table_row_into_statement);
+      })
+      .collect();
+
+    let mut insert_statements: Vec<String> = insert_statements?.into_iter().flatten().collect();
+
+    if let Some(statement) =
+      RelationInsert::into_sequence_resync_statement(touched_table_by_id.values(), config)
+    {
+      insert_statements.push(statement);
+    }
+
+    return Ok(insert_statements);
+  }
+
+  /// Rows are inserted with explicit ids so the sequences of the target db lag behind,
+  /// move them forward so the next insert does not collide.
+  /// Mysql adjusts AUTO_INCREMENT by itself so it's only needed for postgres.
+  pub fn into_sequence_resync_statement<'a>(
+    tables: impl Iterator<Item = &'a PsqlTable>,
+    config: &InsertStatementConfig,
+  ) -> Option<String> {
+    if config.driver != DbDriver::Postgres {
+      return None;
+    }
+
+    let mut statements: Vec<String> = tables
+      .flat_map(|table| {
+        let excluded_columns = config.excluded_columns_by_table_id.get(&table.id);
+
+        return table
+          .columns
+          .iter()
+          .filter(|column| column.sequence_name.is_some())
+          .filter(move |column| {
+            return !excluded_columns
+              .map(|excluded_columns| excluded_columns.contains(&column.name))
+              .unwrap_or(false);
+          })
+          .map(move |column| {
+            let sequence = format!(
+              "pg_get_serial_sequence({}, {})",
+              FromSqlSink::escape_string(table.id.quoted()),
+              FromSqlSink::escape_string(&column.name)
+            );
+
+            return format!(
+              "SELECT setval({}, greatest((SELECT max({}) FROM {}), pg_sequence_last_value({}::regclass)));",
+              sequence,
+              RelationInsert::quote_identifier(config.driver, &column.name),
+              RelationInsert::table_identifier(config.driver, &table.id),
+              sequence
+            );
+          });
       })
       .collect();
 
-    return Ok(insert_statements?.into_iter().flatten().collect());
+    if statements.is_empty() {
+      return None;
+    }
+
+    statements.sort();
+
+    return Some(format!(
+      indoc::indoc! {"
+        ------------------------------------------------
+        -- resync sequences
+        ------------------------------------------------
+        {}
+        ---------------
+
+      "},
+      statements.join("\n")
+    ));
   }
 
-  pub fn table_rows_into_insert_statement(
+  fn table_rows_into_statements(
     rows: &HashSet<&Rc<PsqlTableRow>>,
+    config: &InsertStatementConfig,
+    table_row_into_statement: TableRowIntoStatement,
   ) -> ResultAnyError<Vec<String>> {
     // Rows of the same table can be scattered through vec of psql table rows,
     // remember Vec<PsqlTableRows> meaning Vec<Vec<Row>> due to PsqlTableRows
@@ -136,10 +310,7 @@ impl RelationInsert {
     return rows_by_table_id
       .iter()
       .map(|(table_id, rows)| {
-        return RelationInsert::table_row_into_insert_statement(
-          psql_table_by_id.get(table_id).unwrap(),
-          rows,
-        );
+        return table_row_into_statement(psql_table_by_id.get(table_id).unwrap(), rows, config);
       })
       .collect::<ResultAnyError<Vec<String>>>();
   }
@@ -147,25 +318,67 @@ impl RelationInsert {
   pub fn table_row_into_insert_statement(
     table: &PsqlTable,
     rows: &Vec<&Rc<PsqlTableRow>>,
+    config: &InsertStatementConfig,
   ) -> ResultAnyError<String> {
+    let driver = config.driver;
     let first_row: &PsqlTableRow = rows.get(0).unwrap();
+    let column_value_by_name = config.column_value_by_table_id.get(&table.id);
+
+    if let Some(column_value_by_name) = column_value_by_name {
+      let row_column_names: HashSet<&str> = first_row.get_column_names().into_iter().collect();
+
+      for column_name in column_value_by_name.keys() {
+        if !row_column_names.contains(column_name.as_str()) {
+          return Err(anyhow!(
+            "Could not set column {}, it's not found in table {}",
+            column_name,
+            table.id
+          ));
+        }
+      }
+    }
+
+    let column_by_name: HashMap<&str, &PsqlTableColumn> = table
+      .columns
+      .iter()
+      .map(|column| (column.name.as_str(), column))
+      .collect();
+
+    let column_names: Vec<&str> = RelationInsert::inserted_column_names(table, first_row, config);
+
+    let overriding_system_value = driver == DbDriver::Postgres
+      && column_names.iter().any(|column_name| {
+        return column_by_name
+          .get(column_name)
+          .map(|column| column.is_identity)
+          .unwrap_or(false);
+      });
+
     let table_insert_row_columns = TableInsertRowColumns {
-      column_names: first_row.get_column_names(),
+      driver,
+      column_names,
     };
 
     let row_values: Vec<TableInsertRowValues> = rows
       .iter()
       .map(|row| {
-        let column_value_map: HashMap<&str, FromSqlSink> = row.get_column_value_map();
+        let column_value_map: HashMap<&str, &FromSqlSink> = row.get_column_value_map();
 
         // Use ordering on table insert row columns to preserve ordering
         return table_insert_row_columns
           .column_names
           .iter()
           .map(|column_name| {
+            let column_value = column_value_by_name
+              .and_then(|column_value_by_name| column_value_by_name.get(*column_name));
+
+            if let Some(column_value) = column_value {
+              return Ok(column_value.clone());
+            }
+
             let from_sql_sink = column_value_map.get(column_name).unwrap();
 
-            return from_sql_sink.to_string_for_statement();
+            return RelationInsert::value_literal(driver, from_sql_sink);
           })
           .collect::<ResultAnyError<Vec<String>>>()
           .map(|values_in_string| {
@@ -177,11 +390,357 @@ impl RelationInsert {
       .collect::<ResultAnyError<Vec<TableInsertRowValues>>>()?;
 
     let table_insert_statement = TableInsertStatement {
+      driver,
       table: table.clone(),
       columns: table_insert_row_columns,
+      overriding_system_value,
       row_values,
     };
 
     return Ok(format!("{}", table_insert_statement));
   }
+
+  /// Identity columns do not need `OVERRIDING SYSTEM VALUE`, COPY always inserts the given values
+  pub fn table_row_into_copy_statement(
+    table: &PsqlTable,
+    rows: &Vec<&Rc<PsqlTableRow>>,
+    config: &InsertStatementConfig,
+  ) -> ResultAnyError<String> {
+    let first_row: &PsqlTableRow = rows.first().unwrap();
+
+    if let Some(column_value_by_name) = config.column_value_by_table_id.get(&table.id) {
+      if !column_value_by_name.is_empty() {
+        return Err(anyhow!(
+          "Could not set columns {} of table {}, COPY does not evaluate sql values",
+          column_value_by_name.keys().sorted().join(", "),
+          table.id
+        ));
+      }
+    }
+
+    let column_names: Vec<&str> = RelationInsert::inserted_column_names(table, first_row, config);
+
+    let copy_rows: Vec<String> = rows
+      .iter()
+      .map(|row| {
+        let column_value_map: HashMap<&str, &FromSqlSink> = row.get_column_value_map();
+
+        return column_names
+          .iter()
+          .map(|column_name| {
+            return column_value_map
+              .get(column_name)
+              .unwrap()
+              .to_text()
+              .map(|text| RelationInsert::copy_text_value(text.as_deref()));
+          })
+          .collect::<ResultAnyError<Vec<String>>>()
+          .map(|values| values.join("\t"));
+      })
+      .collect::<ResultAnyError<Vec<String>>>()?;
+
+    let table_copy_statement = TableCopyStatement {
+      table: table.clone(),
+      columns: TableInsertRowColumns {
+        driver: config.driver,
+        column_names,
+      },
+      rows: copy_rows,
+    };
+
+    return Ok(format!("{}", table_copy_statement));
+  }
+
+  /// Generated column values are computed by the db, inserting them will fail
+  fn inserted_column_names<'r>(
+    table: &PsqlTable,
+    row: &'r PsqlTableRow,
+    config: &InsertStatementConfig,
+  ) -> Vec<&'r str> {
+    let excluded_columns = config.excluded_columns_by_table_id.get(&table.id);
+
+    return row
+      .get_column_names()
+      .into_iter()
+      .filter(|column_name| {
+        return !table
+          .columns
+          .iter()
+          .any(|column| column.name == *column_name && column.is_generated);
+      })
+      .filter(|column_name| {
+        return !excluded_columns
+          .map(|excluded_columns| excluded_columns.contains(*column_name))
+          .unwrap_or(false);
+      })
+      .collect();
+  }
+
+  /// Value in the COPY text format, null is `\N` and backslashes
+  /// and the row/column delimiters are backslash escaped.
+  fn copy_text_value(text: Option<&str>) -> String {
+    let text = match text {
+      None => return "\\N".to_owned(),
+      Some(text) => text,
+    };
+
+    let mut value = String::with_capacity(text.len());
+
+    for c in text.chars() {
+      match c {
+        '\\' => value.push_str("\\\\"),
+        '\n' => value.push_str("\\n"),
+        '\r' => value.push_str("\\r"),
+        '\t' => value.push_str("\\t"),
+        _ => value.push(c),
+      }
+    }
+
+    return value;
+  }
+
+  pub(crate) fn quote_identifier(driver: DbDriver, identifier: &str) -> String {
+    return match driver {
+      DbDriver::Postgres => escape_identifier(identifier),
+      DbDriver::Mysql => mysql_dto::escape_identifier(identifier),
+    };
+  }
+
+  pub(crate) fn table_identifier(driver: DbDriver, table_id: &PsqlTableIdentity) -> String {
+    return match driver {
+      DbDriver::Postgres => table_id.quoted(),
+      DbDriver::Mysql => format!(
+        "{}.{}",
+        mysql_dto::escape_identifier(&table_id.schema),
+        mysql_dto::escape_identifier(&table_id.name)
+      ),
+    };
+  }
+
+  fn value_literal(driver: DbDriver, value: &FromSqlSink) -> ResultAnyError<String> {
+    return match driver {
+      DbDriver::Postgres => value.to_string_for_statement(),
+      DbDriver::Mysql => mysql_dto::to_string_for_statement(value),
+    };
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use lezeh_common::macros::hashmap_literal;
+  use postgres_types::Type as PsqlType;
+
+  mod table_row_into_insert_statement {
+    use super::*;
+
+    fn create_table() -> PsqlTable {
+      return PsqlTable::fixture("orders")
+        .columns(vec![
+          PsqlTableColumn {
+            is_identity: true,
+            ..PsqlTableColumn::new("id", "integer")
+          },
+          PsqlTableColumn::new("price", "integer"),
+          PsqlTableColumn {
+            is_generated: true,
+            ..PsqlTableColumn::new("total", "integer")
+          },
+        ])
+        .build();
+    }
+
+    fn create_row(table: PsqlTable) -> Rc<PsqlTableRow> {
+      return Rc::new(
+        PsqlTableRow::new(
+          table,
+          vec![
+            (
+              "id".to_owned(),
+              FromSqlSink::from_value(&1_i32, PsqlType::INT4).unwrap(),
+            ),
+            (
+              "price".to_owned(),
+              FromSqlSink::from_value(&10_i32, PsqlType::INT4).unwrap(),
+            ),
+            (
+              "total".to_owned(),
+              FromSqlSink::from_value(&20_i32, PsqlType::INT4).unwrap(),
+            ),
+          ],
+        )
+        .unwrap(),
+      );
+    }
+
+    #[test]
+    fn it_should_skip_generated_and_override_identity_columns() -> ResultAnyError<()> {
+      let table = create_table();
+      let row = create_row(table.clone());
+
+      let statement = RelationInsert::table_row_into_insert_statement(
+        &table,
+        &vec![&row],
+        &InsertStatementConfig::new(DbDriver::Postgres),
+      )?;
+
+      assert!(statement.contains(
+        "insert into \"public\".\"orders\" (\"id\", \"price\") OVERRIDING SYSTEM VALUE VALUES\n  (1, 10);"
+      ));
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_not_override_identity_columns_on_mysql() -> ResultAnyError<()> {
+      let table = create_table();
+      let row = create_row(table.clone());
+
+      let statement = RelationInsert::table_row_into_insert_statement(
+        &table,
+        &vec![&row],
+        &InsertStatementConfig::new(DbDriver::Mysql),
+      )?;
+
+      assert!(
+        statement.contains("insert into `public`.`orders` (`id`, `price`) VALUES\n  (1, 10);")
+      );
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_exclude_and_set_columns() -> ResultAnyError<()> {
+      let table = create_table();
+      let row = create_row(table.clone());
+      let mut config = InsertStatementConfig::new(DbDriver::Postgres);
+
+      config.excluded_columns_by_table_id.insert(
+        table.id.clone(),
+        vec!["id".to_owned()].into_iter().collect(),
+      );
+      config.column_value_by_table_id.insert(
+        table.id.clone(),
+        hashmap_literal! { "price".to_owned() => "now()".to_owned() },
+      );
+
+      let statement =
+        RelationInsert::table_row_into_insert_statement(&table, &vec![&row], &config)?;
+
+      assert!(
+        statement.contains("insert into \"public\".\"orders\" (\"price\") VALUES\n  (now());")
+      );
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_fail_to_set_unknown_column() {
+      let table = create_table();
+      let row = create_row(table.clone());
+      let mut config = InsertStatementConfig::new(DbDriver::Postgres);
+
+      config.column_value_by_table_id.insert(
+        table.id.clone(),
+        hashmap_literal! { "tenant_id".to_owned() => "1".to_owned() },
+      );
+
+      let result = RelationInsert::table_row_into_insert_statement(&table, &vec![&row], &config);
+
+      assert!(result.is_err());
+    }
+  }
+
+  mod table_row_into_copy_statement {
+    use super::*;
+
+    #[test]
+    fn it_should_escape_values_in_the_copy_text_format() -> ResultAnyError<()> {
+      let table = PsqlTable::fixture("notes")
+        .columns(vec![
+          PsqlTableColumn::new("id", "integer"),
+          PsqlTableColumn::new("body", "text"),
+          PsqlTableColumn::new("deleted_at", "timestamp"),
+          PsqlTableColumn {
+            is_generated: true,
+            ..PsqlTableColumn::new("body_length", "integer")
+          },
+        ])
+        .build();
+      let row = Rc::new(PsqlTableRow::new(
+        table.clone(),
+        vec![
+          (
+            "id".to_owned(),
+            FromSqlSink::from_value(&1_i32, PsqlType::INT4)?,
+          ),
+          (
+            "body".to_owned(),
+            FromSqlSink::from_value(&"a\tb\\c\nd", PsqlType::TEXT)?,
+          ),
+          ("deleted_at".to_owned(), FromSqlSink::null()),
+          (
+            "body_length".to_owned(),
+            FromSqlSink::from_value(&7_i32, PsqlType::INT4)?,
+          ),
+        ],
+      )?);
+      let mut config = InsertStatementConfig::new(DbDriver::Postgres);
+
+      let statement = RelationInsert::table_row_into_copy_statement(&table, &vec![&row], &config)?;
+
+      assert!(statement.contains(
+        "COPY \"public\".\"notes\" (\"id\", \"body\", \"deleted_at\") FROM stdin;\n1\ta\\tb\\\\c\\nd\t\\N\n\\.\n"
+      ));
+
+      config.column_value_by_table_id.insert(
+        table.id.clone(),
+        hashmap_literal! { "deleted_at".to_owned() => "now()".to_owned() },
+      );
+
+      assert!(RelationInsert::table_row_into_copy_statement(&table, &vec![&row], &config).is_err());
+
+      return Ok(());
+    }
+  }
+
+  mod into_sequence_resync_statement {
+    use super::*;
+
+    fn create_table() -> PsqlTable {
+      return PsqlTable::fixture("orders")
+        .columns(vec![
+          PsqlTableColumn {
+            sequence_name: Some("public.orders_id_seq".to_owned()),
+            ..PsqlTableColumn::new("id", "integer")
+          },
+          PsqlTableColumn::new("price", "integer"),
+        ])
+        .build();
+    }
+
+    #[test]
+    fn it_should_resync_sequence_owned_columns() {
+      let table = create_table();
+      let statement = RelationInsert::into_sequence_resync_statement(
+        vec![&table].into_iter(),
+        &InsertStatementConfig::new(DbDriver::Postgres),
+      );
+
+      assert!(statement.unwrap().contains(
+        "SELECT setval(pg_get_serial_sequence('\"public\".\"orders\"', 'id'), greatest((SELECT max(\"id\") FROM \"public\".\"orders\"), pg_sequence_last_value(pg_get_serial_sequence('\"public\".\"orders\"', 'id')::regclass)));"
+      ));
+    }
+
+    #[test]
+    fn it_should_not_resync_on_mysql() {
+      let table = create_table();
+      let statement = RelationInsert::into_sequence_resync_statement(
+        vec![&table].into_iter(),
+        &InsertStatementConfig::new(DbDriver::Mysql),
+      );
+
+      assert_eq!(statement, None);
+    }
+  }
 }
@@ -0,0 +1,404 @@
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use petgraph::graph::NodeIndex;
+use petgraph::Direction;
+
+use crate::psql::dto::{PsqlTableIdentity, PsqlTableRow};
+use crate::psql::relation_fetcher::RowGraph;
+use lezeh_common::types::ResultAnyError;
+
+/// One foreign key hop between 2 rows of the graph
+#[derive(Debug, Clone)]
+pub struct RelationPathStep {
+  pub row: Rc<PsqlTableRow>,
+
+  /// true when `row` is referenced by the previous row of the path,
+  /// false when `row` references the previous row.
+  pub is_parent: bool,
+  pub constraint_names: Vec<String>,
+}
+
+/// Foreign key path from the closest root row to a row of the graph
+#[derive(Debug, Clone)]
+pub struct RelationPath {
+  pub root: Rc<PsqlTableRow>,
+  pub steps: Vec<RelationPathStep>,
+}
+
+impl std::fmt::Display for RelationPath {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.root)?;
+
+    for step in self.steps.iter() {
+      let arrow = if step.is_parent { "->" } else { "<-" };
+
+      write!(
+        f,
+        "\n  {} {} via {}",
+        arrow,
+        step.row,
+        step.constraint_names.join(" | ")
+      )?;
+    }
+
+    return Ok(());
+  }
+}
+
+/// Node of the path search and whether the path went to a parent before it
+type PathNode = (NodeIndex, bool);
+
+pub struct RelationPathFinder<'a> {
+  graph: &'a RowGraph,
+  root_node_indexes: Vec<NodeIndex>,
+}
+
+impl<'a> RelationPathFinder<'a> {
+  pub fn new(graph: &'a RowGraph, root_node_indexes: &[NodeIndex]) -> RelationPathFinder<'a> {
+    return RelationPathFinder {
+      graph,
+      root_node_indexes: root_node_indexes.to_vec(),
+    };
+  }
+
+  /// Find all of the shortest paths from the closest root rows to the row
+  /// with the given primary key, at most `max_paths` are returned.
+  pub fn find_paths(
+    &self,
+    table_id: &PsqlTableIdentity,
+    row_id: &str,
+    max_paths: usize,
+  ) -> ResultAnyError<Vec<RelationPath>> {
+    let target_node_index = self
+      .graph
+      .node_indices()
+      .find(|node_index| {
+        let row = &self.graph[*node_index];
+
+        return row.table.id == *table_id && row.row_id_representation == row_id;
+      })
+      .ok_or_else(|| {
+        return anyhow!(
+          "Row {} {} is not part of the relation graph",
+          table_id,
+          row_id
+        );
+      })?;
+
+    // Edges always point from child to parent. The fetch goes both ways from the
+    // root and its children but only to the parents of a parent, so a node is
+    // visited with whether the path already went to a parent.
+    let mut distance_by_node: HashMap<PathNode, usize> = Default::default();
+    let mut previous_nodes_by_node: HashMap<PathNode, Vec<PathNode>> = Default::default();
+    let mut queue: VecDeque<PathNode> = Default::default();
+
+    for root_node_index in self.root_node_indexes.iter() {
+      distance_by_node.insert((*root_node_index, false), 0);
+      queue.push_back((*root_node_index, false));
+    }
+
+    while let Some(node) = queue.pop_front() {
+      let (node_index, went_to_parent) = node;
+      let distance = distance_by_node[&node];
+
+      let parent_nodes = self
+        .graph
+        .neighbors_directed(node_index, Direction::Outgoing)
+        .map(|neighbor_index| (neighbor_index, true));
+      let child_nodes = self
+        .graph
+        .neighbors_directed(node_index, Direction::Incoming)
+        .filter(|_| !went_to_parent)
+        .map(|neighbor_index| (neighbor_index, false));
+
+      for neighbor in parent_nodes.chain(child_nodes) {
+        match distance_by_node.get(&neighbor) {
+          None => {
+            distance_by_node.insert(neighbor, distance + 1);
+            queue.push_back(neighbor);
+          }
+          Some(neighbor_distance) if *neighbor_distance != distance + 1 => continue,
+          Some(_) => {}
+        }
+
+        let previous_nodes = previous_nodes_by_node.entry(neighbor).or_default();
+
+        if !previous_nodes.contains(&node) {
+          previous_nodes.push(node);
+        }
+      }
+    }
+
+    // Keep the output stable regardless of the fetch order
+    for previous_nodes in previous_nodes_by_node.values_mut() {
+      previous_nodes.sort_by(|a, b| {
+        return self.graph[a.0].cmp(&self.graph[b.0]).then(a.1.cmp(&b.1));
+      });
+    }
+
+    // The target can be reached both before and after going to a parent
+    let target_nodes: Vec<PathNode> = vec![(target_node_index, false), (target_node_index, true)]
+      .into_iter()
+      .filter(|node| distance_by_node.contains_key(node))
+      .collect();
+    let min_distance: Option<usize> = target_nodes.iter().map(|node| distance_by_node[node]).min();
+    let mut node_index_paths: Vec<Vec<NodeIndex>> = vec![];
+
+    for target_node in target_nodes
+      .into_iter()
+      .filter(|node| Some(distance_by_node[node]) == min_distance)
+    {
+      self.collect_node_index_paths(
+        target_node,
+        &previous_nodes_by_node,
+        &mut vec![],
+        &mut node_index_paths,
+        max_paths,
+      );
+    }
+
+    return Ok(
+      node_index_paths
+        .into_iter()
+        .map(|node_index_path| self.create_relation_path(node_index_path))
+        .collect(),
+    );
+  }
+
+  /// Walk back from the target to the root, `current_path` is in reverse order
+  fn collect_node_index_paths(
+    &self,
+    node: PathNode,
+    previous_nodes_by_node: &HashMap<PathNode, Vec<PathNode>>,
+    current_path: &mut Vec<NodeIndex>,
+    node_index_paths: &mut Vec<Vec<NodeIndex>>,
+    max_paths: usize,
+  ) {
+    if node_index_paths.len() >= max_paths {
+      return;
+    }
+
+    let (node_index, went_to_parent) = node;
+
+    current_path.push(node_index);
+
+    if !went_to_parent && self.root_node_indexes.contains(&node_index) {
+      node_index_paths.push(current_path.iter().rev().cloned().collect());
+    } else if let Some(previous_nodes) = previous_nodes_by_node.get(&node) {
+      for previous_node in previous_nodes.iter() {
+        self.collect_node_index_paths(
+          *previous_node,
+          previous_nodes_by_node,
+          current_path,
+          node_index_paths,
+          max_paths,
+        );
+      }
+    }
+
+    current_path.pop();
+  }
+
+  fn create_relation_path(&self, node_index_path: Vec<NodeIndex>) -> RelationPath {
+    let steps = node_index_path
+      .windows(2)
+      .map(|node_indexes| {
+        let (from_index, to_index) = (node_indexes[0], node_indexes[1]);
+        let is_parent = self
+          .graph
+          .neighbors_directed(from_index, Direction::Outgoing)
+          .any(|node_index| node_index == to_index);
+
+        let (child, parent) = if is_parent {
+          (&self.graph[from_index], &self.graph[to_index])
+        } else {
+          (&self.graph[to_index], &self.graph[from_index])
+        };
+
+        return RelationPathStep {
+          row: self.graph[to_index].clone(),
+          is_parent,
+          constraint_names: RelationPathFinder::constraint_names(child, parent),
+        };
+      })
+      .collect();
+
+    return RelationPath {
+      root: self.graph[node_index_path[0]].clone(),
+      steps,
+    };
+  }
+
+  /// Foreign keys of the child that point to the parent row,
+  /// falls back to every foreign key to the parent table.
+  fn constraint_names(child: &PsqlTableRow, parent: &PsqlTableRow) -> Vec<String> {
+    let mut fk_constraint_names: Vec<String> = vec![];
+    let mut matching_constraint_names: Vec<String> = vec![];
+
+    for (constraint_name, fk) in child.table.referencing_fk_by_constraint_name.iter() {
+      if fk.foreign_table_schema != parent.table.id.schema
+        || fk.foreign_table_name != parent.table.id.name
+      {
+        continue;
+      }
+
+      fk_constraint_names.push(constraint_name.clone());
+
+      let is_matching = child
+        .get_value(&fk.column.name)
+        .and_then(|value| value.to_string_for_statement().ok())
+        .map(|value| value.trim_matches('\'') == parent.row_id_representation)
+        .unwrap_or(false);
+
+      if is_matching {
+        matching_constraint_names.push(constraint_name.clone());
+      }
+    }
+
+    let mut constraint_names = if matching_constraint_names.is_empty() {
+      fk_constraint_names
+    } else {
+      matching_constraint_names
+    };
+
+    constraint_names.sort();
+
+    return constraint_names;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::dto::*;
+  use postgres_types::Type as PsqlType;
+
+  fn create_row(table: &PsqlTable, values: Vec<(&str, i32)>) -> Rc<PsqlTableRow> {
+    let values = values
+      .into_iter()
+      .map(|(column_name, value)| {
+        return (
+          column_name.to_owned(),
+          FromSqlSink::from_value(&value, PsqlType::INT4).unwrap(),
+        );
+      })
+      .collect();
+
+    return Rc::new(PsqlTableRow::new(table.clone(), values).unwrap());
+  }
+
+  mod find_paths {
+    use super::*;
+
+    #[test]
+    fn it_should_explain_path_with_constraint_names() -> ResultAnyError<()> {
+      let orders = PsqlTable::fixture("orders").build();
+      let products = PsqlTable::fixture("products").build();
+      let order_items = PsqlTable::fixture("order_items")
+        .references("order_id", "orders")
+        .references("product_id", "products")
+        .build();
+
+      let mut graph = RowGraph::new();
+      let order_index = graph.add_node(create_row(&orders, vec![("id", 1)]));
+      let product_index = graph.add_node(create_row(&products, vec![("id", 5)]));
+      let order_item_indexes: Vec<NodeIndex> = vec![7, 8]
+        .into_iter()
+        .map(|id| {
+          return graph.add_node(create_row(
+            &order_items,
+            vec![("id", id), ("order_id", 1), ("product_id", 5)],
+          ));
+        })
+        .collect();
+
+      for order_item_index in order_item_indexes {
+        graph.update_edge(order_item_index, order_index, -1);
+        graph.update_edge(order_item_index, product_index, -1);
+      }
+
+      let paths = RelationPathFinder::new(&graph, &[order_index]).find_paths(
+        &PsqlTableIdentity::new("public", "products"),
+        "5",
+        10,
+      )?;
+
+      assert_eq!(
+        paths
+          .iter()
+          .map(ToString::to_string)
+          .collect::<Vec<String>>(),
+        vec![
+          indoc::indoc! {"
+            public.orders 1
+              <- public.order_items 7 via order_items_order_id_foreign
+              -> public.products 5 via order_items_product_id_foreign"},
+          indoc::indoc! {"
+            public.orders 1
+              <- public.order_items 8 via order_items_order_id_foreign
+              -> public.products 5 via order_items_product_id_foreign"},
+        ]
+      );
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_not_explain_children_of_parent_rows() -> ResultAnyError<()> {
+      let shops = PsqlTable::fixture("shops").build();
+      let products = PsqlTable::fixture("products")
+        .references("shop_id", "shops")
+        .build();
+      let variants = PsqlTable::fixture("variants")
+        .references("product_id", "products")
+        .build();
+      let orders = PsqlTable::fixture("orders")
+        .references("shop_id", "shops")
+        .build();
+      let order_items = PsqlTable::fixture("order_items")
+        .references("order_id", "orders")
+        .references("variant_id", "variants")
+        .build();
+
+      let mut graph = RowGraph::new();
+      let order_index = graph.add_node(create_row(&orders, vec![("id", 1), ("shop_id", 3)]));
+      let shop_index = graph.add_node(create_row(&shops, vec![("id", 3)]));
+      let product_index = graph.add_node(create_row(&products, vec![("id", 5), ("shop_id", 3)]));
+      let variant_index = graph.add_node(create_row(&variants, vec![("id", 9), ("product_id", 5)]));
+      let order_item_index = graph.add_node(create_row(
+        &order_items,
+        vec![("id", 7), ("order_id", 1), ("variant_id", 9)],
+      ));
+
+      graph.update_edge(order_index, shop_index, -1);
+      graph.update_edge(product_index, shop_index, -1);
+      graph.update_edge(variant_index, product_index, -1);
+      graph.update_edge(order_item_index, order_index, -1);
+      graph.update_edge(order_item_index, variant_index, -1);
+
+      // orders 1 -> shops 3 <- products 5 is shorter but the fetch never goes
+      // from the shop to its products
+      let paths = RelationPathFinder::new(&graph, &[order_index]).find_paths(
+        &PsqlTableIdentity::new("public", "products"),
+        "5",
+        10,
+      )?;
+
+      assert_eq!(
+        paths
+          .iter()
+          .map(ToString::to_string)
+          .collect::<Vec<String>>(),
+        vec![indoc::indoc! {"
+          public.orders 1
+            <- public.order_items 7 via order_items_order_id_foreign
+            -> public.variants 9 via order_items_variant_id_foreign
+            -> public.products 5 via variants_product_id_foreign"}]
+      );
+
+      return Ok(());
+    }
+  }
+}
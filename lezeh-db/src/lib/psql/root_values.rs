@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+
+use lezeh_common::types::ResultAnyError;
+
+/// Root values of `lezeh db cherry-pick --values-from`, either 1 value per line,
+/// comma separated values or a csv export with a header that contains the column.
+pub struct RootValues;
+
+impl RootValues {
+  /// Values are deduplicated while keeping the order of the content.
+  /// `header_column` is the csv column of the values, without it the first line
+  /// is only a header when it contains `column_name`.
+  pub fn parse(
+    content: &str,
+    column_name: &str,
+    header_column: Option<&str>,
+  ) -> ResultAnyError<Vec<String>> {
+    // Line numbers are kept for the errors, empty lines are still counted
+    let mut rows = content
+      .lines()
+      .enumerate()
+      .map(|(line_index, line)| (line_index + 1, line.trim()))
+      .filter(|(_, line)| !line.is_empty())
+      .map(|(line_number, line)| (line_number, RootValues::split_cells(line)))
+      .peekable();
+
+    // A header picks the column of a csv export, otherwise every cell is a value
+    let column_index: Option<usize> = match (rows.peek(), header_column) {
+      (None, _) => None,
+      (Some((_, header)), None) => header
+        .iter()
+        .position(|cell| cell.eq_ignore_ascii_case(column_name)),
+      (Some((_, header)), Some(header_column)) => Some(
+        header
+          .iter()
+          .position(|cell| cell.eq_ignore_ascii_case(header_column))
+          .ok_or_else(|| {
+            return anyhow!(
+              "Header has no {} column, it only has {}",
+              header_column,
+              header.join(", ")
+            );
+          })?,
+      ),
+    };
+
+    let mut values: Vec<String> = vec![];
+    let mut seen_values: HashSet<String> = Default::default();
+
+    let mut push_value = |value: String| {
+      if !value.is_empty() && seen_values.insert(value.clone()) {
+        values.push(value);
+      }
+    };
+
+    match column_index {
+      None => {
+        for (_, cells) in rows {
+          cells.into_iter().for_each(&mut push_value);
+        }
+      }
+      Some(column_index) => {
+        for (line_number, mut cells) in rows.skip(1) {
+          if column_index >= cells.len() {
+            return Err(anyhow!(
+              "Line {} has no {} column, it only has {} columns",
+              line_number,
+              header_column.unwrap_or(column_name),
+              cells.len()
+            ));
+          }
+
+          push_value(cells.swap_remove(column_index));
+        }
+      }
+    }
+
+    return Ok(values);
+  }
+
+  /// Split a csv line, commas inside double quotes are part of the value
+  fn split_cells(line: &str) -> Vec<String> {
+    let mut cells: Vec<String> = vec![];
+    let mut cell = String::new();
+    let mut is_quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+      match c {
+        '"' if is_quoted && chars.peek() == Some(&'"') => {
+          cell.push('"');
+          chars.next();
+        }
+        '"' => is_quoted = !is_quoted,
+        ',' if !is_quoted => cells.push(std::mem::take(&mut cell).trim().to_owned()),
+        _ => cell.push(c),
+      }
+    }
+
+    cells.push(cell.trim().to_owned());
+
+    return cells;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  mod parse {
+    use super::*;
+
+    #[test]
+    fn it_should_parse_lines_and_comma_separated_values() -> ResultAnyError<()> {
+      let content = "1\n\n 2, 3\n\"4\"\n2\n";
+
+      assert_eq!(
+        RootValues::parse(content, "id", None)?,
+        vec!["1", "2", "3", "4"]
+      );
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_pick_the_column_of_a_csv_with_header() -> ResultAnyError<()> {
+      let content = indoc::indoc! {r#"
+        ticket,ID,note
+        T-1,10,"refund, urgent"
+        T-2,11,""
+        T-3,10,"said ""hi"""
+      "#};
+
+      assert_eq!(RootValues::parse(content, "id", None)?, vec!["10", "11"]);
+      assert!(RootValues::parse("id,note\n1\n", "id", None).is_ok());
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_pick_the_given_header_column() -> ResultAnyError<()> {
+      let content = "ticket,order_id\nT-1,10\nT-2,11\n";
+
+      assert_eq!(
+        RootValues::parse(content, "id", Some("order_id"))?,
+        vec!["10", "11"]
+      );
+      assert_eq!(
+        RootValues::parse(content, "id", Some("id"))
+          .unwrap_err()
+          .to_string(),
+        "Header has no id column, it only has ticket, order_id"
+      );
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_report_the_line_number_of_a_row_without_the_column() {
+      let content = "note,id\n\na,1\n\nb\n";
+
+      assert_eq!(
+        RootValues::parse(content, "id", None)
+          .unwrap_err()
+          .to_string(),
+        "Line 5 has no id column, it only has 1 columns"
+      );
+    }
+  }
+}
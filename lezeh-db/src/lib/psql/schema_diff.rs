@@ -0,0 +1,901 @@
+use std::collections::HashMap;
+
+use crate::config::DbDriver;
+use crate::psql::dto::{
+  FromSqlSink, PsqlCheckConstraint, PsqlEnumType, PsqlForeignKey, PsqlTable, PsqlTableColumn,
+  PsqlTableIdentity, PsqlUniqueConstraint,
+};
+use crate::psql::relation_insert::RelationInsert;
+use crate::psql::table_ddl::TableDdl;
+
+/// Difference of a table that exists in both dbs,
+/// `Added` exists only in the source db and `Removed` only in the target db.
+#[derive(Debug, PartialEq)]
+pub enum TableChange<'a> {
+  ColumnAdded(&'a PsqlTableColumn),
+  ColumnRemoved(&'a PsqlTableColumn),
+  ColumnChanged {
+    source: &'a PsqlTableColumn,
+    target: &'a PsqlTableColumn,
+  },
+  PrimaryKeyChanged {
+    source: &'a PsqlTableColumn,
+    target: &'a PsqlTableColumn,
+  },
+  ForeignKeyAdded(&'a PsqlForeignKey),
+  ForeignKeyRemoved(&'a PsqlForeignKey),
+  UniqueConstraintAdded(&'a PsqlUniqueConstraint),
+  UniqueConstraintRemoved(&'a PsqlUniqueConstraint),
+  CheckConstraintAdded(&'a PsqlCheckConstraint),
+  CheckConstraintRemoved(&'a PsqlCheckConstraint),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TableDiff<'a> {
+  pub table: &'a PsqlTable,
+  pub changes: Vec<TableChange<'a>>,
+}
+
+/// Labels of an enum type that exists in both dbs, the enum type is the source one
+#[derive(Debug, PartialEq)]
+pub struct EnumTypeDiff<'a> {
+  pub enum_type: &'a PsqlEnumType,
+  pub added_labels: Vec<&'a str>,
+  pub removed_labels: Vec<&'a str>,
+}
+
+/// Changes that turn the target schema into the source schema, printed by `lezeh db schema-diff`.
+/// Both schemas must be loaded by the same driver.
+pub struct SchemaDiff<'a> {
+  driver: DbDriver,
+  source_table_by_id: &'a HashMap<PsqlTableIdentity, PsqlTable>,
+  table_ddl: TableDdl<'a>,
+  pub added_tables: Vec<&'a PsqlTable>,
+  pub removed_tables: Vec<&'a PsqlTable>,
+  pub table_diffs: Vec<TableDiff<'a>>,
+
+  /// Enum types are only known through the columns that use them
+  pub added_enum_types: Vec<&'a PsqlEnumType>,
+  pub enum_type_diffs: Vec<EnumTypeDiff<'a>>,
+}
+
+impl<'a> SchemaDiff<'a> {
+  pub fn new(
+    driver: DbDriver,
+    source_table_by_id: &'a HashMap<PsqlTableIdentity, PsqlTable>,
+    target_table_by_id: &'a HashMap<PsqlTableIdentity, PsqlTable>,
+  ) -> SchemaDiff<'a> {
+    let mut added_tables: Vec<&'a PsqlTable> = vec![];
+    let mut removed_tables: Vec<&'a PsqlTable> = vec![];
+    let mut table_diffs: Vec<TableDiff<'a>> = vec![];
+
+    for source_table in source_table_by_id.values() {
+      match target_table_by_id.get(&source_table.id) {
+        Some(target_table) => {
+          let changes = SchemaDiff::table_changes(source_table, target_table);
+
+          if !changes.is_empty() {
+            table_diffs.push(TableDiff {
+              table: target_table,
+              changes,
+            });
+          }
+        }
+        None => added_tables.push(source_table),
+      }
+    }
+
+    for target_table in target_table_by_id.values() {
+      if !source_table_by_id.contains_key(&target_table.id) {
+        removed_tables.push(target_table);
+      }
+    }
+
+    added_tables.sort_by_key(|table| table.id.to_string());
+    removed_tables.sort_by_key(|table| table.id.to_string());
+    table_diffs.sort_by_key(|table_diff| table_diff.table.id.to_string());
+
+    let target_enum_types = TableDdl::enum_types(target_table_by_id.values());
+    let mut added_enum_types: Vec<&'a PsqlEnumType> = vec![];
+    let mut enum_type_diffs: Vec<EnumTypeDiff<'a>> = vec![];
+
+    for (enum_type_id, source_enum_type) in TableDdl::enum_types(source_table_by_id.values()) {
+      let target_enum_type = match target_enum_types.get(&enum_type_id) {
+        Some(target_enum_type) => target_enum_type,
+        None => {
+          added_enum_types.push(source_enum_type);
+
+          continue;
+        }
+      };
+
+      let labels_missing_in = |enum_type: &'a PsqlEnumType, other_enum_type: &PsqlEnumType| {
+        return enum_type
+          .labels
+          .iter()
+          .filter(|label| !other_enum_type.labels.contains(label))
+          .map(String::as_str)
+          .collect::<Vec<&'a str>>();
+      };
+      let added_labels = labels_missing_in(source_enum_type, target_enum_type);
+      let removed_labels = labels_missing_in(target_enum_type, source_enum_type);
+
+      if !added_labels.is_empty() || !removed_labels.is_empty() {
+        enum_type_diffs.push(EnumTypeDiff {
+          enum_type: source_enum_type,
+          added_labels,
+          removed_labels,
+        });
+      }
+    }
+
+    return SchemaDiff {
+      driver,
+      source_table_by_id,
+      table_ddl: TableDdl::new(driver, added_tables.clone().into_iter())
+        .existing_tables(target_table_by_id.values()),
+      added_tables,
+      removed_tables,
+      table_diffs,
+      added_enum_types,
+      enum_type_diffs,
+    };
+  }
+
+  /// Tables of the source schema moved into the target schema,
+  /// e.g. mysql schemas are named after their database.
+  pub fn rebase_tables(
+    table_by_id: HashMap<PsqlTableIdentity, PsqlTable>,
+    source_schema: &str,
+    target_schema: &str,
+  ) -> HashMap<PsqlTableIdentity, PsqlTable> {
+    let rebase_schema = |schema: &mut String| {
+      if schema == source_schema {
+        *schema = target_schema.to_owned();
+      }
+    };
+    let rebase_column = |mut column: PsqlTableColumn| {
+      if let Some(enum_type) = column.enum_type.as_mut() {
+        rebase_schema(&mut enum_type.schema);
+      }
+
+      return column;
+    };
+
+    return table_by_id
+      .into_values()
+      .map(|mut table| {
+        rebase_schema(&mut table.id.schema);
+        table.primary_column = rebase_column(table.primary_column);
+        table.columns = table.columns.into_iter().map(rebase_column).collect();
+
+        for fk in table
+          .referencing_fk_by_constraint_name
+          .values_mut()
+          .chain(table.referenced_fk_by_constraint_name.values_mut())
+        {
+          rebase_schema(&mut fk.foreign_table_schema);
+        }
+
+        return (table.id.clone(), table);
+      })
+      .collect();
+  }
+
+  pub fn is_empty(&self) -> bool {
+    return self.added_tables.is_empty()
+      && self.removed_tables.is_empty()
+      && self.table_diffs.is_empty()
+      && self.added_enum_types.is_empty()
+      && self.enum_type_diffs.is_empty();
+  }
+
+  fn table_changes(
+    source_table: &'a PsqlTable,
+    target_table: &'a PsqlTable,
+  ) -> Vec<TableChange<'a>> {
+    let mut changes: Vec<TableChange<'a>> = vec![];
+    let target_column_by_name: HashMap<&str, &PsqlTableColumn> = target_table
+      .columns
+      .iter()
+      .map(|column| (column.name.as_str(), column))
+      .collect();
+
+    for source_column in source_table.ordered_columns() {
+      match target_column_by_name.get(source_column.name.as_str()) {
+        Some(target_column) => {
+          if SchemaDiff::column_properties(source_column)
+            != SchemaDiff::column_properties(target_column)
+          {
+            changes.push(TableChange::ColumnChanged {
+              source: source_column,
+              target: target_column,
+            });
+          }
+        }
+        None => changes.push(TableChange::ColumnAdded(source_column)),
+      }
+    }
+
+    for target_column in target_table.ordered_columns() {
+      if !source_table
+        .columns
+        .iter()
+        .any(|column| column.name == target_column.name)
+      {
+        changes.push(TableChange::ColumnRemoved(target_column));
+      }
+    }
+
+    if source_table.primary_column.name != target_table.primary_column.name {
+      changes.push(TableChange::PrimaryKeyChanged {
+        source: &source_table.primary_column,
+        target: &target_table.primary_column,
+      });
+    }
+
+    // Foreign keys that changed are dropped and added again
+    let fk_signature = |fk: &PsqlForeignKey| {
+      return (
+        fk.column.name.clone(),
+        fk.foreign_table_schema.clone(),
+        fk.foreign_table_name.clone(),
+      );
+    };
+    let mut source_fks: Vec<&PsqlForeignKey> = source_table
+      .referencing_fk_by_constraint_name
+      .values()
+      .collect();
+    let mut target_fks: Vec<&PsqlForeignKey> = target_table
+      .referencing_fk_by_constraint_name
+      .values()
+      .collect();
+
+    source_fks.sort_by_key(|fk| fk.name.clone());
+    target_fks.sort_by_key(|fk| fk.name.clone());
+
+    for target_fk in target_fks.iter() {
+      let is_unchanged = source_fks
+        .iter()
+        .any(|fk| fk.name == target_fk.name && fk_signature(fk) == fk_signature(target_fk));
+
+      if !is_unchanged {
+        changes.push(TableChange::ForeignKeyRemoved(target_fk));
+      }
+    }
+
+    for source_fk in source_fks.iter() {
+      let is_unchanged = target_fks
+        .iter()
+        .any(|fk| fk.name == source_fk.name && fk_signature(fk) == fk_signature(source_fk));
+
+      if !is_unchanged {
+        changes.push(TableChange::ForeignKeyAdded(source_fk));
+      }
+    }
+
+    for target_unique_constraint in target_table.unique_constraints.iter() {
+      if !source_table
+        .unique_constraints
+        .contains(target_unique_constraint)
+      {
+        changes.push(TableChange::UniqueConstraintRemoved(
+          target_unique_constraint,
+        ));
+      }
+    }
+
+    for source_unique_constraint in source_table.unique_constraints.iter() {
+      if !target_table
+        .unique_constraints
+        .contains(source_unique_constraint)
+      {
+        changes.push(TableChange::UniqueConstraintAdded(source_unique_constraint));
+      }
+    }
+
+    for target_check_constraint in target_table.check_constraints.iter() {
+      if !source_table
+        .check_constraints
+        .contains(target_check_constraint)
+      {
+        changes.push(TableChange::CheckConstraintRemoved(target_check_constraint));
+      }
+    }
+
+    for source_check_constraint in source_table.check_constraints.iter() {
+      if !target_table
+        .check_constraints
+        .contains(source_check_constraint)
+      {
+        changes.push(TableChange::CheckConstraintAdded(source_check_constraint));
+      }
+    }
+
+    return changes;
+  }
+
+  /// Properties that are compared, ordinal position and sequence names are ignored
+  fn column_properties(column: &PsqlTableColumn) -> Vec<(&'static str, String)> {
+    let column_type = match (column.column_type.as_ref(), column.enum_type.as_ref()) {
+      (_, Some(enum_type)) => format!("{}.{}", enum_type.schema, enum_type.name),
+      (Some(column_type), None) => column_type.clone(),
+      (None, None) => match column.character_maximum_length {
+        Some(length) => format!("{}({})", column.data_type, length),
+        None => column.data_type.clone(),
+      },
+    };
+
+    return vec![
+      ("type", column_type),
+      (
+        "nullable",
+        if column.is_nullable { "yes" } else { "no" }.to_owned(),
+      ),
+      (
+        "default",
+        column
+          .default_value
+          .clone()
+          .unwrap_or_else(|| "none".to_owned()),
+      ),
+      (
+        "identity",
+        if column.is_identity || column.is_auto_increment {
+          "yes"
+        } else {
+          "no"
+        }
+        .to_owned(),
+      ),
+      (
+        "generated",
+        column
+          .generation_expression
+          .clone()
+          .filter(|_| column.is_generated)
+          .unwrap_or_else(|| "none".to_owned()),
+      ),
+    ];
+  }
+
+  /// Statements that turn the target schema into the source schema, statements that drop
+  /// tables or columns are commented out so they are only run after a review.
+  pub fn migration_statements(&self) -> Vec<String> {
+    let mut drop_constraint_statements: Vec<String> = vec![];
+    let mut column_statements: Vec<String> = vec![];
+    let mut add_constraint_statements: Vec<String> = vec![];
+    let mut drop_data_statements: Vec<String> = vec![];
+
+    for table_diff in self.table_diffs.iter() {
+      let table_identifier = self.table_identifier(&table_diff.table.id);
+
+      for change in table_diff.changes.iter() {
+        match change {
+          TableChange::ColumnAdded(column) => column_statements.push(format!(
+            "ALTER TABLE {} ADD COLUMN {};",
+            table_identifier,
+            self.table_ddl.column_definition(column)
+          )),
+          TableChange::ColumnRemoved(column) => drop_data_statements.push(format!(
+            "-- ALTER TABLE {} DROP COLUMN {};",
+            table_identifier,
+            self.table_ddl.quote_identifier(&column.name)
+          )),
+          TableChange::ColumnChanged { source, target } => column_statements
+            .extend(self.alter_column_statements(&table_identifier, source, target)),
+          TableChange::PrimaryKeyChanged { source, target } => column_statements.push(format!(
+            "-- Primary key of {} changed from {} to {}, it is not migrated",
+            table_diff.table.id, target.name, source.name
+          )),
+          TableChange::ForeignKeyAdded(fk) => {
+            let foreign_table_id = PsqlTableIdentity::new(
+              fk.foreign_table_schema.as_str(),
+              fk.foreign_table_name.as_str(),
+            );
+
+            add_constraint_statements.push(match self.source_table_by_id.get(&foreign_table_id) {
+              Some(foreign_table) => format!(
+                "ALTER TABLE {} ADD {};",
+                table_identifier,
+                self
+                  .table_ddl
+                  .foreign_key_definition(fk, &foreign_table.primary_column.name)
+              ),
+              None => format!(
+                "-- Skipped foreign key {}, {} is not loaded",
+                fk.name, foreign_table_id
+              ),
+            });
+          }
+          TableChange::ForeignKeyRemoved(fk) => drop_constraint_statements
+            .push(self.drop_constraint_statement(&table_identifier, "FOREIGN KEY", &fk.name)),
+          TableChange::UniqueConstraintAdded(unique_constraint) => {
+            add_constraint_statements.push(format!(
+              "ALTER TABLE {} ADD {};",
+              table_identifier,
+              self
+                .table_ddl
+                .unique_constraint_definition(unique_constraint)
+            ))
+          }
+          TableChange::UniqueConstraintRemoved(unique_constraint) => drop_constraint_statements
+            .push(self.drop_constraint_statement(
+              &table_identifier,
+              "INDEX",
+              &unique_constraint.name,
+            )),
+          TableChange::CheckConstraintAdded(check_constraint) => {
+            add_constraint_statements.push(format!(
+              "ALTER TABLE {} ADD {};",
+              table_identifier,
+              self.table_ddl.check_constraint_definition(check_constraint)
+            ))
+          }
+          TableChange::CheckConstraintRemoved(check_constraint) => drop_constraint_statements.push(
+            self.drop_constraint_statement(&table_identifier, "CHECK", &check_constraint.name),
+          ),
+        }
+      }
+    }
+
+    for table in self.removed_tables.iter() {
+      drop_data_statements.push(format!(
+        "-- DROP TABLE {};",
+        self.table_identifier(&table.id)
+      ));
+    }
+
+    let mut statements: Vec<String> = drop_constraint_statements;
+
+    statements.extend(self.enum_type_statements());
+    statements.extend(self.table_ddl.into_statements());
+    statements.extend(column_statements);
+    statements.extend(add_constraint_statements);
+
+    if !drop_data_statements.is_empty() {
+      statements.push("-- Statements below drop data, run them after a review".to_owned());
+      statements.extend(drop_data_statements);
+    }
+
+    return statements;
+  }
+
+  /// Enum types used by the added tables are created along with the tables,
+  /// labels are added next to their neighbour so they keep the source order.
+  fn enum_type_statements(&self) -> Vec<String> {
+    let added_table_enum_types = TableDdl::enum_types(self.added_tables.iter().cloned());
+    let mut statements: Vec<String> = self
+      .added_enum_types
+      .iter()
+      .filter(|enum_type| {
+        return !added_table_enum_types
+          .contains_key(&(enum_type.schema.as_str(), enum_type.name.as_str()));
+      })
+      .map(|enum_type| self.table_ddl.enum_type_statement(enum_type))
+      .collect();
+
+    for enum_type_diff in self.enum_type_diffs.iter() {
+      let enum_type = enum_type_diff.enum_type;
+      let enum_type_identifier = self.table_ddl.enum_type_identifier(enum_type);
+
+      for (index, label) in enum_type.labels.iter().enumerate() {
+        if !enum_type_diff.added_labels.contains(&label.as_str()) {
+          continue;
+        }
+
+        let position = match index {
+          0 => enum_type
+            .labels
+            .iter()
+            .find(|label| !enum_type_diff.added_labels.contains(&label.as_str()))
+            .map(|next_label| format!(" BEFORE {}", FromSqlSink::escape_string(next_label))),
+          _ => Some(format!(
+            " AFTER {}",
+            FromSqlSink::escape_string(&enum_type.labels[index - 1])
+          )),
+        };
+
+        statements.push(format!(
+          "ALTER TYPE {} ADD VALUE IF NOT EXISTS {}{};",
+          enum_type_identifier,
+          FromSqlSink::escape_string(label),
+          position.unwrap_or_default()
+        ));
+      }
+
+      for label in enum_type_diff.removed_labels.iter() {
+        statements.push(format!(
+          "-- Label {} of {} is not in the source, postgres can't drop enum labels",
+          FromSqlSink::escape_string(label),
+          enum_type_identifier
+        ));
+      }
+    }
+
+    return statements;
+  }
+
+  fn alter_column_statements(
+    &self,
+    table_identifier: &str,
+    source: &PsqlTableColumn,
+    target: &PsqlTableColumn,
+  ) -> Vec<String> {
+    if self.driver == DbDriver::Mysql {
+      return vec![format!(
+        "ALTER TABLE {} MODIFY COLUMN {};",
+        table_identifier,
+        self.table_ddl.column_definition(source)
+      )];
+    }
+
+    let column_identifier = self.table_ddl.quote_identifier(&source.name);
+    let alter_column = format!(
+      "ALTER TABLE {} ALTER COLUMN {}",
+      table_identifier, column_identifier
+    );
+    let target_properties = SchemaDiff::column_properties(target);
+    let mut statements: Vec<String> = vec![];
+
+    for (index, (name, value)) in SchemaDiff::column_properties(source)
+      .into_iter()
+      .enumerate()
+    {
+      if target_properties[index].1 == value {
+        continue;
+      }
+
+      statements.push(match name {
+        "type" => {
+          let column_type = self.table_ddl.column_type(source);
+
+          format!(
+            "{} TYPE {} USING {}::{};",
+            alter_column, column_type, column_identifier, column_type
+          )
+        }
+        "nullable" if source.is_nullable => format!("{} DROP NOT NULL;", alter_column),
+        "nullable" => format!("{} SET NOT NULL;", alter_column),
+        "default" => match source.default_value.as_ref() {
+          Some(default_value) => format!(
+            "{} SET DEFAULT {};",
+            alter_column,
+            self.table_ddl.default_literal(default_value)
+          ),
+          None => format!("{} DROP DEFAULT;", alter_column),
+        },
+        _ => format!(
+          "-- {} of {}.{} changed from {} to {}, it is not migrated",
+          name, table_identifier, source.name, target_properties[index].1, value
+        ),
+      });
+    }
+
+    return statements;
+  }
+
+  /// Mysql drops constraints by their kind, postgres only needs the name
+  fn drop_constraint_statement(
+    &self,
+    table_identifier: &str,
+    mysql_constraint_kind: &str,
+    constraint_name: &str,
+  ) -> String {
+    let constraint_kind = match self.driver {
+      DbDriver::Postgres => "CONSTRAINT",
+      DbDriver::Mysql => mysql_constraint_kind,
+    };
+
+    return format!(
+      "ALTER TABLE {} DROP {} {};",
+      table_identifier,
+      constraint_kind,
+      self.table_ddl.quote_identifier(constraint_name)
+    );
+  }
+
+  fn table_identifier(&self, table_id: &PsqlTableIdentity) -> String {
+    return RelationInsert::table_identifier(self.driver, table_id);
+  }
+}
+
+impl<'a> std::fmt::Display for SchemaDiff<'a> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if self.is_empty() {
+      return writeln!(f, "No differences");
+    }
+
+    for table in self.added_tables.iter() {
+      writeln!(f, "+ table {}", table.id)?;
+    }
+
+    for table in self.removed_tables.iter() {
+      writeln!(f, "- table {}", table.id)?;
+    }
+
+    for enum_type in self.added_enum_types.iter() {
+      writeln!(f, "+ type {}.{}", enum_type.schema, enum_type.name)?;
+    }
+
+    for enum_type_diff in self.enum_type_diffs.iter() {
+      let label_changes: Vec<String> = enum_type_diff
+        .added_labels
+        .iter()
+        .map(|label| format!("+ label {}", label))
+        .chain(
+          enum_type_diff
+            .removed_labels
+            .iter()
+            .map(|label| format!("- label {}", label)),
+        )
+        .collect();
+
+      writeln!(
+        f,
+        "~ type {}.{}: {}",
+        enum_type_diff.enum_type.schema,
+        enum_type_diff.enum_type.name,
+        label_changes.join(", ")
+      )?;
+    }
+
+    for table_diff in self.table_diffs.iter() {
+      writeln!(f)?;
+      writeln!(f, "{}", table_diff.table.id)?;
+
+      for change in table_diff.changes.iter() {
+        let column_type = |column: &PsqlTableColumn| {
+          return SchemaDiff::column_properties(column)[0].1.clone();
+        };
+
+        match change {
+          TableChange::ColumnAdded(column) => {
+            writeln!(f, "  + column {} {}", column.name, column_type(column))?
+          }
+          TableChange::ColumnRemoved(column) => {
+            writeln!(f, "  - column {} {}", column.name, column_type(column))?
+          }
+          TableChange::ColumnChanged { source, target } => {
+            let target_properties = SchemaDiff::column_properties(target);
+            let changed_properties: Vec<String> = SchemaDiff::column_properties(source)
+              .into_iter()
+              .zip(target_properties)
+              .filter(|(source_property, target_property)| source_property != target_property)
+              .map(|((name, source_value), (_, target_value))| {
+                return format!("{} {} -> {}", name, target_value, source_value);
+              })
+              .collect();
+
+            writeln!(
+              f,
+              "  ~ column {}: {}",
+              source.name,
+              changed_properties.join(", ")
+            )?
+          }
+          TableChange::PrimaryKeyChanged { source, target } => {
+            writeln!(f, "  ~ primary key {} -> {}", target.name, source.name)?
+          }
+          TableChange::ForeignKeyAdded(fk) => writeln!(
+            f,
+            "  + foreign key {} ({}) -> {}.{}",
+            fk.name, fk.column.name, fk.foreign_table_schema, fk.foreign_table_name
+          )?,
+          TableChange::ForeignKeyRemoved(fk) => writeln!(
+            f,
+            "  - foreign key {} ({}) -> {}.{}",
+            fk.name, fk.column.name, fk.foreign_table_schema, fk.foreign_table_name
+          )?,
+          TableChange::UniqueConstraintAdded(unique_constraint) => writeln!(
+            f,
+            "  + unique {} ({})",
+            unique_constraint.name,
+            unique_constraint.column_names.join(", ")
+          )?,
+          TableChange::UniqueConstraintRemoved(unique_constraint) => writeln!(
+            f,
+            "  - unique {} ({})",
+            unique_constraint.name,
+            unique_constraint.column_names.join(", ")
+          )?,
+          TableChange::CheckConstraintAdded(check_constraint) => writeln!(
+            f,
+            "  + check {} {}",
+            check_constraint.name, check_constraint.definition
+          )?,
+          TableChange::CheckConstraintRemoved(check_constraint) => writeln!(
+            f,
+            "  - check {} {}",
+            check_constraint.name, check_constraint.definition
+          )?,
+        }
+      }
+    }
+
+    return Ok(());
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn create_column(name: &str, data_type: &str, ordinal_position: i32) -> PsqlTableColumn {
+    return PsqlTableColumn {
+      ordinal_position,
+      ..PsqlTableColumn::new(name, data_type)
+    };
+  }
+
+  fn create_table_by_id(tables: Vec<PsqlTable>) -> HashMap<PsqlTableIdentity, PsqlTable> {
+    return tables
+      .into_iter()
+      .map(|table| (table.id.clone(), table))
+      .collect();
+  }
+
+  mod migration_statements {
+    use super::*;
+
+    #[test]
+    fn it_should_print_the_diff_and_the_migration_of_the_target_schema() {
+      let id_column = create_column("id", "integer", 1);
+      let user_fk = PsqlForeignKey::new(
+        "orders_user_id_fkey",
+        create_column("user_id", "integer", 2),
+        "prod",
+        "users",
+      );
+
+      let mut source_orders = PsqlTable::fixture("orders")
+        .schema("prod")
+        .columns(vec![
+          id_column.clone(),
+          create_column("user_id", "integer", 2),
+          create_column("total", "numeric", 3),
+        ])
+        .referencing_fks(vec![user_fk])
+        .build();
+
+      source_orders.check_constraints.push(PsqlCheckConstraint {
+        name: "orders_total_check".into(),
+        definition: "CHECK ((total >= (0)::numeric))".into(),
+      });
+
+      let source_table_by_id = SchemaDiff::rebase_tables(
+        create_table_by_id(vec![
+          source_orders,
+          PsqlTable::fixture("users")
+            .schema("prod")
+            .columns(vec![id_column.clone()])
+            .build(),
+        ]),
+        "prod",
+        "public",
+      );
+      let target_table_by_id = create_table_by_id(vec![
+        PsqlTable::fixture("orders")
+          .columns(vec![
+            id_column.clone(),
+            PsqlTableColumn {
+              is_nullable: true,
+              ..create_column("user_id", "bigint", 2)
+            },
+            create_column("legacy_code", "text", 3),
+          ])
+          .build(),
+        PsqlTable::fixture("coupons")
+          .columns(vec![id_column])
+          .build(),
+      ]);
+
+      let schema_diff =
+        SchemaDiff::new(DbDriver::Postgres, &source_table_by_id, &target_table_by_id);
+
+      assert_eq!(
+        schema_diff.to_string(),
+        indoc::indoc! {"
+          + table public.users
+          - table public.coupons
+
+          public.orders
+            ~ column user_id: type bigint -> integer, nullable yes -> no
+            + column total numeric
+            - column legacy_code text
+            + foreign key orders_user_id_fkey (user_id) -> public.users
+            + check orders_total_check CHECK ((total >= (0)::numeric))
+        "}
+      );
+
+      assert_eq!(
+        schema_diff.migration_statements(),
+        vec![
+          "CREATE TABLE IF NOT EXISTS \"public\".\"users\" (\n  \"id\" integer NOT NULL,\n  PRIMARY KEY (\"id\")\n);",
+          "ALTER TABLE \"public\".\"orders\" ALTER COLUMN \"user_id\" TYPE integer USING \"user_id\"::integer;",
+          "ALTER TABLE \"public\".\"orders\" ALTER COLUMN \"user_id\" SET NOT NULL;",
+          "ALTER TABLE \"public\".\"orders\" ADD COLUMN \"total\" numeric NOT NULL;",
+          "ALTER TABLE \"public\".\"orders\" ADD CONSTRAINT \"orders_user_id_fkey\" FOREIGN KEY (\"user_id\") REFERENCES \"public\".\"users\" (\"id\");",
+          "ALTER TABLE \"public\".\"orders\" ADD CONSTRAINT \"orders_total_check\" CHECK ((total >= (0)::numeric));",
+          "-- Statements below drop data, run them after a review",
+          "-- ALTER TABLE \"public\".\"orders\" DROP COLUMN \"legacy_code\";",
+          "-- DROP TABLE \"public\".\"coupons\";",
+        ]
+      );
+    }
+
+    #[test]
+    fn it_should_only_create_enum_types_that_are_missing_in_the_target() {
+      let enum_column = |name: &str, enum_type_name: &str, labels: Vec<&str>, position: i32| {
+        return PsqlTableColumn {
+          column_type: Some(enum_type_name.to_owned()),
+          enum_type: Some(PsqlEnumType {
+            schema: "public".into(),
+            name: enum_type_name.into(),
+            labels: labels.into_iter().map(String::from).collect(),
+          }),
+          ..create_column(name, "USER-DEFINED", position)
+        };
+      };
+      let id_column = create_column("id", "integer", 1);
+      let source_status_column = enum_column(
+        "status",
+        "order_status",
+        vec!["draft", "pending", "paid", "refunded"],
+        2,
+      );
+
+      let source_table_by_id = create_table_by_id(vec![
+        PsqlTable::fixture("orders")
+          .columns(vec![
+            id_column.clone(),
+            source_status_column.clone(),
+            enum_column("payment_method", "payment_method", vec!["card", "cash"], 3),
+          ])
+          .build(),
+        PsqlTable::fixture("order_histories")
+          .columns(vec![
+            id_column.clone(),
+            PsqlTableColumn {
+              name: "from_status".into(),
+              ..source_status_column
+            },
+          ])
+          .build(),
+      ]);
+      let target_table_by_id = create_table_by_id(vec![PsqlTable::fixture("orders")
+        .columns(vec![
+          id_column,
+          enum_column("status", "order_status", vec!["pending", "paid", "void"], 2),
+        ])
+        .build()]);
+
+      let schema_diff =
+        SchemaDiff::new(DbDriver::Postgres, &source_table_by_id, &target_table_by_id);
+
+      assert_eq!(
+        schema_diff.to_string(),
+        indoc::indoc! {"
+          + table public.order_histories
+          + type public.payment_method
+          ~ type public.order_status: + label draft, + label refunded, - label void
+
+          public.orders
+            + column payment_method public.payment_method
+        "}
+      );
+
+      assert_eq!(
+        schema_diff.migration_statements(),
+        vec![
+          "CREATE TYPE \"public\".\"payment_method\" AS ENUM ('card', 'cash');",
+          "ALTER TYPE \"public\".\"order_status\" ADD VALUE IF NOT EXISTS 'draft' BEFORE 'pending';",
+          "ALTER TYPE \"public\".\"order_status\" ADD VALUE IF NOT EXISTS 'refunded' AFTER 'paid';",
+          "-- Label 'void' of \"public\".\"order_status\" is not in the source, postgres can't drop enum labels",
+          "CREATE TABLE IF NOT EXISTS \"public\".\"order_histories\" (\n  \"id\" integer NOT NULL,\n  \"from_status\" \"public\".\"order_status\" NOT NULL,\n  PRIMARY KEY (\"id\")\n);",
+          "ALTER TABLE \"public\".\"orders\" ADD COLUMN \"payment_method\" \"public\".\"payment_method\" NOT NULL;",
+        ]
+      );
+    }
+  }
+}
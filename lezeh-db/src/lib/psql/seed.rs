@@ -0,0 +1,608 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
+use fake::faker::address::en::{CityName, CountryName, StreetName, ZipCode};
+use fake::faker::chrono::en::DateTimeBetween;
+use fake::faker::company::en::CompanyName;
+use fake::faker::internet::en::{SafeEmail, Username};
+use fake::faker::lorem::en::{Sentence, Words};
+use fake::faker::name::en::{FirstName, LastName, Name};
+use fake::faker::phone_number::en::PhoneNumber;
+use fake::{Fake, Faker};
+use postgres_types::Type as PsqlType;
+
+use crate::psql::dto::*;
+use crate::psql::table_metadata::TableMetadata;
+use lezeh_common::types::ResultAnyError;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SeedConfig {
+  /// Rows generated for the seeded table
+  pub row_count: usize,
+
+  /// Rows generated for each of the required ancestor tables
+  pub parent_row_count: usize,
+}
+
+/// Generates fake rows for a table and all of the tables it references,
+/// foreign key columns always point to the generated parent rows.
+pub struct SeedRowGenerator<'a> {
+  table_metadata: &'a dyn TableMetadata,
+  psql_table_by_id: &'a HashMap<PsqlTableIdentity, PsqlTable>,
+  config: SeedConfig,
+}
+
+impl<'a> SeedRowGenerator<'a> {
+  pub fn new(
+    table_metadata: &'a dyn TableMetadata,
+    psql_table_by_id: &'a HashMap<PsqlTableIdentity, PsqlTable>,
+    config: SeedConfig,
+  ) -> SeedRowGenerator<'a> {
+    return SeedRowGenerator {
+      table_metadata,
+      psql_table_by_id,
+      config,
+    };
+  }
+
+  /// Generated rows keyed by level, the seeded table is on level 0
+  /// and its parents are on the lower levels.
+  pub fn generate(
+    &self,
+    table_id: &PsqlTableIdentity,
+  ) -> ResultAnyError<HashMap<i32, Vec<Rc<PsqlTableRow>>>> {
+    let mut level_by_table_id: HashMap<PsqlTableIdentity, i32> = Default::default();
+    let mut cyclic_fk_column_names: HashSet<(PsqlTableIdentity, String)> = Default::default();
+
+    self.fill_table_levels(
+      table_id,
+      0,
+      &mut vec![],
+      &mut level_by_table_id,
+      &mut cyclic_fk_column_names,
+    )?;
+
+    let mut table_ids: Vec<&PsqlTableIdentity> = level_by_table_id.keys().collect();
+
+    table_ids.sort_by_key(|table_id| (level_by_table_id[table_id], table_id.to_string()));
+
+    let mut rows_by_table_id: HashMap<PsqlTableIdentity, Vec<Rc<PsqlTableRow>>> =
+      Default::default();
+    let mut rows_by_level: HashMap<i32, Vec<Rc<PsqlTableRow>>> = Default::default();
+
+    // Parents are generated first so the children can reference them
+    for current_table_id in table_ids {
+      let table = &self.psql_table_by_id[current_table_id];
+      let row_count = if current_table_id == table_id {
+        self.config.row_count
+      } else {
+        self.config.parent_row_count
+      };
+
+      let rows =
+        self.generate_table_rows(table, row_count, &rows_by_table_id, &cyclic_fk_column_names)?;
+
+      rows_by_level
+        .entry(level_by_table_id[current_table_id])
+        .or_default()
+        .extend(rows.iter().cloned());
+
+      rows_by_table_id.insert(current_table_id.clone(), rows);
+    }
+
+    return Ok(rows_by_level);
+  }
+
+  /// Parents are placed below the lowest level of their children,
+  /// foreign keys that point back to a table in the current path are marked as cyclic.
+  fn fill_table_levels(
+    &self,
+    table_id: &PsqlTableIdentity,
+    level: i32,
+    table_id_path: &mut Vec<PsqlTableIdentity>,
+    level_by_table_id: &mut HashMap<PsqlTableIdentity, i32>,
+    cyclic_fk_column_names: &mut HashSet<(PsqlTableIdentity, String)>,
+  ) -> ResultAnyError<()> {
+    let table = self
+      .psql_table_by_id
+      .get(table_id)
+      .ok_or_else(|| anyhow!("Table {} not found", table_id))?;
+
+    // The parents are already placed below a level that is at least as low,
+    // walking them again can't lower them, this keeps shared parents from being walked per path.
+    if let Some(visited_level) = level_by_table_id.get(table_id) {
+      if *visited_level <= level {
+        return Ok(());
+      }
+    }
+
+    level_by_table_id.insert(table_id.clone(), level);
+
+    table_id_path.push(table_id.clone());
+
+    for fk in table.referencing_fk_by_constraint_name.values() {
+      let foreign_table_id = PsqlTableIdentity::new(
+        fk.foreign_table_schema.clone(),
+        fk.foreign_table_name.clone(),
+      );
+
+      if table_id_path.contains(&foreign_table_id) {
+        cyclic_fk_column_names.insert((table_id.clone(), fk.column.name.clone()));
+        continue;
+      }
+
+      self.fill_table_levels(
+        &foreign_table_id,
+        level - 1,
+        table_id_path,
+        level_by_table_id,
+        cyclic_fk_column_names,
+      )?;
+    }
+
+    table_id_path.pop();
+
+    return Ok(());
+  }
+
+  fn generate_table_rows(
+    &self,
+    table: &PsqlTable,
+    row_count: usize,
+    rows_by_table_id: &HashMap<PsqlTableIdentity, Vec<Rc<PsqlTableRow>>>,
+    cyclic_fk_column_names: &HashSet<(PsqlTableIdentity, String)>,
+  ) -> ResultAnyError<Vec<Rc<PsqlTableRow>>> {
+    let fk_by_column_name: HashMap<&str, &PsqlForeignKey> = table
+      .referencing_fk_by_constraint_name
+      .values()
+      .map(|fk| (fk.column.name.as_str(), fk))
+      .collect();
+
+    let mut columns: Vec<&PsqlTableColumn> = table
+      .columns
+      .iter()
+      .filter(|column| !column.is_generated && column.name != table.primary_column.name)
+      .collect();
+
+    columns.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let first_id: i64 = if SeedRowGenerator::is_integer_type(&table.primary_column.data_type) {
+      self
+        .table_metadata
+        .get_max_id(table, &table.primary_column.name)?
+        .unwrap_or_default()
+        + 1
+    } else {
+      0
+    };
+
+    return (0..row_count)
+      .map(|index| {
+        let id = SeedRowGenerator::fake_id(&table.primary_column, first_id + index as i64)?;
+        let mut values: PsqlRowValues = vec![(table.primary_column.name.clone(), id.clone())];
+
+        for column in columns.iter() {
+          let is_cyclic = cyclic_fk_column_names.contains(&(table.id.clone(), column.name.clone()));
+
+          let value = match fk_by_column_name.get(column.name.as_str()) {
+            Some(_) if is_cyclic && column.is_nullable => Some(FromSqlSink::null()),
+            // The row can reference itself since its id is generated up front
+            Some(fk)
+              if is_cyclic
+                && fk.foreign_table_schema == table.id.schema
+                && fk.foreign_table_name == table.id.name =>
+            {
+              Some(id.clone())
+            }
+            Some(fk) if is_cyclic => {
+              return Err(anyhow!(
+                "Could not seed {}.{}, it's a not null foreign key to {}.{} in a foreign key cycle",
+                table.id,
+                column.name,
+                fk.foreign_table_schema,
+                fk.foreign_table_name
+              ));
+            }
+            Some(fk) => Some(SeedRowGenerator::pick_parent_id(fk, rows_by_table_id)?),
+            None => match SeedRowGenerator::fake_value(column)? {
+              Some(value) => Some(value),
+              // Let the db fill it when we don't know how to fake it
+              None if column.default_value.is_some() => None,
+              None if column.is_nullable => Some(FromSqlSink::null()),
+              None => {
+                return Err(anyhow!(
+                  "Could not seed {}.{}, its type {} is not supported and it's not null without a default value",
+                  table.id,
+                  column.name,
+                  column.data_type
+                ));
+              }
+            },
+          };
+
+          if let Some(value) = value {
+            values.push((column.name.clone(), value));
+          }
+        }
+
+        return PsqlTableRow::new(table.clone(), values).map(Rc::new);
+      })
+      .collect();
+  }
+
+  fn pick_parent_id(
+    fk: &PsqlForeignKey,
+    rows_by_table_id: &HashMap<PsqlTableIdentity, Vec<Rc<PsqlTableRow>>>,
+  ) -> ResultAnyError<FromSqlSink> {
+    let foreign_table_id = PsqlTableIdentity::new(
+      fk.foreign_table_schema.clone(),
+      fk.foreign_table_name.clone(),
+    );
+
+    let parent_rows = rows_by_table_id
+      .get(&foreign_table_id)
+      .filter(|parent_rows| !parent_rows.is_empty())
+      .ok_or_else(|| anyhow!("No generated rows of {} to reference", foreign_table_id))?;
+
+    let parent_row = &parent_rows[(0..parent_rows.len()).fake::<usize>()];
+
+    return parent_row.get_id(&parent_row.table.primary_column).cloned();
+  }
+
+  fn is_integer_type(data_type: &str) -> bool {
+    return matches!(
+      data_type.to_lowercase().as_str(),
+      "smallint" | "integer" | "bigint" | "tinyint" | "mediumint" | "int"
+    );
+  }
+
+  fn fake_id(column: &PsqlTableColumn, id: i64) -> ResultAnyError<FromSqlSink> {
+    let data_type = column.data_type.to_lowercase();
+
+    if SeedRowGenerator::is_integer_type(&data_type) {
+      return FromSqlSink::from_value(&id, PsqlType::INT8);
+    }
+
+    let uuid = uuid::Uuid::from_u128(Faker.fake::<u128>());
+
+    if data_type == "uuid" {
+      return Ok(FromSqlSink::from_raw(
+        uuid.as_bytes().to_vec(),
+        PsqlType::UUID,
+      ));
+    }
+
+    return FromSqlSink::from_value(&uuid.to_string(), PsqlType::TEXT);
+  }
+
+  /// Fake value based on the column type and name, None if the type is not supported
+  pub fn fake_value(column: &PsqlTableColumn) -> ResultAnyError<Option<FromSqlSink>> {
+    let now: DateTime<Utc> = Utc::now();
+    let fake_date_time: DateTime<Utc> = DateTimeBetween(now - Duration::days(365), now).fake();
+
+    let value = match column.data_type.to_lowercase().as_str() {
+      "boolean" | "bool" => FromSqlSink::from_value(&Faker.fake::<bool>(), PsqlType::BOOL),
+      "tinyint" => FromSqlSink::from_value(&(0..2).fake::<i16>(), PsqlType::INT2),
+      "smallint" => FromSqlSink::from_value(&(1..1000).fake::<i16>(), PsqlType::INT2),
+      "integer" | "int" | "mediumint" => {
+        FromSqlSink::from_value(&(1..100_000).fake::<i32>(), PsqlType::INT4)
+      }
+      "bigint" => FromSqlSink::from_value(&(1..1_000_000).fake::<i64>(), PsqlType::INT8),
+      "numeric" | "decimal" => FromSqlSink::from_value(
+        &rust_decimal::Decimal::new((0..1_000_000).fake::<i64>(), 2),
+        PsqlType::NUMERIC,
+      ),
+      "real" | "float" => FromSqlSink::from_value(&(0.0..1000.0).fake::<f32>(), PsqlType::FLOAT4),
+      "double precision" | "double" => {
+        FromSqlSink::from_value(&(0.0..1000.0).fake::<f64>(), PsqlType::FLOAT8)
+      }
+      "date" => FromSqlSink::from_value(&fake_date_time.naive_utc().date(), PsqlType::DATE),
+      "time" | "time without time zone" => {
+        FromSqlSink::from_value(&fake_date_time.naive_utc().time(), PsqlType::TIME)
+      }
+      "timestamp" | "timestamp without time zone" | "datetime" => {
+        FromSqlSink::from_value(&fake_date_time.naive_utc(), PsqlType::TIMESTAMP)
+      }
+      "timestamp with time zone" => FromSqlSink::from_value(&fake_date_time, PsqlType::TIMESTAMPTZ),
+      "uuid" => Ok(FromSqlSink::from_raw(
+        uuid::Uuid::from_u128(Faker.fake::<u128>())
+          .as_bytes()
+          .to_vec(),
+        PsqlType::UUID,
+      )),
+      "json" | "jsonb" => Ok(FromSqlSink::from_raw(b"{}".to_vec(), PsqlType::JSON)),
+      // Length is unknown, a single character is the safest
+      "character" | "char" => FromSqlSink::from_value(
+        &char::from((b'a'..=b'z').fake::<u8>()).to_string(),
+        PsqlType::BPCHAR,
+      ),
+      "text" | "character varying" | "varchar" | "citext" | "tinytext" | "mediumtext"
+      | "longtext" => {
+        FromSqlSink::from_value(&SeedRowGenerator::fake_text(&column.name), PsqlType::TEXT)
+      }
+      _ => return Ok(None),
+    };
+
+    return value.map(Some);
+  }
+
+  fn fake_text(column_name: &str) -> String {
+    let column_name = column_name.to_lowercase();
+
+    if column_name.contains("email") {
+      return SafeEmail().fake();
+    }
+
+    if column_name.contains("username") {
+      return Username().fake();
+    }
+
+    if column_name.contains("first_name") {
+      return FirstName().fake();
+    }
+
+    if column_name.contains("last_name") {
+      return LastName().fake();
+    }
+
+    if column_name.contains("company") {
+      return CompanyName().fake();
+    }
+
+    if column_name.contains("name") {
+      return Name().fake();
+    }
+
+    if column_name.contains("phone") {
+      return PhoneNumber().fake();
+    }
+
+    if column_name.contains("city") {
+      return CityName().fake();
+    }
+
+    if column_name.contains("country") {
+      return CountryName().fake();
+    }
+
+    if column_name.contains("address") || column_name.contains("street") {
+      return StreetName().fake();
+    }
+
+    if column_name.contains("zip") || column_name.contains("postal") {
+      return ZipCode().fake();
+    }
+
+    if column_name.contains("description") || column_name.contains("note") {
+      return Sentence(3..10).fake();
+    }
+
+    return Words(1..3).fake::<Vec<String>>().join(" ");
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::table_metadata::MockTableMetadata;
+
+  fn create_table_by_id() -> HashMap<PsqlTableIdentity, PsqlTable> {
+    let users = PsqlTable::fixture("users")
+      .columns(vec![
+        PsqlTableColumn::new("id", "integer"),
+        PsqlTableColumn::new("email", "character varying"),
+        PsqlTableColumn {
+          is_nullable: true,
+          ..PsqlTableColumn::new("manager_id", "integer")
+        },
+      ])
+      .references("manager_id", "users")
+      .build();
+
+    let orders = PsqlTable::fixture("orders")
+      .columns(vec![
+        PsqlTableColumn::new("id", "integer"),
+        PsqlTableColumn::new("user_id", "integer"),
+        PsqlTableColumn::new("total", "numeric"),
+        PsqlTableColumn::new("created_at", "timestamp with time zone"),
+      ])
+      .references("user_id", "users")
+      .build();
+
+    return vec![users, orders]
+      .into_iter()
+      .map(|table| (table.id.clone(), table))
+      .collect();
+  }
+
+  mod generate {
+    use super::*;
+
+    #[test]
+    fn it_should_generate_rows_that_reference_generated_parents() -> ResultAnyError<()> {
+      let table_by_id = create_table_by_id();
+      let mut table_metadata = MockTableMetadata::new();
+
+      table_metadata
+        .expect_get_max_id()
+        .returning(|table, _column_name| {
+          return Ok(if table.id.name == "users" {
+            Some(41)
+          } else {
+            None
+          });
+        });
+
+      let generator = SeedRowGenerator::new(
+        &table_metadata,
+        &table_by_id,
+        SeedConfig {
+          row_count: 3,
+          parent_row_count: 2,
+        },
+      );
+
+      let mut rows_by_level = generator.generate(&PsqlTableIdentity::new("public", "orders"))?;
+      let orders = rows_by_level.remove(&0).unwrap();
+      let users = rows_by_level.remove(&-1).unwrap();
+
+      assert!(rows_by_level.is_empty());
+      assert_eq!(
+        users
+          .iter()
+          .map(|row| row.row_id_representation.as_str())
+          .collect::<Vec<&str>>(),
+        vec!["42", "43"]
+      );
+      assert_eq!(
+        orders
+          .iter()
+          .map(|row| row.row_id_representation.as_str())
+          .collect::<Vec<&str>>(),
+        vec!["1", "2", "3"]
+      );
+
+      for user in users.iter() {
+        // Self reference is cyclic so it's left empty
+        assert_eq!(user.get_value("manager_id").unwrap().get_type(), None);
+        assert!(user
+          .get_value("email")
+          .unwrap()
+          .to_text()?
+          .unwrap()
+          .contains('@'));
+      }
+
+      for order in orders.iter() {
+        let user_id = order.get_value("user_id").unwrap().to_text()?.unwrap();
+
+        assert!(["42", "43"].contains(&user_id.as_str()));
+        assert!(order.get_value("total").is_some());
+        assert!(order.get_value("created_at").is_some());
+      }
+
+      return Ok(());
+    }
+
+    fn generate(
+      table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+      table_name: &str,
+    ) -> ResultAnyError<HashMap<i32, Vec<Rc<PsqlTableRow>>>> {
+      let mut table_metadata = MockTableMetadata::new();
+
+      table_metadata
+        .expect_get_max_id()
+        .returning(|_table, _column_name| Ok(None));
+
+      return SeedRowGenerator::new(
+        &table_metadata,
+        table_by_id,
+        SeedConfig {
+          row_count: 2,
+          parent_row_count: 1,
+        },
+      )
+      .generate(&PsqlTableIdentity::new("public", table_name));
+    }
+
+    fn to_table_by_id(tables: Vec<PsqlTable>) -> HashMap<PsqlTableIdentity, PsqlTable> {
+      return tables
+        .into_iter()
+        .map(|table| (table.id.clone(), table))
+        .collect();
+    }
+
+    #[test]
+    fn it_should_reference_the_row_itself_in_a_not_null_self_reference() -> ResultAnyError<()> {
+      let table_by_id = to_table_by_id(vec![PsqlTable::fixture("categories")
+        .columns(vec![PsqlTableColumn::new("parent_id", "integer")])
+        .references("parent_id", "categories")
+        .build()]);
+
+      let mut rows_by_level = generate(&table_by_id, "categories")?;
+
+      for category in rows_by_level.remove(&0).unwrap() {
+        assert_eq!(
+          category.get_value("parent_id").unwrap().to_text()?,
+          Some(category.row_id_representation.clone())
+        );
+      }
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_fail_on_a_not_null_foreign_key_cycle() {
+      let table_by_id = to_table_by_id(vec![
+        PsqlTable::fixture("users")
+          .columns(vec![PsqlTableColumn::new("team_id", "integer")])
+          .references("team_id", "teams")
+          .build(),
+        PsqlTable::fixture("teams")
+          .columns(vec![PsqlTableColumn::new("owner_id", "integer")])
+          .references("owner_id", "users")
+          .build(),
+      ]);
+
+      assert_eq!(
+        generate(&table_by_id, "users").unwrap_err().to_string(),
+        "Could not seed public.teams.owner_id, it's a not null foreign key to public.users in a foreign key cycle"
+      );
+    }
+
+    #[test]
+    fn it_should_fail_on_a_not_null_column_of_unsupported_type() {
+      let table_by_id = to_table_by_id(vec![PsqlTable::fixture("documents")
+        .columns(vec![
+          PsqlTableColumn::new("search", "tsvector"),
+          PsqlTableColumn {
+            default_value: Some("'{}'::int4range".into()),
+            ..PsqlTableColumn::new("pages", "int4range")
+          },
+        ])
+        .build()]);
+
+      assert_eq!(
+        generate(&table_by_id, "documents").unwrap_err().to_string(),
+        "Could not seed public.documents.search, its type tsvector is not supported and it's not null without a default value"
+      );
+    }
+
+    #[test]
+    fn it_should_place_shared_parents_once_below_all_their_children() -> ResultAnyError<()> {
+      // Every table references all the tables before it, walking each path would never end
+      let table_count = 40;
+      let table_by_id = to_table_by_id(
+        (0..table_count)
+          .map(|index| {
+            return (0..index).fold(
+              PsqlTable::fixture(format!("t{}", index).as_str()),
+              |table, parent_index| {
+                return table.references(
+                  format!("t{}_id", parent_index).as_str(),
+                  format!("t{}", parent_index).as_str(),
+                );
+              },
+            );
+          })
+          .map(|table| table.build())
+          .collect(),
+      );
+
+      let rows_by_level = generate(&table_by_id, format!("t{}", table_count - 1).as_str())?;
+
+      assert_eq!(rows_by_level.len(), table_count);
+
+      for (level, rows) in rows_by_level {
+        assert_eq!(
+          rows[0].table.id.name,
+          format!("t{}", table_count as i32 - 1 + level)
+        );
+      }
+
+      return Ok(());
+    }
+  }
+}
@@ -0,0 +1,786 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::config::DbDriver;
+use crate::psql::dto::{PsqlTable, PsqlTableIdentity, PsqlTableRow};
+use crate::psql::relation_insert::{InsertStatementConfig, RelationInsert};
+use crate::psql::statement_executor::{StatementError, StatementExecutor};
+use lezeh_common::types::ResultAnyError;
+
+const TRANSACTION_STATEMENTS: [&str; 4] = ["BEGIN", "COMMIT", "ROLLBACK", "START TRANSACTION"];
+
+/// Postgres settings that only last until the end of the current transaction
+const TRANSACTION_SETTING_PREFIXES: [&str; 2] = ["SET LOCAL ", "SET CONSTRAINTS "];
+const MAX_LABEL_LENGTH: usize = 80;
+
+/// Statement that is applied on its own so one failure does not abort the rest
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApplyStatement {
+  /// Row or statement representation used in the report, e.g. `public.users 1`
+  pub label: String,
+  pub statement: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApplyOutcome {
+  Applied,
+
+  /// The primary key is already taken in the target db
+  AlreadyExisted,
+  Conflicted {
+    constraint_name: String,
+  },
+  Failed {
+    message: String,
+  },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SqlCharKind {
+  Code,
+  Quoted,
+  Comment,
+}
+
+/// Sql text of an `INSERT INTO .. VALUES` statement split per row
+struct InsertStatementRows {
+  /// Everything before the first row, e.g. `insert into public.users ("id") VALUES`
+  header: String,
+  table_name: String,
+  column_names: Vec<String>,
+  rows: Vec<Vec<String>>,
+}
+
+impl ApplyStatement {
+  /// 1 statement per row, tables are ordered by their dependency
+  /// and followed by the sequence resync on postgres.
+  pub fn from_rows(
+    rows: Vec<PsqlTableRow>,
+    config: &InsertStatementConfig,
+  ) -> ResultAnyError<Vec<ApplyStatement>> {
+    let rows: Vec<Rc<PsqlTableRow>> = rows.into_iter().map(Rc::new).collect();
+    let rows_by_table_id: HashMap<&PsqlTableIdentity, Vec<&Rc<PsqlTableRow>>> =
+      rows.iter().map(|row| (&row.table.id, row)).into_group_map();
+    let tables: Vec<&PsqlTable> = rows_by_table_id
+      .values()
+      .map(|rows| &rows[0].table)
+      .collect();
+    let mut statements: Vec<ApplyStatement> = vec![];
+
+    for table in PsqlTable::dependency_ordered(tables.iter().cloned()) {
+      for row in rows_by_table_id[&table.id].iter() {
+        statements.push(ApplyStatement {
+          label: row.to_string(),
+          statement: RelationInsert::table_row_into_insert_statement(table, &vec![*row], config)?,
+        });
+      }
+    }
+
+    if let Some(statement) =
+      RelationInsert::into_sequence_resync_statement(tables.into_iter(), config)
+    {
+      statements.push(ApplyStatement {
+        label: "sequence resync".into(),
+        statement,
+      });
+    }
+
+    return Ok(statements);
+  }
+
+  /// Split a sql script, e.g. the output of cherry-pick, into statements.
+  /// Multi rows inserts are split into 1 statement per row, labeled with the primary key
+  /// when the table is found in `table_by_id`, the rows of these tables are reordered
+  /// by their dependency. Transaction statements are skipped since every statement
+  /// is applied on its own, postgres `SET LOCAL` and `SET CONSTRAINTS` only last for
+  /// a transaction so the statements after them run in their own transaction with them.
+  /// `COPY ... FROM stdin` reads its rows from the client, these scripts are rejected.
+  pub fn from_sql_script(
+    driver: DbDriver,
+    script: &str,
+    table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+  ) -> ResultAnyError<Vec<ApplyStatement>> {
+    let mut transaction_settings: Vec<String> = vec![];
+    let mut statements: Vec<(Option<&PsqlTable>, ApplyStatement)> = vec![];
+
+    for (line_number, statement) in ApplyStatement::split_statements(driver, script) {
+      if TRANSACTION_STATEMENTS
+        .iter()
+        .any(|transaction_statement| statement.eq_ignore_ascii_case(transaction_statement))
+      {
+        continue;
+      }
+
+      let upper_statement = statement.to_uppercase();
+
+      if upper_statement.starts_with("COPY ") && upper_statement.contains(" FROM STDIN") {
+        return Err(anyhow!(
+          "Line {}: COPY scripts must be loaded with psql -f",
+          line_number
+        ));
+      }
+
+      let is_transaction_setting = TRANSACTION_SETTING_PREFIXES.iter().any(|prefix| {
+        return statement
+          .get(..prefix.len())
+          .map(|statement_prefix| statement_prefix.eq_ignore_ascii_case(prefix))
+          .unwrap_or(false);
+      });
+
+      if driver == DbDriver::Postgres && is_transaction_setting {
+        transaction_settings.push(statement);
+
+        continue;
+      }
+
+      let table_statements: Vec<(Option<&PsqlTable>, ApplyStatement)> =
+        match ApplyStatement::parse_insert(driver, &statement) {
+          Some(insert_statement_rows) => ApplyStatement::from_insert_statement_rows(
+            line_number,
+            insert_statement_rows,
+            table_by_id,
+          ),
+          None => {
+            let first_line: String = statement
+              .lines()
+              .next()
+              .unwrap_or_default()
+              .chars()
+              .take(MAX_LABEL_LENGTH)
+              .collect();
+
+            vec![(
+              None,
+              ApplyStatement {
+                label: format!("line {}: {}", line_number, first_line),
+                statement,
+              },
+            )]
+          }
+        };
+
+      statements.extend(
+        table_statements
+          .into_iter()
+          .map(|(table, apply_statement)| {
+            if transaction_settings.is_empty() {
+              return (table, apply_statement);
+            }
+
+            let statement = format!(
+              "BEGIN;\n{};\n{};\nCOMMIT;",
+              transaction_settings.join(";\n"),
+              apply_statement.statement
+            );
+
+            return (
+              table,
+              ApplyStatement {
+                statement,
+                ..apply_statement
+              },
+            );
+          }),
+      );
+    }
+
+    return Ok(ApplyStatement::dependency_ordered(statements));
+  }
+
+  /// Rows of known tables are moved so parents come before their children,
+  /// they keep their order within a table and other statements keep their place.
+  fn dependency_ordered(
+    statements: Vec<(Option<&PsqlTable>, ApplyStatement)>,
+  ) -> Vec<ApplyStatement> {
+    let table_by_id: HashMap<&PsqlTableIdentity, &PsqlTable> = statements
+      .iter()
+      .filter_map(|(table, _)| *table)
+      .map(|table| (&table.id, table))
+      .collect();
+    let rank_by_table_id: HashMap<&PsqlTableIdentity, usize> =
+      PsqlTable::dependency_ordered(table_by_id.values().cloned())
+        .into_iter()
+        .enumerate()
+        .map(|(rank, table)| (&table.id, rank))
+        .collect();
+
+    let mut row_positions: Vec<usize> = vec![];
+    let mut ranked_rows: Vec<(usize, ApplyStatement)> = vec![];
+    let mut slots: Vec<Option<ApplyStatement>> = vec![];
+
+    for (position, (table, apply_statement)) in statements.into_iter().enumerate() {
+      match table {
+        Some(table) => {
+          row_positions.push(position);
+          ranked_rows.push((rank_by_table_id[&table.id], apply_statement));
+          slots.push(None);
+        }
+        None => slots.push(Some(apply_statement)),
+      }
+    }
+
+    ranked_rows.sort_by_key(|(rank, _)| *rank);
+
+    for (position, (_, apply_statement)) in row_positions.into_iter().zip(ranked_rows) {
+      slots[position] = Some(apply_statement);
+    }
+
+    return slots.into_iter().flatten().collect();
+  }
+
+  fn from_insert_statement_rows(
+    line_number: usize,
+    insert_statement_rows: InsertStatementRows,
+    table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+  ) -> Vec<(Option<&PsqlTable>, ApplyStatement)> {
+    let InsertStatementRows {
+      header,
+      table_name,
+      column_names,
+      rows,
+    } = insert_statement_rows;
+
+    let table: Option<&PsqlTable> = table_name
+      .split_once('.')
+      .and_then(|(schema, name)| table_by_id.get(&PsqlTableIdentity::new(schema, name)));
+    let primary_column_index: Option<usize> = table.and_then(|table| {
+      return column_names
+        .iter()
+        .position(|column_name| *column_name == table.primary_column.name);
+    });
+
+    return rows
+      .into_iter()
+      .enumerate()
+      .map(|(row_index, values)| {
+        let label = match primary_column_index.and_then(|index| values.get(index)) {
+          Some(id) => format!(
+            "{} {}",
+            table_name,
+            id.trim().trim_start_matches('E').trim_matches('\'')
+          ),
+          None => format!(
+            "{} (line {}, row {})",
+            table_name,
+            line_number,
+            row_index + 1
+          ),
+        };
+
+        return (
+          table,
+          ApplyStatement {
+            label,
+            statement: format!("{}\n  ({})", header, values.join(", ")),
+          },
+        );
+      })
+      .collect();
+  }
+
+  /// Classify every char so statements and values are only split outside of
+  /// quoted literals, quoted identifiers and comments.
+  fn classify_chars(driver: DbDriver, sql: &str) -> Vec<(usize, char, SqlCharKind)> {
+    let chars: Vec<(usize, char)> = sql.char_indices().collect();
+    let mut classified_chars: Vec<(usize, char, SqlCharKind)> = Vec::with_capacity(chars.len());
+    let mut index = 0;
+
+    while index < chars.len() {
+      let (offset, char) = chars[index];
+      let next_char = chars.get(index + 1).map(|(_, char)| *char);
+
+      if char == '-' && next_char == Some('-') {
+        while index < chars.len() && chars[index].1 != '\n' {
+          classified_chars.push((chars[index].0, chars[index].1, SqlCharKind::Comment));
+          index += 1;
+        }
+
+        continue;
+      }
+
+      if char != '\'' && char != '"' && char != '`' {
+        classified_chars.push((offset, char, SqlCharKind::Code));
+        index += 1;
+
+        continue;
+      }
+
+      // Postgres only treats backslash as escape in E'' literals
+      let is_backslash_escaped = char == '\''
+        && (driver == DbDriver::Mysql
+          || index
+            .checked_sub(1)
+            .map(|previous_index| chars[previous_index].1.eq_ignore_ascii_case(&'e'))
+            .unwrap_or(false));
+
+      classified_chars.push((offset, char, SqlCharKind::Quoted));
+      index += 1;
+
+      while index < chars.len() {
+        let (quoted_offset, quoted_char) = chars[index];
+
+        classified_chars.push((quoted_offset, quoted_char, SqlCharKind::Quoted));
+        index += 1;
+
+        if is_backslash_escaped && quoted_char == '\\' {
+          if let Some((escaped_offset, escaped_char)) = chars.get(index) {
+            classified_chars.push((*escaped_offset, *escaped_char, SqlCharKind::Quoted));
+            index += 1;
+          }
+
+          continue;
+        }
+
+        if quoted_char != char {
+          continue;
+        }
+
+        // Doubled quote is an escaped quote
+        match chars.get(index) {
+          Some((escaped_offset, escaped_char)) if *escaped_char == char => {
+            classified_chars.push((*escaped_offset, *escaped_char, SqlCharKind::Quoted));
+            index += 1;
+          }
+          _ => break,
+        }
+      }
+    }
+
+    return classified_chars;
+  }
+
+  /// Statements without comments and the trailing semicolon, paired with their line number
+  fn split_statements(driver: DbDriver, script: &str) -> Vec<(usize, String)> {
+    let mut statements: Vec<(usize, String)> = vec![];
+    let mut statement = String::new();
+    let mut statement_line_number: Option<usize> = None;
+    let mut line_number = 1;
+
+    for (_, char, kind) in ApplyStatement::classify_chars(driver, script) {
+      if kind == SqlCharKind::Comment {
+        continue;
+      }
+
+      if kind == SqlCharKind::Code && char == ';' {
+        if let Some(statement_line_number) = statement_line_number.take() {
+          statements.push((statement_line_number, statement.trim().to_owned()));
+        }
+
+        statement.clear();
+      } else {
+        if statement_line_number.is_none() && !char.is_whitespace() {
+          statement_line_number = Some(line_number);
+        }
+
+        statement.push(char);
+      }
+
+      if char == '\n' {
+        line_number += 1;
+      }
+    }
+
+    if let Some(statement_line_number) = statement_line_number {
+      statements.push((statement_line_number, statement.trim().to_owned()));
+    }
+
+    return statements;
+  }
+
+  /// Split by commas that are not nested in parentheses
+  fn split_values(driver: DbDriver, sql: &str) -> Vec<String> {
+    let mut values: Vec<String> = vec![];
+    let mut value_start = 0;
+    let mut depth = 0;
+
+    for (offset, char, kind) in ApplyStatement::classify_chars(driver, sql) {
+      match (kind, char) {
+        (SqlCharKind::Code, '(') => depth += 1,
+        (SqlCharKind::Code, ')') => depth -= 1,
+        (SqlCharKind::Code, ',') if depth == 0 => {
+          values.push(sql[value_start..offset].trim().to_owned());
+          value_start = offset + 1;
+        }
+        _ => {}
+      }
+    }
+
+    values.push(sql[value_start..].trim().to_owned());
+
+    return values;
+  }
+
+  /// None when the statement is not a plain `INSERT INTO .. (..) VALUES (..), (..)`
+  fn parse_insert(driver: DbDriver, statement: &str) -> Option<InsertStatementRows> {
+    const INSERT_INTO: &str = "insert into";
+
+    if !statement
+      .get(..INSERT_INTO.len())?
+      .eq_ignore_ascii_case(INSERT_INTO)
+    {
+      return None;
+    }
+
+    // Byte ranges of the top level parentheses content
+    let mut groups: Vec<(usize, usize)> = vec![];
+    let mut group_start = 0;
+    let mut depth = 0;
+
+    for (offset, char, kind) in ApplyStatement::classify_chars(driver, statement) {
+      match (kind, char) {
+        (SqlCharKind::Code, '(') => {
+          if depth == 0 {
+            group_start = offset + 1;
+          }
+
+          depth += 1;
+        }
+        (SqlCharKind::Code, ')') => {
+          depth -= 1;
+
+          if depth == 0 {
+            groups.push((group_start, offset));
+          }
+        }
+        _ => {}
+      }
+    }
+
+    if groups.len() < 2 {
+      return None;
+    }
+
+    let header = statement[..groups[1].0 - 1].trim_end();
+    let is_values_header = header
+      .get(header.len().checked_sub("values".len())?..)
+      .map(|keyword| keyword.eq_ignore_ascii_case("values"))
+      .unwrap_or(false);
+
+    // Anything else than commas between the rows, e.g. ON CONFLICT, can't be split
+    let is_row_list = groups.windows(2).skip(1).all(|groups| {
+      return statement[groups[0].1 + 1..groups[1].0 - 1].trim() == ",";
+    }) && statement[groups[groups.len() - 1].1 + 1..]
+      .trim()
+      .is_empty();
+
+    if !is_values_header || !is_row_list {
+      return None;
+    }
+
+    let unquote = |identifier: &str| -> String {
+      return identifier
+        .chars()
+        .filter(|char| *char != '"' && *char != '`')
+        .collect();
+    };
+
+    return Some(InsertStatementRows {
+      header: header.to_owned(),
+      table_name: unquote(statement[INSERT_INTO.len()..groups[0].0 - 1].trim()),
+      column_names: ApplyStatement::split_values(driver, &statement[groups[0].0..groups[0].1])
+        .iter()
+        .map(|column_name| unquote(column_name))
+        .collect(),
+      rows: groups[1..]
+        .iter()
+        .map(|(start, end)| ApplyStatement::split_values(driver, &statement[*start..*end]))
+        .collect(),
+    });
+  }
+}
+
+/// Outcome of every applied statement
+pub struct ApplyReport {
+  pub outcomes: Vec<(String, ApplyOutcome)>,
+}
+
+impl ApplyReport {
+  pub fn failed_count(&self) -> usize {
+    return self
+      .outcomes
+      .iter()
+      .filter(|(_, outcome)| matches!(outcome, ApplyOutcome::Failed { .. }))
+      .count();
+  }
+
+  fn labels<'a>(
+    &'a self,
+    describe: impl Fn(&'a str, &'a ApplyOutcome) -> Option<String> + 'a,
+  ) -> Vec<String> {
+    return self
+      .outcomes
+      .iter()
+      .filter_map(|(label, outcome)| describe(label, outcome))
+      .collect();
+  }
+}
+
+impl std::fmt::Display for ApplyReport {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let applied_labels = self.labels(|label, outcome| {
+      return matches!(outcome, ApplyOutcome::Applied).then(|| label.to_owned());
+    });
+    let already_existed_labels = self.labels(|label, outcome| {
+      return matches!(outcome, ApplyOutcome::AlreadyExisted).then(|| label.to_owned());
+    });
+    let conflicted_labels = self.labels(|label, outcome| {
+      return match outcome {
+        ApplyOutcome::Conflicted { constraint_name } => {
+          Some(format!("{} on {}", label, constraint_name))
+        }
+        _ => None,
+      };
+    });
+    let failed_labels = self.labels(|label, outcome| {
+      return match outcome {
+        ApplyOutcome::Failed { message } => {
+          Some(format!("{}: {}", label, message.replace('\n', " ")))
+        }
+        _ => None,
+      };
+    });
+
+    writeln!(
+      f,
+      "{} applied, {} already existed, {} conflicted, {} failed",
+      applied_labels.len(),
+      already_existed_labels.len(),
+      conflicted_labels.len(),
+      failed_labels.len()
+    )?;
+
+    for (title, labels) in [
+      ("Already existed", already_existed_labels),
+      ("Conflicted", conflicted_labels),
+      ("Failed", failed_labels),
+    ] {
+      if labels.is_empty() {
+        continue;
+      }
+
+      writeln!(f, "{}:", title)?;
+
+      for label in labels {
+        writeln!(f, "  {}", label)?;
+      }
+    }
+
+    return Ok(());
+  }
+}
+
+pub struct StatementApply<'a> {
+  executor: &'a dyn StatementExecutor,
+}
+
+impl<'a> StatementApply<'a> {
+  pub fn new(executor: &'a dyn StatementExecutor) -> StatementApply<'a> {
+    return StatementApply { executor };
+  }
+
+  /// Apply every statement in order, failures are collected instead of aborting
+  pub fn apply(&self, statements: Vec<ApplyStatement>) -> ApplyReport {
+    let outcomes = statements
+      .into_iter()
+      .map(|apply_statement| {
+        let outcome = match self.executor.execute(&apply_statement.statement) {
+          Ok(_) => ApplyOutcome::Applied,
+          Err(StatementError::UniqueViolation {
+            is_primary_key: true,
+            ..
+          }) => ApplyOutcome::AlreadyExisted,
+          Err(StatementError::UniqueViolation {
+            constraint_name, ..
+          }) => ApplyOutcome::Conflicted { constraint_name },
+          Err(StatementError::Other(err)) => ApplyOutcome::Failed {
+            message: err.to_string(),
+          },
+        };
+
+        return (apply_statement.label, outcome);
+      })
+      .collect();
+
+    return ApplyReport { outcomes };
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::statement_executor::MockStatementExecutor;
+
+  mod from_sql_script {
+    use super::*;
+
+    #[test]
+    fn it_should_split_insert_statements_per_row() -> ResultAnyError<()> {
+      let table = PsqlTable::fixture("users").build();
+      let table_by_id = vec![(table.id.clone(), table)].into_iter().collect();
+
+      let script = indoc::indoc! {"
+        -- Generated by lezeh db cherry-pick
+        BEGIN;
+
+        ------------------------------------------------
+        -- insert into table public.users
+        ------------------------------------------------
+        insert into public.users (\"name\", \"id\") VALUES
+          ('a; b', 1),
+          ( E'c\\\\''), (d', lower('X')),
+          ('e', 3);
+        ---------------
+
+        SELECT setval(pg_get_serial_sequence('public.users', 'id'), 3);
+        COMMIT;
+      "};
+
+      assert_eq!(
+        ApplyStatement::from_sql_script(DbDriver::Postgres, script, &table_by_id)?,
+        vec![
+          ApplyStatement {
+            label: "public.users 1".into(),
+            statement: "insert into public.users (\"name\", \"id\") VALUES\n  ('a; b', 1)".into(),
+          },
+          ApplyStatement {
+            label: "public.users lower('X')".into(),
+            statement:
+              "insert into public.users (\"name\", \"id\") VALUES\n  (E'c\\\\''), (d', lower('X'))"
+                .into(),
+          },
+          ApplyStatement {
+            label: "public.users 3".into(),
+            statement: "insert into public.users (\"name\", \"id\") VALUES\n  ('e', 3)".into(),
+          },
+          ApplyStatement {
+            label: "line 13: SELECT setval(pg_get_serial_sequence('public.users', 'id'), 3)".into(),
+            statement: "SELECT setval(pg_get_serial_sequence('public.users', 'id'), 3)".into(),
+          },
+        ]
+      );
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_order_rows_by_dependency_and_apply_transaction_settings_per_row(
+    ) -> ResultAnyError<()> {
+      let table_by_id = vec![
+        PsqlTable::fixture("users").build(),
+        PsqlTable::fixture("orders")
+          .references("user_id", "users")
+          .build(),
+      ]
+      .into_iter()
+      .map(|table| (table.id.clone(), table))
+      .collect();
+
+      let script = indoc::indoc! {"
+        BEGIN;
+        SET LOCAL session_replication_role = replica;
+        insert into public.orders (\"id\", \"user_id\") VALUES (1, 7);
+        insert into public.notes (\"id\") VALUES (5);
+        insert into public.users (\"id\") VALUES (7);
+        COMMIT;
+      "};
+
+      let in_transaction = |statement: &str| {
+        return format!(
+          "BEGIN;\nSET LOCAL session_replication_role = replica;\n{};\nCOMMIT;",
+          statement
+        );
+      };
+
+      assert_eq!(
+        ApplyStatement::from_sql_script(DbDriver::Postgres, script, &table_by_id)?,
+        vec![
+          ApplyStatement {
+            label: "public.users 7".into(),
+            statement: in_transaction("insert into public.users (\"id\") VALUES\n  (7)"),
+          },
+          ApplyStatement {
+            label: "public.notes (line 4, row 1)".into(),
+            statement: in_transaction("insert into public.notes (\"id\") VALUES\n  (5)"),
+          },
+          ApplyStatement {
+            label: "public.orders 1".into(),
+            statement: in_transaction(
+              "insert into public.orders (\"id\", \"user_id\") VALUES\n  (1, 7)"
+            ),
+          },
+        ]
+      );
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_reject_copy_scripts() {
+      let script = indoc::indoc! {"
+        BEGIN;
+        -- copy into table public.users
+        COPY \"public\".\"users\" (\"id\", \"name\") FROM stdin;
+        1\tjohn's
+        \\.
+        COMMIT;
+      "};
+
+      assert_eq!(
+        ApplyStatement::from_sql_script(DbDriver::Postgres, script, &Default::default())
+          .unwrap_err()
+          .to_string(),
+        "Line 3: COPY scripts must be loaded with psql -f"
+      );
+    }
+  }
+
+  mod apply {
+    use super::*;
+
+    #[test]
+    fn it_should_report_existing_conflicting_and_failed_rows() {
+      let mut executor = MockStatementExecutor::new();
+
+      executor.expect_execute().returning(|statement| {
+        return match statement {
+          "1" => Err(StatementError::UniqueViolation {
+            constraint_name: "users_pkey".into(),
+            is_primary_key: true,
+          }),
+          "2" => Err(StatementError::UniqueViolation {
+            constraint_name: "users_email_key".into(),
+            is_primary_key: false,
+          }),
+          "3" => Err(StatementError::Other(anyhow!("violates\nforeign key"))),
+          _ => Ok(()),
+        };
+      });
+
+      let statements = (1..=4)
+        .map(|id| ApplyStatement {
+          label: format!("public.users {}", id),
+          statement: id.to_string(),
+        })
+        .collect();
+
+      let report = StatementApply::new(&executor).apply(statements);
+
+      assert_eq!(report.failed_count(), 1);
+      assert_eq!(
+        report.to_string(),
+        indoc::indoc! {"
+          1 applied, 1 already existed, 1 conflicted, 1 failed
+          Already existed:
+            public.users 1
+          Conflicted:
+            public.users 2 on users_email_key
+          Failed:
+            public.users 3: violates foreign key
+        "}
+      );
+    }
+  }
+}
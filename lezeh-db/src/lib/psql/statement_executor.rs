@@ -0,0 +1,105 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use postgres::error::SqlState;
+use thiserror::Error;
+
+use crate::psql::connection::PsqlConnection;
+use lezeh_common::types::ResultAnyError;
+
+const PRIMARY_KEY_CONSTRAINT_QUERY: &str = "
+  SELECT EXISTS (
+    SELECT 1 FROM pg_catalog.pg_constraint WHERE conname = $1 AND contype = 'p'
+  )
+";
+
+#[derive(Error, Debug)]
+pub enum StatementError {
+  #[error("Duplicate key violates unique constraint {constraint_name}")]
+  UniqueViolation {
+    constraint_name: String,
+    is_primary_key: bool,
+  },
+
+  #[error(transparent)]
+  Other(#[from] anyhow::Error),
+}
+
+/// Runs generated statements against a db
+#[cfg_attr(test, mockall::automock)]
+pub trait StatementExecutor {
+  /// Execute all of the statements in a single transaction,
+  /// nothing is committed when one of them fails.
+  fn execute_in_transaction(&self, statements: &[String]) -> ResultAnyError<()>;
+
+  /// Execute the statement on its own, unique violations are reported separately
+  /// so existing rows can be told apart from other failures.
+  fn execute(&self, statement: &str) -> Result<(), StatementError>;
+}
+
+pub struct PsqlStatementExecutor {
+  connection: Rc<RefCell<PsqlConnection>>,
+}
+
+impl PsqlStatementExecutor {
+  pub fn new(psql_connection: Rc<RefCell<PsqlConnection>>) -> PsqlStatementExecutor {
+    return PsqlStatementExecutor {
+      connection: psql_connection,
+    };
+  }
+}
+
+impl StatementExecutor for PsqlStatementExecutor {
+  fn execute_in_transaction(&self, statements: &[String]) -> ResultAnyError<()> {
+    let mut connection = self.connection.borrow_mut();
+    let mut transaction = connection.get().transaction()?;
+
+    transaction.batch_execute(&statements.join("\n"))?;
+
+    return transaction.commit().map_err(anyhow::Error::from);
+  }
+
+  fn execute(&self, statement: &str) -> Result<(), StatementError> {
+    let mut connection = self.connection.borrow_mut();
+    let client = connection.get();
+
+    let err = match client.batch_execute(statement) {
+      Ok(_) => return Ok(()),
+      Err(err) => err,
+    };
+
+    // A statement that begins its own transaction leaves it aborted when it fails,
+    // the following statements would fail as well.
+    let is_transaction = statement
+      .get(.."BEGIN;".len())
+      .map(|prefix| prefix.eq_ignore_ascii_case("BEGIN;"))
+      .unwrap_or(false);
+
+    if is_transaction {
+      client
+        .batch_execute("ROLLBACK;")
+        .map_err(anyhow::Error::from)?;
+    }
+
+    let constraint_name = err
+      .as_db_error()
+      .filter(|db_error| *db_error.code() == SqlState::UNIQUE_VIOLATION)
+      .and_then(|db_error| db_error.constraint())
+      .map(ToOwned::to_owned);
+
+    return match constraint_name {
+      None => Err(StatementError::Other(err.into())),
+      Some(constraint_name) => {
+        let is_primary_key: bool = client
+          .query_one(PRIMARY_KEY_CONSTRAINT_QUERY, &[&constraint_name])
+          .map_err(anyhow::Error::from)?
+          .get(0);
+
+        Err(StatementError::UniqueViolation {
+          constraint_name,
+          is_primary_key,
+        })
+      }
+    };
+  }
+}
@@ -0,0 +1,494 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::config::DbDriver;
+use crate::psql::dto::{
+  FromSqlSink, PsqlCheckConstraint, PsqlEnumType, PsqlForeignKey, PsqlTable, PsqlTableColumn,
+  PsqlTableIdentity, PsqlUniqueConstraint,
+};
+use crate::psql::relation_insert::RelationInsert;
+
+/// `CREATE TABLE` statements for a set of tables, parents are created before their children.
+/// Foreign keys that can't be created inline (cyclic relations) are added with `ALTER TABLE`
+/// after all of the tables are created.
+pub struct TableDdl<'a> {
+  driver: DbDriver,
+  table_by_id: HashMap<&'a PsqlTableIdentity, &'a PsqlTable>,
+
+  /// Tables that already exist, foreign keys to them are created inline
+  existing_table_by_id: HashMap<&'a PsqlTableIdentity, &'a PsqlTable>,
+}
+
+impl<'a> TableDdl<'a> {
+  pub fn new(driver: DbDriver, tables: impl Iterator<Item = &'a PsqlTable>) -> TableDdl<'a> {
+    return TableDdl {
+      driver,
+      table_by_id: tables.map(|table| (&table.id, table)).collect(),
+      existing_table_by_id: Default::default(),
+    };
+  }
+
+  pub fn existing_tables(mut self, tables: impl Iterator<Item = &'a PsqlTable>) -> TableDdl<'a> {
+    self.existing_table_by_id = tables.map(|table| (&table.id, table)).collect();
+
+    return self;
+  }
+
+  pub fn into_statements(&self) -> Vec<String> {
+    let ordered_tables = PsqlTable::dependency_ordered(self.table_by_id.values().cloned());
+    let mut statements: Vec<String> = self.schema_statements();
+    let mut created_table_ids: HashSet<&PsqlTableIdentity> =
+      self.existing_table_by_id.keys().cloned().collect();
+    let mut deferred_fk_statements: Vec<String> = vec![];
+
+    statements.extend(self.enum_type_statements());
+
+    for table in ordered_tables {
+      created_table_ids.insert(&table.id);
+
+      let (statement, deferred_statements) = self.create_table_statement(table, &created_table_ids);
+
+      statements.push(statement);
+      deferred_fk_statements.extend(deferred_statements);
+    }
+
+    statements.extend(deferred_fk_statements);
+
+    return statements;
+  }
+
+  fn schema_statements(&self) -> Vec<String> {
+    let schemas: BTreeSet<&str> = self
+      .table_by_id
+      .keys()
+      .map(|table_id| table_id.schema.as_str())
+      .filter(|schema| *schema != "public")
+      .collect();
+
+    return schemas
+      .into_iter()
+      .map(|schema| {
+        let schema = self.quote_identifier(schema);
+
+        return match self.driver {
+          DbDriver::Postgres => format!("CREATE SCHEMA IF NOT EXISTS {};", schema),
+          DbDriver::Mysql => format!("CREATE DATABASE IF NOT EXISTS {};", schema),
+        };
+      })
+      .collect();
+  }
+
+  /// Enum types of the table columns by their schema and name
+  pub(crate) fn enum_types<'t>(
+    tables: impl Iterator<Item = &'t PsqlTable>,
+  ) -> BTreeMap<(&'t str, &'t str), &'t PsqlEnumType> {
+    return tables
+      .flat_map(|table| table.columns.iter())
+      .filter_map(|column| column.enum_type.as_ref())
+      .map(|enum_type| {
+        (
+          (enum_type.schema.as_str(), enum_type.name.as_str()),
+          enum_type,
+        )
+      })
+      .collect();
+  }
+
+  /// Enum types used by the existing tables are already created
+  fn enum_type_statements(&self) -> Vec<String> {
+    let existing_enum_types = TableDdl::enum_types(self.existing_table_by_id.values().cloned());
+
+    return TableDdl::enum_types(self.table_by_id.values().cloned())
+      .into_iter()
+      .filter(|(enum_type_id, _)| !existing_enum_types.contains_key(enum_type_id))
+      .map(|(_, enum_type)| self.enum_type_statement(enum_type))
+      .collect();
+  }
+
+  pub(crate) fn enum_type_statement(&self, enum_type: &PsqlEnumType) -> String {
+    return format!(
+      "CREATE TYPE {} AS ENUM ({});",
+      self.enum_type_identifier(enum_type),
+      enum_type
+        .labels
+        .iter()
+        .map(FromSqlSink::escape_string)
+        .collect::<Vec<String>>()
+        .join(", ")
+    );
+  }
+
+  pub(crate) fn enum_type_identifier(&self, enum_type: &PsqlEnumType) -> String {
+    return format!(
+      "{}.{}",
+      self.quote_identifier(&enum_type.schema),
+      self.quote_identifier(&enum_type.name)
+    );
+  }
+
+  /// Returns the create statement and the foreign keys that have to be added later
+  fn create_table_statement(
+    &self,
+    table: &PsqlTable,
+    created_table_ids: &HashSet<&PsqlTableIdentity>,
+  ) -> (String, Vec<String>) {
+    let table_identifier = RelationInsert::table_identifier(self.driver, &table.id);
+    let mut columns: Vec<&PsqlTableColumn> = table.ordered_columns();
+
+    // Tables loaded by older version only know the primary column
+    if columns.is_empty() {
+      columns.push(&table.primary_column);
+    }
+
+    let mut definitions: Vec<String> = columns
+      .iter()
+      .map(|column| self.column_definition(column))
+      .collect();
+    let mut comments: Vec<String> = vec![];
+    let mut deferred_fk_statements: Vec<String> = vec![];
+
+    definitions.push(format!(
+      "PRIMARY KEY ({})",
+      self.quote_identifier(&table.primary_column.name)
+    ));
+
+    for unique_constraint in table.unique_constraints.iter() {
+      definitions.push(self.unique_constraint_definition(unique_constraint));
+    }
+
+    for check_constraint in table.check_constraints.iter() {
+      definitions.push(self.check_constraint_definition(check_constraint));
+    }
+
+    let mut fks: Vec<_> = table.referencing_fk_by_constraint_name.values().collect();
+
+    fks.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for fk in fks {
+      let foreign_table_id = PsqlTableIdentity::new(
+        fk.foreign_table_schema.as_str(),
+        fk.foreign_table_name.as_str(),
+      );
+
+      let foreign_table = match self
+        .table_by_id
+        .get(&foreign_table_id)
+        .or_else(|| self.existing_table_by_id.get(&foreign_table_id))
+      {
+        Some(foreign_table) => foreign_table,
+        None => {
+          comments.push(format!(
+            "-- Skipped foreign key {}, {} is not part of the output",
+            fk.name, foreign_table_id
+          ));
+
+          continue;
+        }
+      };
+
+      let fk_definition = self.foreign_key_definition(fk, &foreign_table.primary_column.name);
+
+      if created_table_ids.contains(&foreign_table_id) {
+        definitions.push(fk_definition);
+      } else {
+        deferred_fk_statements.push(format!(
+          "ALTER TABLE {} ADD {};",
+          table_identifier, fk_definition
+        ));
+      }
+    }
+
+    comments.push(format!(
+      "CREATE TABLE IF NOT EXISTS {} (\n  {}\n);",
+      table_identifier,
+      definitions.join(",\n  ")
+    ));
+
+    return (comments.join("\n"), deferred_fk_statements);
+  }
+
+  pub(crate) fn unique_constraint_definition(
+    &self,
+    unique_constraint: &PsqlUniqueConstraint,
+  ) -> String {
+    return format!(
+      "CONSTRAINT {} UNIQUE ({})",
+      self.quote_identifier(&unique_constraint.name),
+      unique_constraint
+        .column_names
+        .iter()
+        .map(|column_name| self.quote_identifier(column_name))
+        .collect::<Vec<String>>()
+        .join(", ")
+    );
+  }
+
+  pub(crate) fn check_constraint_definition(
+    &self,
+    check_constraint: &PsqlCheckConstraint,
+  ) -> String {
+    return format!(
+      "CONSTRAINT {} {}",
+      self.quote_identifier(&check_constraint.name),
+      check_constraint.definition
+    );
+  }
+
+  /// Foreign keys always reference the primary column of the parent table
+  pub(crate) fn foreign_key_definition(
+    &self,
+    fk: &PsqlForeignKey,
+    foreign_primary_column_name: &str,
+  ) -> String {
+    let foreign_table_id = PsqlTableIdentity::new(
+      fk.foreign_table_schema.as_str(),
+      fk.foreign_table_name.as_str(),
+    );
+
+    return format!(
+      "CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({})",
+      self.quote_identifier(&fk.name),
+      self.quote_identifier(&fk.column.name),
+      RelationInsert::table_identifier(self.driver, &foreign_table_id),
+      self.quote_identifier(foreign_primary_column_name)
+    );
+  }
+
+  /// Type as written in DDL, enum columns use their enum type
+  pub(crate) fn column_type(&self, column: &PsqlTableColumn) -> String {
+    if let Some(enum_type) = column.enum_type.as_ref() {
+      return self.enum_type_identifier(enum_type);
+    }
+
+    return column
+      .column_type
+      .clone()
+      .unwrap_or_else(|| column.data_type.clone());
+  }
+
+  pub(crate) fn column_definition(&self, column: &PsqlTableColumn) -> String {
+    let mut column_type: String = self.column_type(column);
+    let mut default_value: Option<String> = column.default_value.clone();
+
+    // The sequence does not exist in the target db, serial creates it
+    let is_serial = self.driver == DbDriver::Postgres
+      && !column.is_identity
+      && column.sequence_name.is_some()
+      && default_value
+        .as_ref()
+        .map(|default_value| default_value.starts_with("nextval("))
+        .unwrap_or(false);
+
+    if is_serial {
+      let serial_type = match column.data_type.as_str() {
+        "smallint" => Some("smallserial"),
+        "integer" => Some("serial"),
+        "bigint" => Some("bigserial"),
+        _ => None,
+      };
+
+      if let Some(serial_type) = serial_type {
+        column_type = serial_type.to_owned();
+        default_value = None;
+      }
+    }
+
+    let mut definition = format!("{} {}", self.quote_identifier(&column.name), column_type);
+
+    if column.is_identity {
+      definition.push_str(" GENERATED BY DEFAULT AS IDENTITY");
+    }
+
+    if let Some(generation_expression) = column
+      .generation_expression
+      .as_ref()
+      .filter(|_| column.is_generated)
+    {
+      definition.push_str(&match self.driver {
+        DbDriver::Postgres => format!(" GENERATED ALWAYS AS ({}) STORED", generation_expression),
+        DbDriver::Mysql => format!(" GENERATED ALWAYS AS ({})", generation_expression),
+      });
+    }
+
+    if !column.is_nullable {
+      definition.push_str(" NOT NULL");
+    }
+
+    if let Some(default_value) = default_value.filter(|_| !column.is_generated) {
+      definition.push_str(&format!(
+        " DEFAULT {}",
+        self.default_literal(&default_value)
+      ));
+    }
+
+    if column.is_auto_increment {
+      definition.push_str(" AUTO_INCREMENT");
+    }
+
+    return definition;
+  }
+
+  /// Postgres defaults are already sql expressions,
+  /// mysql returns the raw value of literal defaults.
+  pub(crate) fn default_literal(&self, default_value: &str) -> String {
+    if self.driver == DbDriver::Postgres {
+      return default_value.to_owned();
+    }
+
+    let is_expression = default_value.parse::<f64>().is_ok()
+      || default_value.eq_ignore_ascii_case("null")
+      || default_value
+        .to_uppercase()
+        .starts_with("CURRENT_TIMESTAMP");
+
+    if is_expression {
+      return default_value.to_owned();
+    }
+
+    return format!("'{}'", default_value.replace('\'', "''"));
+  }
+
+  pub(crate) fn quote_identifier(&self, identifier: &str) -> String {
+    return RelationInsert::quote_identifier(self.driver, identifier);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::dto::*;
+
+  /// Columns positioned in the given order
+  fn ordered_columns(columns: Vec<PsqlTableColumn>) -> Vec<PsqlTableColumn> {
+    return columns
+      .into_iter()
+      .enumerate()
+      .map(|(index, column)| PsqlTableColumn {
+        ordinal_position: index as i32 + 1,
+        ..column
+      })
+      .collect();
+  }
+
+  fn serial_id_column(table_name: &str) -> PsqlTableColumn {
+    return PsqlTableColumn {
+      default_value: Some(format!("nextval('{}_id_seq'::regclass)", table_name)),
+      sequence_name: Some(format!("public.{}_id_seq", table_name)),
+      ..PsqlTableColumn::new("id", "integer")
+    };
+  }
+
+  mod into_statements {
+    use super::*;
+
+    #[test]
+    fn it_should_create_parents_first_and_defer_cyclic_foreign_keys() {
+      let users = PsqlTable::fixture("users")
+        .columns(ordered_columns(vec![
+          serial_id_column("users"),
+          PsqlTableColumn {
+            is_nullable: true,
+            ..PsqlTableColumn::new("last_order_id", "integer")
+          },
+        ]))
+        .references("last_order_id", "orders")
+        .build();
+
+      let mut orders = PsqlTable::fixture("orders")
+        .columns(ordered_columns(vec![
+          serial_id_column("orders"),
+          PsqlTableColumn::new("user_id", "integer"),
+          PsqlTableColumn {
+            column_type: Some("order_status".into()),
+            default_value: Some("'pending'::order_status".into()),
+            enum_type: Some(PsqlEnumType {
+              schema: "public".into(),
+              name: "order_status".into(),
+              labels: vec!["pending".into(), "paid".into()],
+            }),
+            ..PsqlTableColumn::new("status", "USER-DEFINED")
+          },
+          PsqlTableColumn {
+            column_type: Some("numeric(10,2)".into()),
+            ..PsqlTableColumn::new("total", "numeric")
+          },
+        ]))
+        .references("user_id", "users")
+        .references("coupon_id", "coupons")
+        .build();
+
+      orders.check_constraints = vec![PsqlCheckConstraint {
+        name: "orders_total_check".into(),
+        definition: "CHECK ((total >= (0)::numeric))".into(),
+      }];
+
+      let tables = [orders, users];
+      let statements = TableDdl::new(DbDriver::Postgres, tables.iter()).into_statements();
+
+      assert_eq!(
+        statements,
+        vec![
+          "CREATE TYPE \"public\".\"order_status\" AS ENUM ('pending', 'paid');".to_owned(),
+          indoc::indoc! {"
+            -- Skipped foreign key orders_coupon_id_foreign, public.coupons is not part of the output
+            CREATE TABLE IF NOT EXISTS \"public\".\"orders\" (
+              \"id\" serial NOT NULL,
+              \"user_id\" integer NOT NULL,
+              \"status\" \"public\".\"order_status\" NOT NULL DEFAULT 'pending'::order_status,
+              \"total\" numeric(10,2) NOT NULL,
+              PRIMARY KEY (\"id\"),
+              CONSTRAINT \"orders_total_check\" CHECK ((total >= (0)::numeric))
+            );"}
+          .to_owned(),
+          indoc::indoc! {"
+            CREATE TABLE IF NOT EXISTS \"public\".\"users\" (
+              \"id\" serial NOT NULL,
+              \"last_order_id\" integer,
+              PRIMARY KEY (\"id\"),
+              CONSTRAINT \"users_last_order_id_foreign\" FOREIGN KEY (\"last_order_id\") REFERENCES \"public\".\"orders\" (\"id\")
+            );"}
+          .to_owned(),
+          "ALTER TABLE \"public\".\"orders\" ADD CONSTRAINT \"orders_user_id_foreign\" FOREIGN KEY (\"user_id\") REFERENCES \"public\".\"users\" (\"id\");".to_owned(),
+        ]
+      );
+    }
+
+    #[test]
+    fn it_should_quote_mysql_literal_defaults() {
+      let table = PsqlTable::fixture("orders")
+        .schema("shop")
+        .columns(ordered_columns(vec![
+          PsqlTableColumn {
+            column_type: Some("int unsigned".into()),
+            is_auto_increment: true,
+            ..PsqlTableColumn::new("id", "int")
+          },
+          PsqlTableColumn {
+            column_type: Some("enum('pending','paid')".into()),
+            default_value: Some("pending".into()),
+            ..PsqlTableColumn::new("status", "enum")
+          },
+          PsqlTableColumn {
+            column_type: Some("timestamp".into()),
+            default_value: Some("CURRENT_TIMESTAMP".into()),
+            ..PsqlTableColumn::new("created_at", "timestamp")
+          },
+        ]))
+        .build();
+
+      assert_eq!(
+        TableDdl::new(DbDriver::Mysql, [table].iter()).into_statements(),
+        vec![
+          "CREATE DATABASE IF NOT EXISTS `shop`;".to_owned(),
+          indoc::indoc! {"
+            CREATE TABLE IF NOT EXISTS `shop`.`orders` (
+              `id` int unsigned NOT NULL AUTO_INCREMENT,
+              `status` enum('pending','paid') NOT NULL DEFAULT 'pending',
+              `created_at` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+              PRIMARY KEY (`id`)
+            );"}
+          .to_owned(),
+        ]
+      );
+    }
+  }
+}
@@ -0,0 +1,201 @@
+use crate::psql::dto::{PsqlForeignKey, PsqlTable, PsqlTableColumn};
+
+/// Human readable table structure, printed by `lezeh db describe`
+pub struct TableDescription<'a> {
+  table: &'a PsqlTable,
+}
+
+impl<'a> TableDescription<'a> {
+  pub fn new(table: &'a PsqlTable) -> TableDescription<'a> {
+    return TableDescription { table };
+  }
+
+  fn column_type(column: &PsqlTableColumn) -> String {
+    return match column.character_maximum_length {
+      Some(length) => format!("{}({})", column.data_type, length),
+      None => column.data_type.clone(),
+    };
+  }
+
+  fn column_attributes(&self, column: &PsqlTableColumn) -> Vec<String> {
+    let mut attributes: Vec<String> = vec![];
+
+    if column.name == self.table.primary_column.name {
+      attributes.push("primary key".into());
+    }
+
+    if !column.is_nullable {
+      attributes.push("not null".into());
+    }
+
+    if column.is_identity {
+      attributes.push("identity".into());
+    }
+
+    if column.is_generated {
+      attributes.push("generated".into());
+    }
+
+    if let Some(default_value) = column.default_value.as_ref() {
+      attributes.push(format!("default {}", default_value));
+    }
+
+    return attributes;
+  }
+
+  fn sorted_fks<'b>(fks: impl Iterator<Item = &'b PsqlForeignKey>) -> Vec<&'b PsqlForeignKey> {
+    let mut fks: Vec<&PsqlForeignKey> = fks.collect();
+
+    fks.sort_by(|a, b| a.name.cmp(&b.name));
+
+    return fks;
+  }
+}
+
+impl<'a> std::fmt::Display for TableDescription<'a> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let columns = self.table.ordered_columns();
+    let name_width = columns
+      .iter()
+      .map(|column| column.name.len())
+      .max()
+      .unwrap_or_default();
+    let type_width = columns
+      .iter()
+      .map(|column| TableDescription::column_type(column).len())
+      .max()
+      .unwrap_or_default();
+
+    writeln!(f, "Table {}", self.table.id)?;
+    writeln!(f, "Columns:")?;
+
+    for column in columns.iter() {
+      let line = format!(
+        "  {:name_width$}  {:type_width$}  {}",
+        column.name,
+        TableDescription::column_type(column),
+        self.column_attributes(column).join(", "),
+        name_width = name_width,
+        type_width = type_width
+      );
+
+      writeln!(f, "{}", line.trim_end())?;
+    }
+
+    if !self.table.unique_constraints.is_empty() {
+      writeln!(f, "Unique constraints:")?;
+
+      for constraint in self.table.unique_constraints.iter() {
+        writeln!(
+          f,
+          "  {} ({})",
+          constraint.name,
+          constraint.column_names.join(", ")
+        )?;
+      }
+    }
+
+    if !self.table.check_constraints.is_empty() {
+      writeln!(f, "Check constraints:")?;
+
+      for constraint in self.table.check_constraints.iter() {
+        writeln!(f, "  {} {}", constraint.name, constraint.definition)?;
+      }
+    }
+
+    if !self.table.referencing_fk_by_constraint_name.is_empty() {
+      writeln!(f, "Foreign keys:")?;
+
+      for fk in TableDescription::sorted_fks(self.table.referencing_fk_by_constraint_name.values())
+      {
+        writeln!(
+          f,
+          "  {} ({}) -> {}.{}",
+          fk.name, fk.column.name, fk.foreign_table_schema, fk.foreign_table_name
+        )?;
+      }
+    }
+
+    if !self.table.referenced_fk_by_constraint_name.is_empty() {
+      writeln!(f, "Referenced by:")?;
+
+      for fk in TableDescription::sorted_fks(self.table.referenced_fk_by_constraint_name.values()) {
+        writeln!(
+          f,
+          "  {} {}.{} ({})",
+          fk.name, fk.foreign_table_schema, fk.foreign_table_name, fk.column.name
+        )?;
+      }
+    }
+
+    return Ok(());
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::dto::*;
+
+  mod fmt {
+    use super::*;
+
+    #[test]
+    fn it_should_describe_columns_and_constraints() {
+      let mut table = PsqlTable::fixture("orders")
+        .columns(vec![
+          PsqlTableColumn {
+            default_value: Some("nextval('orders_id_seq'::regclass)".into()),
+            ordinal_position: 1,
+            ..PsqlTableColumn::new("id", "integer")
+          },
+          PsqlTableColumn {
+            is_nullable: true,
+            ordinal_position: 3,
+            ..PsqlTableColumn::new("user_id", "integer")
+          },
+          PsqlTableColumn {
+            character_maximum_length: Some(32),
+            ordinal_position: 2,
+            ..PsqlTableColumn::new("number", "character varying")
+          },
+        ])
+        .referenced_fks(vec![PsqlForeignKey::new(
+          "order_items_order_id_foreign",
+          PsqlTableColumn::new("order_id", "integer"),
+          "public",
+          "order_items",
+        )])
+        .references("user_id", "users")
+        .build();
+
+      table.unique_constraints = vec![PsqlUniqueConstraint {
+        name: "orders_number_key".into(),
+        column_names: vec!["number".into()],
+      }];
+      table.check_constraints = vec![PsqlCheckConstraint {
+        name: "orders_number_check".into(),
+        definition: "CHECK ((length((number)::text) > 0))".into(),
+      }];
+
+      assert_eq!(
+        TableDescription::new(&table).to_string(),
+        indoc::indoc! {"
+          Table public.orders
+          Columns:
+            id       integer                primary key, not null, default nextval('orders_id_seq'::regclass)
+            number   character varying(32)  not null
+            user_id  integer
+          Unique constraints:
+            orders_number_key (number)
+          Check constraints:
+            orders_number_check CHECK ((length((number)::text) > 0))
+          Foreign keys:
+            orders_user_id_foreign (user_id) -> public.users
+          Referenced by:
+            order_items_order_id_foreign public.order_items (order_id)
+        "}
+      );
+    }
+  }
+}
@@ -2,10 +2,13 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use anyhow::anyhow;
+use async_trait::async_trait;
+use postgres::types::ToSql;
 use postgres::Row;
 use thiserror::Error;
 
 use crate::psql::connection::PsqlConnection;
+use crate::psql::connection_pool::PsqlConnectionPool;
 use crate::psql::dto::*;
 use lezeh_common::types::ResultAnyError;
 
@@ -32,7 +35,100 @@ pub enum QueryError {
 pub struct FetchRowInput<'a> {
   pub table_id: &'a PsqlTableIdentity,
   pub column_name: &'a str,
-  pub column_value: &'a PsqlParamValue,
+  pub column_value: &'a (dyn ToSql + Sync),
+  pub limit: Option<&'a RowLimit>,
+
+  /// Raw sql predicates that the fetched rows must satisfy
+  pub predicates: Vec<&'a str>,
+}
+
+/// Raw sql predicate appended to every relation query, e.g. `deleted_at IS NULL`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowFilter {
+  /// Only applies to this table, applies to every table if not set
+  pub table_id: Option<PsqlTableIdentity>,
+
+  /// Only applies to tables that contain this column
+  pub column_name: Option<String>,
+  pub predicate: String,
+}
+
+impl RowFilter {
+  pub fn applies_to(&self, table: &PsqlTable) -> bool {
+    if let Some(table_id) = self.table_id.as_ref() {
+      if *table_id != table.id {
+        return false;
+      }
+    }
+
+    if let Some(column_name) = self.column_name.as_ref() {
+      return table
+        .columns
+        .iter()
+        .any(|column| column.name == *column_name);
+    }
+
+    return true;
+  }
+
+  pub fn predicates<'a>(row_filters: &'a [RowFilter], table: &PsqlTable) -> Vec<&'a str> {
+    return row_filters
+      .iter()
+      .filter(|row_filter| row_filter.applies_to(table))
+      .map(|row_filter| row_filter.predicate.as_str())
+      .collect();
+  }
+}
+
+/// Child column that must reference an existing parent row,
+/// it's either a declared foreign key or a configured one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowRelation {
+  pub name: String,
+  pub table_id: PsqlTableIdentity,
+  pub primary_column_name: String,
+  pub column_name: String,
+  pub foreign_table_id: PsqlTableIdentity,
+  pub foreign_column_name: String,
+}
+
+impl std::fmt::Display for RowRelation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(
+      f,
+      "{} {}.{} -> {}.{}",
+      self.name, self.table_id, self.column_name, self.foreign_table_id, self.foreign_column_name
+    );
+  }
+}
+
+/// Child rows whose non null value does not exist in the parent table
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrphanRows {
+  pub row_count: usize,
+
+  /// Primary key and referencing value of the first orphaned rows, ordered by the primary key
+  pub sample_keys: Vec<(String, String)>,
+}
+
+/// Only fetch the first `max_rows` rows ordered by the given column
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowLimit {
+  pub max_rows: usize,
+  pub order_column_name: String,
+  pub descending: bool,
+}
+
+impl std::fmt::Display for RowLimit {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(
+      f,
+      "ORDER BY {} {} LIMIT {}",
+      self.order_column_name,
+      if self.descending { "DESC" } else { "ASC" },
+      self.max_rows
+    );
+  }
 }
 
 impl<'b> FetchRowInput<'b> {
@@ -64,42 +160,164 @@ impl<'b> FetchRowInput<'b> {
   }
 }
 
-impl Query {
-  fn find_rows(&mut self, input: &FetchRowInput) -> ResultAnyError<Vec<Row>> {
-    let query_str = format!(
+impl<'a> FetchRowInput<'a> {
+  fn select_query_str(&self) -> String {
+    let mut query_str = format!(
       "SELECT * FROM {} where {} = $1",
-      input.table_id, input.column_name
+      self.table_id.quoted(),
+      escape_identifier(self.column_name)
+    );
+
+    for predicate in self.predicates.iter() {
+      query_str = format!("{} AND ({})", query_str, predicate);
+    }
+
+    if let Some(limit) = self.limit {
+      query_str = format!(
+        "{} ORDER BY {} {} LIMIT {}",
+        query_str,
+        escape_identifier(&limit.order_column_name),
+        if limit.descending { "DESC" } else { "ASC" },
+        limit.max_rows
+      );
+    }
+
+    return query_str;
+  }
+
+  fn count_query_str(&self) -> String {
+    let mut query_str = format!(
+      "SELECT count(*) FROM {} where {} = $1",
+      self.table_id.quoted(),
+      escape_identifier(self.column_name)
     );
 
+    for predicate in self.predicates.iter() {
+      query_str = format!("{} AND ({})", query_str, predicate);
+    }
+
+    return query_str;
+  }
+}
+
+const COLUMN_METADATA_QUERY: &str =
+  "SELECT * FROM information_schema.columns where table_schema = $1 and table_name = $2 and column_name = $3";
+
+impl Query {
+  fn find_rows(&mut self, input: &FetchRowInput) -> ResultAnyError<Vec<Row>> {
+    let query_str = input.select_query_str();
+
     let mut connection = self.connection.borrow_mut();
     let connection = connection.get();
     let statement = connection.prepare(&query_str)?;
 
     return connection
-      .query(&statement, &[input.column_value.as_ref()])
+      .query(&statement, &[input.column_value])
       .map_err(anyhow::Error::from);
   }
 
+  fn count_rows(&mut self, input: &FetchRowInput) -> ResultAnyError<i64> {
+    let query_str = input.count_query_str();
+
+    let mut connection = self.connection.borrow_mut();
+    let connection = connection.get();
+    let statement = connection.prepare(&query_str)?;
+
+    return connection
+      .query_one(&statement, &[input.column_value])
+      .map(|row| row.get(0))
+      .map_err(anyhow::Error::from);
+  }
+
+  fn find_max_id(
+    &mut self,
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+  ) -> ResultAnyError<Option<i64>> {
+    let query_str = format!(
+      "SELECT max({})::bigint FROM {}",
+      escape_identifier(column_name),
+      table_id.quoted()
+    );
+
+    let mut connection = self.connection.borrow_mut();
+
+    return connection
+      .get()
+      .query_one(query_str.as_str(), &[])
+      .map(|row| row.get(0))
+      .map_err(anyhow::Error::from);
+  }
+
+  fn find_orphan_rows(
+    &mut self,
+    relation: &RowRelation,
+    sample_size: usize,
+  ) -> ResultAnyError<OrphanRows> {
+    let from_str = format!(
+      "FROM {} AS c WHERE c.{} IS NOT NULL AND NOT EXISTS (SELECT 1 FROM {} AS p WHERE p.{} = c.{})",
+      relation.table_id.quoted(),
+      escape_identifier(&relation.column_name),
+      relation.foreign_table_id.quoted(),
+      escape_identifier(&relation.foreign_column_name),
+      escape_identifier(&relation.column_name)
+    );
+
+    let mut connection = self.connection.borrow_mut();
+    let connection = connection.get();
+
+    let row_count: i64 = connection
+      .query_one(format!("SELECT count(*) {}", from_str).as_str(), &[])?
+      .get(0);
+
+    let sample_keys = connection
+      .query(
+        format!(
+          "SELECT c.{}::text, c.{}::text {} ORDER BY c.{} LIMIT {}",
+          escape_identifier(&relation.primary_column_name),
+          escape_identifier(&relation.column_name),
+          from_str,
+          escape_identifier(&relation.primary_column_name),
+          sample_size
+        )
+        .as_str(),
+        &[],
+      )?
+      .into_iter()
+      .map(|row| (row.get(0), row.get(1)))
+      .collect();
+
+    return Ok(OrphanRows {
+      row_count: row_count as usize,
+      sample_keys,
+    });
+  }
+
   fn find_one_row(&mut self, input: &FetchRowInput) -> ResultAnyError<Option<Row>> {
-    let rows_result = self.find_rows(input);
-
-    return match rows_result {
-      Err(any) => Err(any),
-      Ok(mut rows) => {
-        if rows.len() > 1 {
-          return Err(anyhow!(QueryError::TooManyRows {
-            row_count: rows.len(),
-            expected_row_count: 1,
-          }));
-        }
-
-        if rows.len() == 0 {
-          return Ok(None);
-        }
-
-        return Ok(Some(rows.remove(0)));
-      }
-    };
+    return self.find_rows(input).and_then(Query::into_single_row);
+  }
+
+  fn into_single_row(mut rows: Vec<Row>) -> ResultAnyError<Option<Row>> {
+    if rows.len() > 1 {
+      return Err(anyhow!(QueryError::TooManyRows {
+        row_count: rows.len(),
+        expected_row_count: 1,
+      }));
+    }
+
+    if rows.len() == 0 {
+      return Ok(None);
+    }
+
+    return Ok(Some(rows.remove(0)));
+  }
+
+  fn row_not_found(table: &PsqlTable, column_name: &str, id: &PsqlParamValue) -> anyhow::Error {
+    return anyhow!(QueryError::RowNotFound {
+      table_id: format!("{:#?}", table.id),
+      column: column_name.into(),
+      identifier: format!("{:#?}", id),
+    });
   }
 
   pub fn get_column_metadata<'a>(
@@ -107,12 +325,9 @@ impl Query {
     table_id: &PsqlTableIdentity,
     column_name: &str,
   ) -> ResultAnyError<Row> {
-    let query_str =
-      "SELECT * FROM information_schema.columns where table_schema = $1 and table_name = $2 and column_name = $3";
-
     let mut connection = self.connection.borrow_mut();
     let connection = connection.get();
-    let statement = connection.prepare(&query_str)?;
+    let statement = connection.prepare(COLUMN_METADATA_QUERY)?;
 
     return connection
       .query_one(
@@ -139,29 +354,54 @@ pub trait TableMetadata {
     &self,
     table: PsqlTable,
     column_name: &str,
-    id: &PsqlParamValue,
+    id: &FromSqlSink,
+    limit: Option<&'a RowLimit>,
   ) -> ResultAnyError<Vec<PsqlTableRow>>;
 
+  fn count_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+  ) -> ResultAnyError<usize>;
+
   fn get_one_row(
     &self,
     table: &PsqlTable,
     column_name: &str,
     id: &str,
   ) -> ResultAnyError<PsqlTableRow>;
+
+  /// Max value of an integer column, None if the table is empty
+  fn get_max_id(&self, table: &PsqlTable, column_name: &str) -> ResultAnyError<Option<i64>>;
+
+  /// Child rows that reference a missing parent row, row filters are not applied
+  fn find_orphan_rows(
+    &self,
+    relation: &RowRelation,
+    sample_size: usize,
+  ) -> ResultAnyError<OrphanRows>;
 }
 
 pub struct TableMetadataImpl {
   /// We know that we own this query so it's ok
   /// to directl borrow_mut() without checking ownership
   query: RefCell<Query>,
+
+  /// Applied when fetching relations, the root row is never filtered
+  row_filters: Vec<RowFilter>,
 }
 
 impl TableMetadataImpl {
-  pub fn new(psql_connection: Rc<RefCell<PsqlConnection>>) -> TableMetadataImpl {
+  pub fn new(
+    psql_connection: Rc<RefCell<PsqlConnection>>,
+    row_filters: Vec<RowFilter>,
+  ) -> TableMetadataImpl {
     return TableMetadataImpl {
       query: RefCell::new(Query {
         connection: psql_connection,
       }),
+      row_filters,
     };
   }
 }
@@ -186,7 +426,8 @@ impl TableMetadata for TableMetadataImpl {
     &self,
     table: PsqlTable,
     column_name: &str,
-    id: &PsqlParamValue,
+    id: &FromSqlSink,
+    limit: Option<&RowLimit>,
   ) -> ResultAnyError<Vec<PsqlTableRow>> {
     return self
       .query
@@ -195,17 +436,51 @@ impl TableMetadata for TableMetadataImpl {
         table_id: &table.id,
         column_name,
         column_value: id,
+        limit,
+        predicates: RowFilter::predicates(&self.row_filters, &table),
       })
       .and_then(|rows| {
         return rows
           .into_iter()
           .map(|inner_row| {
-            return PsqlTableRow::new(table.clone(), Rc::new(inner_row));
+            return PsqlTableRow::from_row(table.clone(), &inner_row);
           })
           .collect::<ResultAnyError<Vec<PsqlTableRow>>>();
       });
   }
 
+  fn count_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+  ) -> ResultAnyError<usize> {
+    let row_count = self.query.borrow_mut().count_rows(&FetchRowInput {
+      table_id: &table.id,
+      column_name,
+      column_value: id,
+      limit: None,
+      predicates: RowFilter::predicates(&self.row_filters, table),
+    })?;
+
+    return Ok(row_count as usize);
+  }
+
+  fn get_max_id(&self, table: &PsqlTable, column_name: &str) -> ResultAnyError<Option<i64>> {
+    return self.query.borrow_mut().find_max_id(&table.id, column_name);
+  }
+
+  fn find_orphan_rows(
+    &self,
+    relation: &RowRelation,
+    sample_size: usize,
+  ) -> ResultAnyError<OrphanRows> {
+    return self
+      .query
+      .borrow_mut()
+      .find_orphan_rows(relation, sample_size);
+  }
+
   fn get_one_row<'a>(
     &self,
     table: &PsqlTable,
@@ -218,17 +493,282 @@ impl TableMetadata for TableMetadataImpl {
     let row = self.query.borrow_mut().find_one_row(&FetchRowInput {
       table_id: &table.id,
       column_name,
-      column_value: &id,
+      column_value: id.as_ref(),
+      limit: None,
+      predicates: vec![],
     })?;
 
     return row
-      .ok_or_else(|| {
-        anyhow!(QueryError::RowNotFound {
-          table_id: format!("{:#?}", table.id),
-          column: column_name.into(),
-          identifier: format!("{:#?}", id),
-        })
+      .ok_or_else(|| Query::row_not_found(table, column_name, &id))
+      .and_then(|inner_row| PsqlTableRow::from_row(table.clone(), &inner_row));
+  }
+}
+
+/// Rows needed to fetch the relation graph, queries of different relations can run concurrently.
+/// Futures are not `Send`, they're polled within 1 thread.
+#[async_trait(?Send)]
+pub trait AsyncTableMetadata {
+  async fn get_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+    limit: Option<&RowLimit>,
+  ) -> ResultAnyError<Vec<PsqlTableRow>>;
+
+  async fn count_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+  ) -> ResultAnyError<usize>;
+
+  async fn get_one_row(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &str,
+  ) -> ResultAnyError<PsqlTableRow>;
+}
+
+/// Runs the queries of a [TableMetadata] one after another
+pub struct BlockingTableMetadata {
+  inner: Box<dyn TableMetadata>,
+}
+
+impl BlockingTableMetadata {
+  pub fn new(inner: Box<dyn TableMetadata>) -> BlockingTableMetadata {
+    return BlockingTableMetadata { inner };
+  }
+}
+
+#[async_trait(?Send)]
+impl AsyncTableMetadata for BlockingTableMetadata {
+  async fn get_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+    limit: Option<&RowLimit>,
+  ) -> ResultAnyError<Vec<PsqlTableRow>> {
+    return self.inner.get_rows(table.clone(), column_name, id, limit);
+  }
+
+  async fn count_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+  ) -> ResultAnyError<usize> {
+    return self.inner.count_rows(table, column_name, id);
+  }
+
+  async fn get_one_row(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &str,
+  ) -> ResultAnyError<PsqlTableRow> {
+    return self.inner.get_one_row(table, column_name, id);
+  }
+}
+
+/// Postgres [AsyncTableMetadata] that borrows a connection of the pool for every query
+pub struct PooledTableMetadata {
+  pool: PsqlConnectionPool,
+
+  /// Applied when fetching relations, the root row is never filtered
+  row_filters: Vec<RowFilter>,
+}
+
+impl PooledTableMetadata {
+  pub fn new(pool: PsqlConnectionPool, row_filters: Vec<RowFilter>) -> PooledTableMetadata {
+    return PooledTableMetadata { pool, row_filters };
+  }
+
+  async fn find_rows(&self, input: &FetchRowInput<'_>) -> ResultAnyError<Vec<Row>> {
+    let client = self.pool.get().await?;
+    let statement = client.prepare(&input.select_query_str()).await?;
+
+    return client
+      .query(&statement, &[input.column_value])
+      .await
+      .map_err(anyhow::Error::from);
+  }
+}
+
+#[async_trait(?Send)]
+impl AsyncTableMetadata for PooledTableMetadata {
+  async fn get_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+    limit: Option<&RowLimit>,
+  ) -> ResultAnyError<Vec<PsqlTableRow>> {
+    let rows = self
+      .find_rows(&FetchRowInput {
+        table_id: &table.id,
+        column_name,
+        column_value: id,
+        limit,
+        predicates: RowFilter::predicates(&self.row_filters, table),
+      })
+      .await?;
+
+    return rows
+      .iter()
+      .map(|inner_row| PsqlTableRow::from_row(table.clone(), inner_row))
+      .collect();
+  }
+
+  async fn count_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+  ) -> ResultAnyError<usize> {
+    let input = FetchRowInput {
+      table_id: &table.id,
+      column_name,
+      column_value: id,
+      limit: None,
+      predicates: RowFilter::predicates(&self.row_filters, table),
+    };
+
+    let client = self.pool.get().await?;
+    let statement = client.prepare(&input.count_query_str()).await?;
+    let row_count: i64 = client
+      .query_one(&statement, &[input.column_value])
+      .await?
+      .get(0);
+
+    return Ok(row_count as usize);
+  }
+
+  async fn get_one_row(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &str,
+  ) -> ResultAnyError<PsqlTableRow> {
+    let column_row = self
+      .pool
+      .get()
+      .await?
+      .query_one(
+        COLUMN_METADATA_QUERY,
+        &[
+          &table.id.schema.to_string(),
+          &table.id.name.to_string(),
+          &column_name.to_string(),
+        ],
+      )
+      .await?;
+    let column = PsqlTableColumn::new(column_name.to_string(), column_row.get("data_type"));
+    let id: PsqlParamValue = FetchRowInput::psql_param_value(id.to_string(), column)?;
+
+    let rows = self
+      .find_rows(&FetchRowInput {
+        table_id: &table.id,
+        column_name,
+        column_value: id.as_ref(),
+        limit: None,
+        predicates: vec![],
       })
-      .and_then(|inner_row| PsqlTableRow::new(table.clone(), Rc::new(inner_row)));
+      .await?;
+
+    return Query::into_single_row(rows)?
+      .ok_or_else(|| Query::row_not_found(table, column_name, &id))
+      .and_then(|inner_row| PsqlTableRow::from_row(table.clone(), &inner_row));
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  mod row_filter {
+    use super::*;
+
+    fn create_table(name: &str, column_names: Vec<&str>) -> PsqlTable {
+      return PsqlTable::fixture(name)
+        .columns(
+          column_names
+            .into_iter()
+            .map(|column_name| PsqlTableColumn::new(column_name, "integer"))
+            .collect(),
+        )
+        .build();
+    }
+
+    #[test]
+    fn it_should_only_apply_to_tables_with_the_column() {
+      let row_filters = vec![RowFilter {
+        table_id: None,
+        column_name: Some("deleted_at".to_owned()),
+        predicate: "deleted_at IS NULL".to_owned(),
+      }];
+
+      assert_eq!(
+        RowFilter::predicates(
+          &row_filters,
+          &create_table("orders", vec!["id", "deleted_at"])
+        ),
+        vec!["deleted_at IS NULL"]
+      );
+      assert!(RowFilter::predicates(&row_filters, &create_table("orders", vec!["id"])).is_empty());
+    }
+
+    #[test]
+    fn it_should_only_apply_to_the_given_table() {
+      let row_filters = vec![
+        RowFilter {
+          table_id: Some(PsqlTableIdentity::new("public", "orders")),
+          column_name: None,
+          predicate: "tenant_id = 1".to_owned(),
+        },
+        RowFilter {
+          table_id: None,
+          column_name: None,
+          predicate: "true".to_owned(),
+        },
+      ];
+
+      assert_eq!(
+        RowFilter::predicates(&row_filters, &create_table("orders", vec![])),
+        vec!["tenant_id = 1", "true"]
+      );
+      assert_eq!(
+        RowFilter::predicates(&row_filters, &create_table("users", vec![])),
+        vec!["true"]
+      );
+    }
+  }
+
+  mod fetch_row_input {
+    use super::*;
+
+    #[test]
+    fn it_should_quote_identifiers() {
+      let table_id = PsqlTableIdentity::new("public", "Order");
+      let limit = RowLimit {
+        max_rows: 2,
+        order_column_name: "created\"at".to_owned(),
+        descending: true,
+      };
+      let input = FetchRowInput {
+        table_id: &table_id,
+        column_name: "user",
+        column_value: &1,
+        limit: Some(&limit),
+        predicates: vec!["deleted_at IS NULL"],
+      };
+
+      assert_eq!(
+        input.select_query_str(),
+        r#"SELECT * FROM "public"."Order" where "user" = $1 AND (deleted_at IS NULL) ORDER BY "created""at" DESC LIMIT 2"#
+      );
+    }
   }
 }
@@ -0,0 +1,626 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use postgres_types::Type as PsqlType;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::DbDriver;
+use crate::psql::dto::*;
+use crate::psql::table_metadata::{OrphanRows, RowLimit, RowRelation, TableMetadata};
+use lezeh_common::types::ResultAnyError;
+
+/// Bump this whenever the snapshot structure changes
+const SNAPSHOT_VERSION: u32 = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct SnapshotValue {
+  /// None if the value is null
+  type_oid: Option<u32>,
+
+  /// Hex encoded postgres binary format of the value
+  raw: String,
+}
+
+type SnapshotRow = Vec<(String, SnapshotValue)>;
+
+impl SnapshotValue {
+  fn from_sink(sink: &FromSqlSink) -> SnapshotValue {
+    return SnapshotValue {
+      type_oid: sink.get_type().map(PsqlType::oid),
+      raw: sink
+        .get_raw()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect(),
+    };
+  }
+
+  fn into_sink(self) -> ResultAnyError<FromSqlSink> {
+    let type_oid = match self.type_oid {
+      None => return Ok(FromSqlSink::null()),
+      Some(type_oid) => type_oid,
+    };
+
+    // Custom types such as enums are not known by oid,
+    // their binary format is the same as text so it's safe to fall back to text.
+    let ty = PsqlType::from_oid(type_oid).unwrap_or(PsqlType::TEXT);
+
+    let raw = (0..self.raw.len())
+      .step_by(2)
+      .map(|index| {
+        return u8::from_str_radix(&self.raw[index..index + 2], 16)
+          .map_err(|err| anyhow!("Invalid snapshot value {}, error: {}", self.raw, err));
+      })
+      .collect::<ResultAnyError<Vec<u8>>>()?;
+
+    return Ok(FromSqlSink::from_raw(raw, ty));
+  }
+}
+
+/// Table structure and every row fetched during a cherry-pick session,
+/// it can be saved into a file then replayed without connecting to the db.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TableMetadataSnapshot {
+  version: u32,
+  pub driver: DbDriver,
+  pub schema: String,
+  tables: Vec<PsqlTable>,
+  column_by_key: HashMap<String, PsqlTableColumn>,
+  one_row_by_key: HashMap<String, SnapshotRow>,
+  rows_by_key: HashMap<String, Vec<SnapshotRow>>,
+
+  #[serde(default)]
+  row_count_by_key: HashMap<String, usize>,
+
+  #[serde(default)]
+  max_id_by_key: HashMap<String, Option<i64>>,
+}
+
+impl TableMetadataSnapshot {
+  pub fn new(
+    driver: DbDriver,
+    schema: &str,
+    psql_table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+  ) -> TableMetadataSnapshot {
+    return TableMetadataSnapshot {
+      version: SNAPSHOT_VERSION,
+      driver,
+      schema: schema.to_owned(),
+      tables: psql_table_by_id.values().cloned().collect(),
+      column_by_key: Default::default(),
+      one_row_by_key: Default::default(),
+      rows_by_key: Default::default(),
+      row_count_by_key: Default::default(),
+      max_id_by_key: Default::default(),
+    };
+  }
+
+  pub fn load(snapshot_file_path: &Path) -> ResultAnyError<TableMetadataSnapshot> {
+    let snapshot_str = fs::read_to_string(snapshot_file_path).map_err(|err| {
+      return anyhow!(
+        "Could not read snapshot {}, error: {}",
+        snapshot_file_path.display(),
+        err
+      );
+    })?;
+
+    let snapshot: TableMetadataSnapshot = serde_json::from_str(&snapshot_str)?;
+
+    if snapshot.version != SNAPSHOT_VERSION {
+      return Err(anyhow!(
+        "Snapshot version {} is not supported, expecting version {}",
+        snapshot.version,
+        SNAPSHOT_VERSION
+      ));
+    }
+
+    return Ok(snapshot);
+  }
+
+  pub fn save(&self, snapshot_file_path: &Path) -> ResultAnyError<()> {
+    fs::write(snapshot_file_path, serde_json::to_string(self)?)?;
+
+    return Ok(());
+  }
+
+  pub fn table_by_id(&self) -> HashMap<PsqlTableIdentity, PsqlTable> {
+    return self
+      .tables
+      .iter()
+      .map(|table| (table.id.clone(), table.clone()))
+      .collect();
+  }
+
+  /// Every recorded row, deduplicated by table and primary key
+  pub fn rows(&self) -> ResultAnyError<Vec<PsqlTableRow>> {
+    let table_by_key: HashMap<String, &PsqlTable> = self
+      .tables
+      .iter()
+      .map(|table| (table.id.to_string(), table))
+      .collect();
+    let mut row_by_key: BTreeMap<String, PsqlTableRow> = Default::default();
+
+    let snapshot_rows = self.one_row_by_key.iter().chain(
+      self
+        .rows_by_key
+        .iter()
+        .flat_map(|(key, rows)| rows.iter().map(move |row| (key, row))),
+    );
+
+    for (key, snapshot_row) in snapshot_rows {
+      // Every key starts with the table id, see the key functions below
+      let table_key = key.split(':').next().unwrap_or_default();
+      let table = table_by_key
+        .get(table_key)
+        .ok_or_else(|| anyhow!("Table of {} is not recorded in the snapshot", key))?;
+      let row = TableMetadataSnapshot::from_snapshot_row((*table).clone(), snapshot_row.clone())?;
+
+      row_by_key.entry(row.to_string()).or_insert(row);
+    }
+
+    return Ok(row_by_key.into_values().collect());
+  }
+}
+
+impl TableMetadataSnapshot {
+  fn column_key(table_id: &PsqlTableIdentity, column_name: &str) -> String {
+    return format!("{}:{}", table_id, column_name);
+  }
+
+  fn max_id_key(table_id: &PsqlTableIdentity, column_name: &str) -> String {
+    return format!("{}:max({})", table_id, column_name);
+  }
+
+  fn one_row_key(table_id: &PsqlTableIdentity, column_name: &str, id: &str) -> String {
+    return format!("{}:{}={}", table_id, column_name, id);
+  }
+
+  /// Rows are keyed by the text representation of the id so that
+  /// ids with different integer width still resolve to the same rows.
+  fn rows_key(
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+    id: &FromSqlSink,
+  ) -> ResultAnyError<String> {
+    return Ok(match id.to_text()? {
+      None => format!("{}:{} IS NULL", table_id, column_name),
+      Some(id) => format!("{}:{}={}", table_id, column_name, id),
+    });
+  }
+
+  fn limited_rows_key(
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+    id: &FromSqlSink,
+    limit: Option<&RowLimit>,
+  ) -> ResultAnyError<String> {
+    let key = TableMetadataSnapshot::rows_key(table_id, column_name, id)?;
+
+    return Ok(match limit {
+      None => key,
+      Some(limit) => format!("{} {}", key, limit),
+    });
+  }
+
+  fn into_snapshot_row(row: &PsqlTableRow) -> SnapshotRow {
+    return row
+      .get_values()
+      .iter()
+      .map(|(column_name, value)| (column_name.clone(), SnapshotValue::from_sink(value)))
+      .collect();
+  }
+
+  fn from_snapshot_row(table: PsqlTable, row: SnapshotRow) -> ResultAnyError<PsqlTableRow> {
+    let values = row
+      .into_iter()
+      .map(|(column_name, value)| {
+        return value.into_sink().map(|value| (column_name, value));
+      })
+      .collect::<ResultAnyError<PsqlRowValues>>()?;
+
+    return PsqlTableRow::new(table, values);
+  }
+}
+
+/// [TableMetadata] that records every result of the inner table metadata
+/// into the given snapshot.
+pub struct RecordingTableMetadata {
+  inner: Box<dyn TableMetadata>,
+
+  /// Shared with the caller so it can be saved after the fetch is done
+  snapshot: Rc<RefCell<TableMetadataSnapshot>>,
+}
+
+impl RecordingTableMetadata {
+  pub fn new(
+    inner: Box<dyn TableMetadata>,
+    snapshot: Rc<RefCell<TableMetadataSnapshot>>,
+  ) -> RecordingTableMetadata {
+    return RecordingTableMetadata { inner, snapshot };
+  }
+}
+
+impl TableMetadata for RecordingTableMetadata {
+  fn get_column(
+    &self,
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+  ) -> ResultAnyError<PsqlTableColumn> {
+    let column = self.inner.get_column(table_id, column_name)?;
+
+    self.snapshot.borrow_mut().column_by_key.insert(
+      TableMetadataSnapshot::column_key(table_id, column_name),
+      column.clone(),
+    );
+
+    return Ok(column);
+  }
+
+  fn get_rows(
+    &self,
+    table: PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+    limit: Option<&RowLimit>,
+  ) -> ResultAnyError<Vec<PsqlTableRow>> {
+    let key = TableMetadataSnapshot::limited_rows_key(&table.id, column_name, id, limit)?;
+    let rows = self.inner.get_rows(table, column_name, id, limit)?;
+
+    self.snapshot.borrow_mut().rows_by_key.insert(
+      key,
+      rows
+        .iter()
+        .map(TableMetadataSnapshot::into_snapshot_row)
+        .collect(),
+    );
+
+    return Ok(rows);
+  }
+
+  fn count_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+  ) -> ResultAnyError<usize> {
+    let key = TableMetadataSnapshot::rows_key(&table.id, column_name, id)?;
+    let row_count = self.inner.count_rows(table, column_name, id)?;
+
+    self
+      .snapshot
+      .borrow_mut()
+      .row_count_by_key
+      .insert(key, row_count);
+
+    return Ok(row_count);
+  }
+
+  fn get_max_id(&self, table: &PsqlTable, column_name: &str) -> ResultAnyError<Option<i64>> {
+    let max_id = self.inner.get_max_id(table, column_name)?;
+
+    self.snapshot.borrow_mut().max_id_by_key.insert(
+      TableMetadataSnapshot::max_id_key(&table.id, column_name),
+      max_id,
+    );
+
+    return Ok(max_id);
+  }
+
+  /// Integrity checks are not part of a cherry-pick session so they are not recorded
+  fn find_orphan_rows(
+    &self,
+    relation: &RowRelation,
+    sample_size: usize,
+  ) -> ResultAnyError<OrphanRows> {
+    return self.inner.find_orphan_rows(relation, sample_size);
+  }
+
+  fn get_one_row(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &str,
+  ) -> ResultAnyError<PsqlTableRow> {
+    let row = self.inner.get_one_row(table, column_name, id)?;
+
+    self.snapshot.borrow_mut().one_row_by_key.insert(
+      TableMetadataSnapshot::one_row_key(&table.id, column_name, id),
+      TableMetadataSnapshot::into_snapshot_row(&row),
+    );
+
+    return Ok(row);
+  }
+}
+
+/// [TableMetadata] that serves every result from a recorded snapshot,
+/// it fails when the requested data was not recorded.
+pub struct ReplayTableMetadata {
+  snapshot: TableMetadataSnapshot,
+}
+
+impl ReplayTableMetadata {
+  pub fn new(snapshot: TableMetadataSnapshot) -> ReplayTableMetadata {
+    return ReplayTableMetadata { snapshot };
+  }
+}
+
+impl TableMetadata for ReplayTableMetadata {
+  fn get_column(
+    &self,
+    table_id: &PsqlTableIdentity,
+    column_name: &str,
+  ) -> ResultAnyError<PsqlTableColumn> {
+    let key = TableMetadataSnapshot::column_key(table_id, column_name);
+
+    return self
+      .snapshot
+      .column_by_key
+      .get(&key)
+      .cloned()
+      .ok_or_else(|| anyhow!("Column {} is not recorded in the snapshot", key));
+  }
+
+  fn get_rows(
+    &self,
+    table: PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+    limit: Option<&RowLimit>,
+  ) -> ResultAnyError<Vec<PsqlTableRow>> {
+    let key = TableMetadataSnapshot::limited_rows_key(&table.id, column_name, id, limit)?;
+
+    return self
+      .snapshot
+      .rows_by_key
+      .get(&key)
+      .ok_or_else(|| anyhow!("Rows of {} are not recorded in the snapshot", key))?
+      .iter()
+      .map(|row| TableMetadataSnapshot::from_snapshot_row(table.clone(), row.clone()))
+      .collect();
+  }
+
+  fn count_rows(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &FromSqlSink,
+  ) -> ResultAnyError<usize> {
+    let key = TableMetadataSnapshot::rows_key(&table.id, column_name, id)?;
+
+    return self
+      .snapshot
+      .row_count_by_key
+      .get(&key)
+      .cloned()
+      .ok_or_else(|| anyhow!("Row count of {} is not recorded in the snapshot", key));
+  }
+
+  fn get_max_id(&self, table: &PsqlTable, column_name: &str) -> ResultAnyError<Option<i64>> {
+    let key = TableMetadataSnapshot::max_id_key(&table.id, column_name);
+
+    return self
+      .snapshot
+      .max_id_by_key
+      .get(&key)
+      .cloned()
+      .ok_or_else(|| anyhow!("Max id of {} is not recorded in the snapshot", key));
+  }
+
+  fn find_orphan_rows(
+    &self,
+    relation: &RowRelation,
+    _sample_size: usize,
+  ) -> ResultAnyError<OrphanRows> {
+    return Err(anyhow!(
+      "Orphan rows of {} are not recorded in the snapshot",
+      relation
+    ));
+  }
+
+  fn get_one_row(
+    &self,
+    table: &PsqlTable,
+    column_name: &str,
+    id: &str,
+  ) -> ResultAnyError<PsqlTableRow> {
+    let key = TableMetadataSnapshot::one_row_key(&table.id, column_name, id);
+
+    let row = self
+      .snapshot
+      .one_row_by_key
+      .get(&key)
+      .ok_or_else(|| anyhow!("Row {} is not recorded in the snapshot", key))?;
+
+    return TableMetadataSnapshot::from_snapshot_row(table.clone(), row.clone());
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::psql::relation_fetcher::{FetchRowsAsRoseTreeInput, RelationFetcher};
+  use crate::psql::table_metadata::MockTableMetadata;
+
+  fn create_table_by_id() -> HashMap<PsqlTableIdentity, PsqlTable> {
+    let users = PsqlTable::fixture("users")
+      .primary_column(PsqlTableColumn::new("id", "bigint"))
+      .build();
+
+    let orders = PsqlTable::fixture("orders")
+      .referencing_fks(vec![PsqlForeignKey::new(
+        "orders_user_id_fkey",
+        PsqlTableColumn::new("user_id", "bigint"),
+        "public",
+        "users",
+      )])
+      .build();
+
+    return HashMap::from([(users.id.clone(), users), (orders.id.clone(), orders)]);
+  }
+
+  fn create_row(table: &PsqlTable, values: Vec<(&str, FromSqlSink)>) -> PsqlTableRow {
+    return PsqlTableRow::new(
+      table.clone(),
+      values
+        .into_iter()
+        .map(|(column_name, value)| (column_name.to_owned(), value))
+        .collect(),
+    )
+    .unwrap();
+  }
+
+  fn create_recording_table_metadata(
+    table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+    snapshot: Rc<RefCell<TableMetadataSnapshot>>,
+  ) -> RecordingTableMetadata {
+    let orders = table_by_id[&PsqlTableIdentity::new("public", "orders")].clone();
+    let users = table_by_id[&PsqlTableIdentity::new("public", "users")].clone();
+    let mut table_metadata = MockTableMetadata::new();
+
+    table_metadata
+      .expect_get_one_row()
+      .returning(move |_, _, _| {
+        return Ok(create_row(
+          &orders,
+          vec![
+            (
+              "id",
+              FromSqlSink::from_value(&1_i32, PsqlType::INT4).unwrap(),
+            ),
+            (
+              "user_id",
+              FromSqlSink::from_value(&10_i64, PsqlType::INT8).unwrap(),
+            ),
+            ("note", FromSqlSink::null()),
+          ],
+        ));
+      });
+
+    table_metadata
+      .expect_get_rows()
+      .returning(move |_, _, _, _| {
+        return Ok(vec![create_row(
+          &users,
+          vec![
+            (
+              "id",
+              FromSqlSink::from_value(&10_i64, PsqlType::INT8).unwrap(),
+            ),
+            (
+              "name",
+              FromSqlSink::from_value(&"john", PsqlType::TEXT).unwrap(),
+            ),
+          ],
+        )]);
+      });
+
+    return RecordingTableMetadata::new(Box::new(table_metadata), snapshot);
+  }
+
+  fn fetch_graph(
+    table_metadata: Box<dyn TableMetadata>,
+    table_by_id: &HashMap<PsqlTableIdentity, PsqlTable>,
+  ) -> ResultAnyError<Vec<String>> {
+    let mut relation_fetcher = RelationFetcher::new(table_metadata, Default::default());
+    let (graph, _) = futures::executor::block_on(relation_fetcher.fetch_as_graphs(
+      FetchRowsAsRoseTreeInput {
+        table_id: &PsqlTableIdentity::new("public", "orders"),
+        column_name: "id",
+        column_value: "1",
+      },
+      table_by_id,
+    ))?;
+
+    let mut rows: Vec<String> = graph
+      .node_weights()
+      .map(|row| {
+        let mut values: Vec<String> = row
+          .get_column_value_map()
+          .into_iter()
+          .map(|(column_name, value)| {
+            return format!(
+              "{}={}",
+              column_name,
+              value.to_string_for_statement().unwrap()
+            );
+          })
+          .collect();
+
+        values.sort();
+
+        return format!("{} {}", row.table.id, values.join(","));
+      })
+      .collect();
+
+    rows.sort();
+
+    return Ok(rows);
+  }
+
+  mod replay_table_metadata {
+    use super::*;
+
+    #[test]
+    fn it_should_replay_recorded_session() -> ResultAnyError<()> {
+      let table_by_id = create_table_by_id();
+      let snapshot = Rc::new(RefCell::new(TableMetadataSnapshot::new(
+        DbDriver::Postgres,
+        "public",
+        &table_by_id,
+      )));
+
+      let recorded_rows = fetch_graph(
+        Box::new(create_recording_table_metadata(
+          &table_by_id,
+          snapshot.clone(),
+        )),
+        &table_by_id,
+      )?;
+
+      let snapshot_file_path = std::env::temp_dir().join(format!(
+        "lezeh-db-snapshot-test-{}.json",
+        std::process::id()
+      ));
+
+      snapshot.borrow().save(&snapshot_file_path)?;
+
+      let loaded_snapshot = TableMetadataSnapshot::load(&snapshot_file_path)?;
+      let _ = fs::remove_file(&snapshot_file_path);
+
+      assert_eq!(loaded_snapshot, *snapshot.borrow());
+      assert_eq!(loaded_snapshot.table_by_id(), table_by_id);
+
+      let replayed_rows = fetch_graph(
+        Box::new(ReplayTableMetadata::new(loaded_snapshot)),
+        &table_by_id,
+      )?;
+
+      assert_eq!(
+        replayed_rows,
+        vec![
+          "public.orders id=1,note=null,user_id=10",
+          "public.users id=10,name='john'",
+        ]
+      );
+      assert_eq!(replayed_rows, recorded_rows);
+
+      return Ok(());
+    }
+
+    #[test]
+    fn it_should_fail_when_row_is_not_recorded() {
+      let table_by_id = create_table_by_id();
+      let snapshot = TableMetadataSnapshot::new(DbDriver::Postgres, "public", &table_by_id);
+
+      let result = fetch_graph(Box::new(ReplayTableMetadata::new(snapshot)), &table_by_id);
+
+      assert!(result.is_err());
+    }
+  }
+}